@@ -0,0 +1,462 @@
+use std::ops::Range;
+
+use ropey::Rope;
+use ropey::iter::Chars;
+
+use super::Buffer;
+use crate::filetype::{self, Syntax};
+
+/// Where the scanner is in the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Code,
+    LineComment,
+    BlockComment,
+    Str(char),
+}
+
+/// Walks the rope yielding `(char index, char, is_code)`, where `is_code` is
+/// false inside comments and string or char literals.
+pub(super) struct CodeChars<'a> {
+    chars: Chars<'a>,
+    idx: usize,
+    state: State,
+    syntax: Syntax,
+    /// Characters still to be yielded as part of a comment or literal
+    /// delimiter, without looking for state changes inside them.
+    opaque: usize,
+}
+
+/// What the scanner knows at the start of a line, enough to pick up
+/// scanning there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Checkpoint {
+    state: State,
+    opaque: usize,
+}
+
+const START: Checkpoint = Checkpoint {
+    state: State::Code,
+    opaque: 0,
+};
+
+/// The scanner's checkpoint at the start of each line, filled in as far as
+/// it has been needed. An edit only invalidates the lines after it.
+#[derive(Debug, Clone, Default)]
+pub(super) struct LineStates(Vec<Checkpoint>);
+
+impl LineStates {
+    /// Forget the lines after `line`, whose start may have changed.
+    pub(super) fn invalidate_after(&mut self, line: usize) {
+        self.0.truncate(line + 1);
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+impl<'a> CodeChars<'a> {
+    fn new(rope: &'a Rope, syntax: Syntax, idx: usize, at: Checkpoint) -> Self {
+        Self {
+            chars: rope.chars_at(idx),
+            idx,
+            state: at.state,
+            syntax,
+            opaque: at.opaque,
+        }
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            state: self.state,
+            opaque: self.opaque,
+        }
+    }
+
+    /// Whether the text starting at the character just yielded by `next`
+    /// (`first`) begins with `token`.
+    fn starts_with(&self, first: char, token: &str) -> bool {
+        let mut token = token.chars();
+        if token.next() != Some(first) {
+            return false;
+        }
+        let mut rest = self.chars.clone();
+        token.all(|t| rest.next() == Some(t))
+    }
+
+    /// The length of a Rust char literal starting with the `'` just yielded,
+    /// or None if it's a lifetime.
+    fn char_literal_len(&self) -> Option<usize> {
+        let mut rest = self.chars.clone();
+        match rest.next()? {
+            '\\' => {
+                // '\n', '\'', '\u{1F600}': find the closing quote, and
+                // count it too.
+                rest.next()?;
+                (3..12)
+                    .find(|_| rest.next() == Some('\''))
+                    .map(|quote| quote + 1)
+            }
+            '\'' => None,
+            _ => (rest.next() == Some('\'')).then_some(3),
+        }
+    }
+}
+
+impl Iterator for CodeChars<'_> {
+    type Item = (usize, char, bool);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let c = self.chars.next()?;
+        let idx = self.idx;
+        self.idx += 1;
+
+        if self.opaque > 0 {
+            self.opaque -= 1;
+            return Some((idx, c, false));
+        }
+
+        let code = match self.state {
+            State::Code => {
+                if let Some(prefix) = self.syntax.line_comment
+                    && self.starts_with(c, prefix)
+                {
+                    self.state = State::LineComment;
+                    false
+                } else if let Some((open, _)) = self.syntax.block_comment
+                    && self.starts_with(c, open)
+                {
+                    self.state = State::BlockComment;
+                    self.opaque = open.chars().count() - 1;
+                    false
+                } else if self.syntax.quotes.contains(&c) {
+                    self.state = State::Str(c);
+                    false
+                } else if c == '\'' && self.syntax.char_literals {
+                    match self.char_literal_len() {
+                        Some(len) => {
+                            self.opaque = len - 1;
+                            false
+                        }
+                        None => true,
+                    }
+                } else {
+                    true
+                }
+            }
+            State::LineComment => {
+                if c == '\n' {
+                    self.state = State::Code;
+                    true
+                } else {
+                    false
+                }
+            }
+            State::BlockComment => {
+                if let Some((_, close)) = self.syntax.block_comment
+                    && self.starts_with(c, close)
+                {
+                    self.state = State::Code;
+                    self.opaque = close.chars().count() - 1;
+                }
+                false
+            }
+            State::Str(quote) => {
+                if c == '\\' {
+                    self.opaque = 1;
+                } else if c == quote {
+                    self.state = State::Code;
+                }
+                false
+            }
+        };
+        Some((idx, c, code))
+    }
+}
+
+/// For a bracket character, its (open, close) pair and whether it opens.
+pub(super) fn bracket_pair(c: char) -> Option<(char, char, bool)> {
+    match c {
+        '(' => Some(('(', ')', true)),
+        ')' => Some(('(', ')', false)),
+        '[' => Some(('[', ']', true)),
+        ']' => Some(('[', ']', false)),
+        '{' => Some(('{', '}', true)),
+        '}' => Some(('{', '}', false)),
+        _ => None,
+    }
+}
+
+pub fn is_bracket(c: char) -> bool {
+    bracket_pair(c).is_some()
+}
+
+impl Buffer {
+    pub(super) fn code_chars(&self) -> CodeChars<'_> {
+        self.code_chars_from(0)
+    }
+
+    /// Scan from the start of `line`, resuming from its checkpoint rather
+    /// than from the top of the buffer.
+    fn code_chars_from(&self, line: usize) -> CodeChars<'_> {
+        let syntax = filetype::syntax(&self.filetype);
+        let line = line.min(self.rope.len_lines() - 1);
+        let mut states = self.line_states.borrow_mut();
+        if states.0.is_empty() {
+            states.0.push(START);
+        }
+        if states.0.len() <= line {
+            let known = states.0.len() - 1;
+            let start = self.rope.line_to_char(known);
+            let mut chars = CodeChars::new(&self.rope, syntax, start, states.0[known]);
+            while states.0.len() <= line {
+                let Some((_, c, _)) = chars.next() else {
+                    break;
+                };
+                if c == '\n' {
+                    states.0.push(chars.checkpoint());
+                }
+            }
+        }
+        let at = states.0[line.min(states.0.len() - 1)];
+        CodeChars::new(&self.rope, syntax, self.rope.line_to_char(line), at)
+    }
+
+    /// Whether the character at `idx` is code rather than part of a comment
+    /// or literal.
+    fn is_code_at(&self, idx: usize) -> Option<bool> {
+        self.code_chars_from(self.rope.try_char_to_line(idx).ok()?)
+            .find(|&(i, _, _)| i == idx)
+            .map(|(_, _, code)| code)
+    }
+
+    /// The char index of the bracket matching the one at `idx` (vim `%`).
+    /// Brackets inside comments and string or char literals are skipped,
+    /// unless the starting bracket is itself in one.
+    pub fn matching_bracket(&self, idx: usize) -> Option<usize> {
+        self.matching_bracket_in(idx, 0..self.line_count())
+    }
+
+    /// Like `matching_bracket`, looking only in `lines`. Highlighting the
+    /// match only needs the lines on screen (vim's matchparen).
+    pub fn matching_bracket_in(&self, idx: usize, lines: Range<usize>) -> Option<usize> {
+        let (open, close, forward) = bracket_pair(self.char_at(idx)?)?;
+        let code = self.is_code_at(idx)?;
+        if forward {
+            self.scan_forward(idx, (open, close), code, 1, lines.end)
+        } else {
+            self.scan_backward(idx, (open, close), code, 1, lines.start)
+        }
+    }
+
+    /// The [count]th unmatched `target` bracket around `idx` (vim `[(`,
+    /// `[{`, `])`, `]}`). Searches backward for an opening bracket and
+    /// forward for a closing one.
+    pub fn unmatched_bracket(&self, idx: usize, target: char, count: usize) -> Option<usize> {
+        let (open, close, opens) = bracket_pair(target)?;
+        if opens {
+            self.scan_backward(idx, (open, close), true, count, 0)
+        } else {
+            self.scan_forward(idx, (open, close), true, count, self.line_count())
+        }
+    }
+
+    /// The positions of the [count]th `open`/`close` pair around `idx`,
+    /// for bracket text objects. A bracket under `idx` is the first level.
+    /// Unlike `%`, any pair of characters works, so `<>` can be used too.
+    pub fn enclosing_pair(
+        &self,
+        idx: usize,
+        (open, close): (char, char),
+        count: usize,
+    ) -> Option<(usize, usize)> {
+        let code = self.is_code_at(idx)?;
+        let outer = count.max(1) - 1;
+        let c = self.char_at(idx)?;
+        let start = if c == open || c == close {
+            let first = if c == open {
+                idx
+            } else {
+                self.scan_backward(idx, (open, close), code, 1, 0)?
+            };
+            match outer {
+                0 => first,
+                n => self.scan_backward(first, (open, close), code, n, 0)?,
+            }
+        } else {
+            self.scan_backward(idx, (open, close), code, count, 0)?
+        };
+        let end = self.scan_forward(start, (open, close), code, 1, self.line_count())?;
+        Some((start, end))
+    }
+
+    /// Walk forward from after `idx` to the [count]th `close` that has no
+    /// `open` between it and `idx`, considering only brackets whose
+    /// code-ness is `code`, and stopping before line `end_line`.
+    fn scan_forward(
+        &self,
+        idx: usize,
+        (open, close): (char, char),
+        code: bool,
+        count: usize,
+        end_line: usize,
+    ) -> Option<usize> {
+        let end = self.rope.line_to_char(end_line.min(self.line_count()));
+        let mut remaining = count.max(1);
+        let mut depth = 0usize;
+        for (i, c, _) in self
+            .code_chars_from(self.rope.char_to_line(idx))
+            .skip_while(|&(i, _, _)| i <= idx)
+            .take_while(|&(i, _, _)| i < end)
+            .filter(|&(_, c, is_code)| is_code == code && (c == open || c == close))
+        {
+            if c == open {
+                depth += 1;
+            } else if depth > 0 {
+                depth -= 1;
+            } else {
+                remaining -= 1;
+                if remaining == 0 {
+                    return Some(i);
+                }
+            }
+        }
+        None
+    }
+
+    /// Like `scan_forward`, walking backward to an unmatched `open` no
+    /// earlier than line `start_line`. Lexing only goes forward, so the
+    /// brackets from `start_line` up to `idx` are collected and then walked
+    /// in reverse.
+    fn scan_backward(
+        &self,
+        idx: usize,
+        (open, close): (char, char),
+        code: bool,
+        count: usize,
+        start_line: usize,
+    ) -> Option<usize> {
+        let before: Vec<(usize, char)> = self
+            .code_chars_from(start_line)
+            .take_while(|&(i, _, _)| i < idx)
+            .filter(|&(_, c, is_code)| is_code == code && (c == open || c == close))
+            .map(|(i, c, _)| (i, c))
+            .collect();
+        let mut remaining = count.max(1);
+        let mut depth = 0usize;
+        for &(i, c) in before.iter().rev() {
+            if c == close {
+                depth += 1;
+            } else if depth > 0 {
+                depth -= 1;
+            } else {
+                remaining -= 1;
+                if remaining == 0 {
+                    return Some(i);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::buffer_with_suffix;
+
+    #[test]
+    fn matches_nested_brackets_both_ways() {
+        let buf = buffer_with_suffix("f(a, (b), [c])\n", ".rs");
+        assert_eq!(buf.matching_bracket(1), Some(13));
+        assert_eq!(buf.matching_bracket(13), Some(1));
+        assert_eq!(buf.matching_bracket(5), Some(7));
+        assert_eq!(buf.matching_bracket(10), Some(12));
+    }
+
+    #[test]
+    fn matches_across_lines() {
+        let buf = buffer_with_suffix("fn f() {\n    x;\n}\n", ".rs");
+        assert_eq!(buf.matching_bracket(7), Some(16));
+        assert_eq!(buf.matching_bracket(16), Some(7));
+    }
+
+    #[test]
+    fn skips_brackets_in_strings_and_comments() {
+        let buf = buffer_with_suffix("f(\")\", // )\n  ')', /* ) */ x)\n", ".rs");
+        let close = buf.char_index(1, 16);
+        assert_eq!(buf.char_at(close), Some(')'));
+        assert_eq!(buf.matching_bracket(1), Some(close));
+        assert_eq!(buf.matching_bracket(close), Some(1));
+    }
+
+    #[test]
+    fn lifetimes_are_not_char_literals() {
+        let buf = buffer_with_suffix("fn f<'a>(x: &'a str) {}\n", ".rs");
+        assert_eq!(buf.matching_bracket(8), Some(19));
+    }
+
+    #[test]
+    fn escaped_char_literals_end_at_their_quote() {
+        let buf = buffer_with_suffix("fn f() { g('\\t','{'); }\n", ".rs");
+        assert_eq!(buf.matching_bracket(7), Some(22));
+        let buf = buffer_with_suffix("('\\'', ')')\n", ".rs");
+        assert_eq!(buf.matching_bracket(0), Some(10));
+    }
+
+    #[test]
+    fn matching_resumes_inside_comments_after_edits() {
+        let mut buf = buffer_with_suffix("(\n/*\n)\n*/\n)\n", ".rs");
+        assert_eq!(buf.matching_bracket(0), Some(10));
+        assert_eq!(buf.matching_bracket_in(0, 0..4), None);
+        buf.delete_line(1);
+        assert_eq!(buf.matching_bracket(0), Some(2));
+    }
+
+    #[test]
+    fn escaped_quote_in_string() {
+        let buf = buffer_with_suffix("(\"\\\")\")\n", ".rs");
+        assert_eq!(buf.matching_bracket(0), Some(6));
+    }
+
+    #[test]
+    fn bracket_inside_string_matches_within_strings() {
+        let buf = buffer_with_suffix("x(\"(\" \")\")\n", ".rs");
+        assert_eq!(buf.matching_bracket(3), Some(7));
+    }
+
+    #[test]
+    fn plain_text_has_no_strings() {
+        let buf = buffer_with_suffix("don't (stop)\n", ".txt");
+        assert_eq!(buf.matching_bracket(6), Some(11));
+    }
+
+    #[test]
+    fn unmatched_bracket_backward_and_forward() {
+        let buf = buffer_with_suffix("{ a(b, (c), d) }\n", ".rs");
+        // Cursor on `d`.
+        assert_eq!(buf.unmatched_bracket(12, '(', 1), Some(3));
+        assert_eq!(buf.unmatched_bracket(12, ')', 1), Some(13));
+        assert_eq!(buf.unmatched_bracket(12, '{', 1), Some(0));
+        assert_eq!(buf.unmatched_bracket(12, '}', 1), Some(15));
+        assert_eq!(buf.unmatched_bracket(12, '[', 1), None);
+    }
+
+    #[test]
+    fn enclosing_pair_levels() {
+        let buf = buffer_with_suffix("(a <b> (c))\n", ".rs");
+        assert_eq!(buf.enclosing_pair(8, ('(', ')'), 1), Some((7, 9)));
+        assert_eq!(buf.enclosing_pair(8, ('(', ')'), 2), Some((0, 10)));
+        assert_eq!(buf.enclosing_pair(7, ('(', ')'), 1), Some((7, 9)));
+        assert_eq!(buf.enclosing_pair(9, ('(', ')'), 2), Some((0, 10)));
+        assert_eq!(buf.enclosing_pair(4, ('<', '>'), 1), Some((3, 5)));
+        assert_eq!(buf.enclosing_pair(1, ('[', ']'), 1), None);
+    }
+
+    #[test]
+    fn unmatched_bracket_with_count() {
+        let buf = buffer_with_suffix("((x))\n", ".rs");
+        assert_eq!(buf.unmatched_bracket(2, '(', 2), Some(0));
+        assert_eq!(buf.unmatched_bracket(2, ')', 2), Some(4));
+    }
+}
@@ -0,0 +1,180 @@
+use super::{Buffer, indenter};
+use crate::error::DvimError;
+use crate::options::MAX_NUMBER;
+
+impl Buffer {
+    pub fn shiftwidth(&self) -> usize {
+        self.shiftwidth
+    }
+
+    pub fn set_shiftwidth(&mut self, value: usize) -> Result<(), DvimError> {
+        self.shiftwidth = in_range("shiftwidth", value)?;
+        Ok(())
+    }
+
+    pub fn tabstop(&self) -> usize {
+        self.tabstop
+    }
+
+    pub fn set_tabstop(&mut self, value: usize) -> Result<(), DvimError> {
+        self.tabstop = in_range("tabstop", value)?;
+        Ok(())
+    }
+
+    pub fn expandtab(&self) -> bool {
+        self.expandtab
+    }
+
+    pub fn set_expandtab(&mut self, value: bool) {
+        self.expandtab = value;
+    }
+
+    pub fn autoindent(&self) -> bool {
+        self.autoindent
+    }
+
+    pub fn set_autoindent(&mut self, value: bool) {
+        self.autoindent = value;
+    }
+
+    pub fn smartindent(&self) -> bool {
+        self.smartindent
+    }
+
+    pub fn set_smartindent(&mut self, value: bool) {
+        self.smartindent = value;
+    }
+
+    /// The name of the indenter in use ('indentexpr'), empty for none.
+    pub fn indentexpr(&self) -> &str {
+        &self.indentexpr
+    }
+
+    /// Pick the indenter by name, rejecting names that aren't built in.
+    pub fn set_indentexpr(&mut self, name: &str) -> Result<(), DvimError> {
+        if !name.is_empty() && indenter::by_name(name).is_none() {
+            return Err(DvimError::InvalidOption {
+                name: "indentexpr".to_string(),
+                value: name.to_string(),
+            });
+        }
+        self.indentexpr = name.to_string();
+        Ok(())
+    }
+
+    /// Whether `row` is empty or only whitespace.
+    pub fn is_blank(&self, row: usize) -> bool {
+        self.line(row)
+            .is_none_or(|l| l.chars().all(char::is_whitespace))
+    }
+
+    /// The width of `row`'s leading whitespace, with each tab reaching the
+    /// next tabstop.
+    pub fn indent_width(&self, row: usize) -> usize {
+        let line = self.line(row).unwrap_or_default();
+        let mut width = 0;
+        for c in line.chars() {
+            match c {
+                '\t' => width += self.tabstop - width % self.tabstop,
+                ' ' => width += 1,
+                _ => break,
+            }
+        }
+        width
+    }
+
+    /// Leading whitespace `width` columns wide: as many tabs as fit and then
+    /// spaces, or only spaces with 'expandtab'.
+    pub fn indent_string(&self, width: usize) -> String {
+        if self.expandtab {
+            " ".repeat(width)
+        } else {
+            "\t".repeat(width / self.tabstop) + &" ".repeat(width % self.tabstop)
+        }
+    }
+
+    /// Replace `row`'s leading whitespace with `width` columns of indent.
+    pub fn set_indent(&mut self, row: usize, width: usize) {
+        self.set_indents(row, &[width]);
+    }
+
+    /// Give the rows from `first` on the indent `widths`, one for each, as
+    /// a single change.
+    pub fn set_indents(&mut self, first: usize, widths: &[usize]) {
+        let mut pieces = Vec::new();
+        for (row, &width) in (first..).zip(widths) {
+            let Some(line) = self.line(row) else {
+                break;
+            };
+            let old = line.chars().take_while(|c| *c == ' ' || *c == '\t');
+            let old: String = old.collect();
+            let new = self.indent_string(width);
+            if old != new {
+                let start = self.rope.line_to_char(row);
+                pieces.push((start..start + old.chars().count(), new));
+            }
+        }
+        self.replace_pieces(pieces);
+    }
+}
+
+fn in_range(name: &str, value: usize) -> Result<usize, DvimError> {
+    if !(1..=MAX_NUMBER).contains(&value) {
+        return Err(DvimError::InvalidOption {
+            name: name.to_string(),
+            value: value.to_string(),
+        });
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::buffer_with_suffix;
+    use crate::options::MAX_NUMBER;
+
+    #[test]
+    fn indent_width_counts_tabs_to_tabstop() {
+        let buf = buffer_with_suffix("\t  x\n  \tx\n", ".txt");
+        assert_eq!(buf.indent_width(0), 10);
+        assert_eq!(buf.indent_width(1), 8);
+    }
+
+    #[test]
+    fn set_indent_with_and_without_tabs() {
+        let mut buf = buffer_with_suffix("x\n", ".go");
+        buf.set_indent(0, 10);
+        assert_eq!(buf.line(0).unwrap(), "\t  x");
+        buf.set_expandtab(true);
+        buf.set_indent(0, 3);
+        assert_eq!(buf.line(0).unwrap(), "   x");
+    }
+
+    #[test]
+    fn indenting_a_range_is_one_change() {
+        let mut buf = buffer_with_suffix("a\n\tb\n  c\n", ".txt");
+        buf.set_expandtab(true);
+        let tick = buf.changedtick();
+        buf.set_indents(0, &[2, 2, 2]);
+        assert_eq!(buf.contents(), "  a\n  b\n  c\n");
+        assert_eq!(buf.changedtick(), tick + 1);
+        assert_eq!(buf.marks().changes().len(), 1);
+        buf.set_indents(0, &[2, 2]);
+        assert_eq!(buf.changedtick(), tick + 1);
+    }
+
+    #[test]
+    fn zero_shiftwidth_is_rejected() {
+        let mut buf = buffer_with_suffix("x\n", ".txt");
+        assert!(buf.set_shiftwidth(0).is_err());
+        assert_eq!(buf.shiftwidth(), 4);
+    }
+
+    #[test]
+    fn huge_shiftwidth_and_tabstop_are_rejected() {
+        let mut buf = buffer_with_suffix("x\n", ".txt");
+        assert!(buf.set_shiftwidth(usize::MAX).is_err());
+        assert!(buf.set_tabstop(MAX_NUMBER + 1).is_err());
+        assert_eq!((buf.shiftwidth(), buf.tabstop()), (4, 8));
+    }
+}
@@ -0,0 +1,312 @@
+//! Indenters work out how far a line should be indented, for new lines in
+//! insert mode and for `=`. A buffer picks one by name with 'indentexpr'
+//! (set per filetype), falling back on 'smartindent' and 'autoindent'.
+//! Anything that can judge indentation, such as a tree-sitter indent
+//! query, can plug in by implementing [`Indenter`].
+
+use super::Buffer;
+use super::brackets::bracket_pair;
+
+pub trait Indenter {
+    /// The indent width `row` should have, judging by the lines above it
+    /// and what's already on it.
+    fn indent(&self, buffer: &Buffer, row: usize) -> usize;
+
+    /// Whether typing `c` as the first thing on a line should reindent it
+    /// (vim's 'indentkeys').
+    fn reindents_on(&self, _c: char) -> bool {
+        false
+    }
+
+    /// Reindent rows `first..=last`, emptying blank ones.
+    fn reindent(&self, buffer: &mut Buffer, first: usize, last: usize) {
+        for row in first..=last {
+            let width = if buffer.is_blank(row) {
+                0
+            } else {
+                self.indent(buffer, row)
+            };
+            buffer.set_indent(row, width);
+        }
+    }
+}
+
+/// The indenter called `name`, as given to 'indentexpr'.
+pub fn by_name(name: &str) -> Option<&'static dyn Indenter> {
+    match name {
+        "auto" => Some(&AutoIndent),
+        "smart" => Some(&SmartIndent),
+        "brackets" => Some(&BracketIndent),
+        _ => None,
+    }
+}
+
+/// The indent of the nearest non-blank line above `row`.
+fn previous_indent(buffer: &Buffer, row: usize) -> Option<(usize, usize)> {
+    let above = (0..row).rev().find(|&r| !buffer.is_blank(r))?;
+    Some((above, buffer.indent_width(above)))
+}
+
+/// Copy the indent of the line above ('autoindent').
+pub struct AutoIndent;
+
+impl Indenter for AutoIndent {
+    fn indent(&self, buffer: &Buffer, row: usize) -> usize {
+        previous_indent(buffer, row).map_or(0, |(_, width)| width)
+    }
+}
+
+/// Like [`AutoIndent`], plus a level after a line ending in `{` or `:`, and
+/// one less on a line starting with `}` ('smartindent').
+pub struct SmartIndent;
+
+impl Indenter for SmartIndent {
+    fn indent(&self, buffer: &Buffer, row: usize) -> usize {
+        let Some((above, mut width)) = previous_indent(buffer, row) else {
+            return 0;
+        };
+        let sw = buffer.shiftwidth();
+        let above = buffer.line(above).unwrap_or_default();
+        if above.trim_end().ends_with(['{', ':']) {
+            width += sw;
+        }
+        let line = buffer.line(row).unwrap_or_default();
+        if line.trim_start().starts_with('}') {
+            width = width.saturating_sub(sw);
+        }
+        width
+    }
+
+    fn reindents_on(&self, c: char) -> bool {
+        c == '}'
+    }
+}
+
+/// C-like indenting: a level for every line inside open brackets, and
+/// lines starting with a closing bracket lined up with the line that opened
+/// it. Brackets in comments and strings don't count.
+pub struct BracketIndent;
+
+impl Indenter for BracketIndent {
+    fn indent(&self, buffer: &Buffer, row: usize) -> usize {
+        let lines = line_brackets(buffer, row);
+        let mut open: Vec<usize> = Vec::new();
+        for (r, line) in lines.iter().enumerate().take(row) {
+            line.apply(&mut open, buffer.indent_width(r));
+        }
+        // `row` counts as non-blank, so a line about to be typed on gets
+        // the indent it will need.
+        let line = LineBrackets {
+            blank: false,
+            continued: lines.get(row).is_some_and(|l| l.continued),
+            leading_close: lines.get(row).is_some_and(|l| l.leading_close),
+            brackets: Vec::new(),
+        };
+        line_indent(buffer, row, &line, &open)
+    }
+
+    fn reindents_on(&self, c: char) -> bool {
+        matches!(c, '}' | ')' | ']')
+    }
+
+    fn reindent(&self, buffer: &mut Buffer, first: usize, last: usize) {
+        // One pass over the buffer, rather than one per line.
+        let lines = line_brackets(buffer, last);
+        let mut open: Vec<usize> = Vec::new();
+        for (row, line) in lines.iter().enumerate() {
+            let width = if row < first {
+                buffer.indent_width(row)
+            } else {
+                let width = line_indent(buffer, row, line, &open);
+                buffer.set_indent(row, width);
+                width
+            };
+            line.apply(&mut open, width);
+        }
+    }
+}
+
+/// What bracket indenting needs to know about one line.
+#[derive(Debug, Default)]
+struct LineBrackets {
+    blank: bool,
+    /// The line starts inside a block comment or multi-line string.
+    continued: bool,
+    /// The first non-blank character is a closing bracket.
+    leading_close: bool,
+    /// The brackets in code on the line, true for opening ones.
+    brackets: Vec<bool>,
+}
+
+impl LineBrackets {
+    /// Update the stack of open brackets (each holding the indent of the
+    /// line it's on) for this line's brackets, the line being `width` wide.
+    fn apply(&self, open: &mut Vec<usize>, width: usize) {
+        for &opens in &self.brackets {
+            if opens {
+                open.push(width);
+            } else {
+                open.pop();
+            }
+        }
+    }
+}
+
+/// The indent `row` should have with `open` brackets before it.
+fn line_indent(buffer: &Buffer, row: usize, line: &LineBrackets, open: &[usize]) -> usize {
+    let expected = open.last().map_or(0, |w| w + buffer.shiftwidth());
+    if line.blank {
+        0
+    } else if line.continued {
+        // Line up ` * ` in block comments; leave strings be.
+        let text = buffer.line(row).unwrap_or_default();
+        if text.trim_start().starts_with('*') {
+            expected + 1
+        } else {
+            buffer.indent_width(row)
+        }
+    } else if line.leading_close {
+        open.last().copied().unwrap_or(0)
+    } else {
+        expected
+    }
+}
+
+/// Scan rows `0..=last` for the brackets that drive indenting.
+fn line_brackets(buffer: &Buffer, last: usize) -> Vec<LineBrackets> {
+    let mut lines = vec![LineBrackets {
+        blank: true,
+        ..LineBrackets::default()
+    }];
+    for (_, c, code) in buffer.code_chars() {
+        let line = lines.last_mut().expect("at least one line");
+        if c == '\n' {
+            if lines.len() > last {
+                break;
+            }
+            lines.push(LineBrackets {
+                blank: true,
+                continued: !code,
+                ..LineBrackets::default()
+            });
+            continue;
+        }
+        let pair = bracket_pair(c).filter(|_| code);
+        if line.blank && !c.is_whitespace() {
+            line.blank = false;
+            line.leading_close = pair.is_some_and(|(_, _, opens)| !opens);
+        }
+        if let Some((_, _, opens)) = pair {
+            line.brackets.push(opens);
+        }
+    }
+    lines
+}
+
+impl Buffer {
+    /// The indenter for new lines: the 'indentexpr' one, else 'smartindent'
+    /// or 'autoindent' if set.
+    pub fn indenter(&self) -> Option<&'static dyn Indenter> {
+        by_name(&self.indentexpr)
+            .or_else(|| self.smartindent.then_some(&SmartIndent as &dyn Indenter))
+            .or_else(|| self.autoindent.then_some(&AutoIndent as &dyn Indenter))
+    }
+
+    /// Reindent rows `first..=last` (`=`) with the buffer's indenter, or by
+    /// copying the line above when it has none. Indenters work line by
+    /// line, so the new indents are worked out on a copy and then made in
+    /// one change.
+    pub fn reindent(&mut self, first: usize, last: usize) {
+        let indenter = self.indenter().unwrap_or(&AutoIndent);
+        let mut copy = self.clone();
+        indenter.reindent(&mut copy, first, last);
+        let widths: Vec<usize> = (first..=last).map(|row| copy.indent_width(row)).collect();
+        self.set_indents(first, &widths);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Buffer;
+    use super::super::tests::buffer_with_suffix;
+
+    fn lines(buf: &Buffer) -> Vec<String> {
+        (0..buf.line_count() - 1)
+            .filter_map(|r| buf.line(r))
+            .collect()
+    }
+
+    #[test]
+    fn reindent_by_brackets() {
+        let mut buf = buffer_with_suffix(
+            "fn f() {\nif x {\ny(a,\nb);\n} else {\n// {\nz\n}\n\n}\n",
+            ".rs",
+        );
+        buf.reindent(0, 9);
+        assert_eq!(
+            lines(&buf),
+            [
+                "fn f() {",
+                "    if x {",
+                "        y(a,",
+                "            b);",
+                "    } else {",
+                "        // {",
+                "        z",
+                "    }",
+                "",
+                "}",
+            ]
+        );
+    }
+
+    #[test]
+    fn reindent_lines_up_block_comments() {
+        let mut buf = buffer_with_suffix("int f() {\n/*\n* x\n*/\n}\n", ".c");
+        buf.reindent(0, 4);
+        assert_eq!(
+            lines(&buf),
+            ["int f() {", "    /*", "     * x", "     */", "}"]
+        );
+    }
+
+    #[test]
+    fn reindent_without_indentexpr_follows_line_above() {
+        let mut buf = buffer_with_suffix("  a\nb\n\n   c\n", ".txt");
+        buf.reindent(1, 3);
+        assert_eq!(lines(&buf), ["  a", "  b", "", "  c"]);
+    }
+
+    #[test]
+    fn bracket_indent_for_a_new_line() {
+        let buf = buffer_with_suffix("fn f() {\n    g(\n\n", ".rs");
+        let indenter = buf.indenter().unwrap();
+        assert_eq!(indenter.indent(&buf, 2), 8);
+        assert!(indenter.reindents_on('}'));
+    }
+
+    #[test]
+    fn smart_indent_after_colon() {
+        let buf = buffer_with_suffix("def f():\n\n", ".py");
+        assert_eq!(buf.indenter().unwrap().indent(&buf, 1), 4);
+    }
+
+    #[test]
+    fn indenter_falls_back_on_options() {
+        let mut buf = buffer_with_suffix("x\n", ".txt");
+        assert!(buf.indenter().is_some());
+        buf.set_autoindent(false);
+        assert!(buf.indenter().is_none());
+        buf.set_smartindent(true);
+        assert!(buf.indenter().is_some());
+    }
+
+    #[test]
+    fn reindent_is_one_change() {
+        let mut buf = buffer_with_suffix("fn f() {\nx;\ny;\n}\n", ".rs");
+        let tick = buf.changedtick();
+        buf.reindent(0, 3);
+        assert_eq!(lines(&buf), ["fn f() {", "    x;", "    y;", "}"]);
+        assert_eq!(buf.changedtick(), tick + 1);
+    }
+}
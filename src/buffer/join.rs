@@ -0,0 +1,119 @@
+use super::Buffer;
+use crate::filetype;
+
+impl Buffer {
+    /// Join rows `first..=last` into one, as a single change (`J`). Each
+    /// joined line's indent is replaced by a single space. With `spaces`
+    /// false (`gJ`) the indent is kept and no space is added. Either way, a
+    /// comment leader continuing the comment above is dropped. Returns the
+    /// column where the last two lines met, or None if `first` is the last
+    /// line.
+    pub fn join_rows(&mut self, first: usize, last: usize, spaces: bool) -> Option<usize> {
+        let mut cur = self.line(first)?;
+        let mut pieces = Vec::new();
+        let mut join_col = None;
+        for row in first + 1..=last {
+            if row >= self.rope.len_lines() {
+                break;
+            }
+            let next_start = self.rope.line_to_char(row);
+            if next_start >= self.rope.len_chars() {
+                break;
+            }
+            let next = self.line(row)?;
+
+            let indent = next.chars().take_while(|c| c.is_whitespace()).count();
+            let mut skip = if spaces { indent } else { 0 };
+            if let Some(leader) = self.comment_leader(&cur)
+                && self.comment_leader(&next).as_ref() == Some(&leader)
+            {
+                skip = indent + leader.chars().count();
+                if spaces {
+                    let after = next.chars().skip(skip);
+                    skip += after.take_while(|c| c.is_whitespace()).count();
+                }
+            }
+            let rest: String = next.chars().skip(skip).collect();
+
+            let col = cur.chars().count();
+            let space = spaces
+                && !cur.is_empty()
+                && !cur.ends_with([' ', '\t'])
+                && !rest.is_empty()
+                && !rest.starts_with(')');
+            let above = self.line(row - 1).unwrap_or_default();
+            let line_end = self.rope.line_to_char(row - 1) + above.chars().count();
+            let joint = if space { " " } else { "" };
+            pieces.push((line_end..next_start + skip, joint.to_string()));
+            cur = cur + joint + &rest;
+            join_col = Some(col);
+        }
+        self.replace_pieces(pieces);
+        join_col
+    }
+
+    /// The line comment leader `line` starts with, along with any marker
+    /// right after it (`///`, `//!`, `;;`).
+    fn comment_leader(&self, line: &str) -> Option<String> {
+        let prefix = filetype::syntax(&self.filetype).line_comment?;
+        let rest = line.trim_start().strip_prefix(prefix)?;
+        let marker = rest
+            .chars()
+            .take_while(|c| !c.is_whitespace() && !c.is_alphanumeric());
+        Some(prefix.chars().chain(marker).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::buffer_with_suffix;
+
+    #[test]
+    fn join_replaces_indent_with_a_space() {
+        let mut buf = buffer_with_suffix("a\n    b\n", ".txt");
+        assert_eq!(buf.join_rows(0, 1, true), Some(1));
+        assert_eq!(buf.line(0).unwrap(), "a b");
+        assert_eq!(buf.join_rows(0, 1, true), None);
+    }
+
+    #[test]
+    fn join_adds_no_space_where_it_would_be_wrong() {
+        let mut buf = buffer_with_suffix("f(a\n)\nx \ny\n\nz\n", ".txt");
+        buf.join_rows(0, 1, true);
+        assert_eq!(buf.line(0).unwrap(), "f(a)");
+        buf.join_rows(1, 2, true);
+        assert_eq!(buf.line(1).unwrap(), "x y");
+        buf.join_rows(2, 3, true);
+        assert_eq!(buf.line(2).unwrap(), "z");
+    }
+
+    #[test]
+    fn join_without_spaces_keeps_indent() {
+        let mut buf = buffer_with_suffix("a\n  b\n", ".txt");
+        assert_eq!(buf.join_rows(0, 1, false), Some(1));
+        assert_eq!(buf.line(0).unwrap(), "a  b");
+    }
+
+    #[test]
+    fn joining_a_range_is_one_change() {
+        let mut buf = buffer_with_suffix("a\n  b\n  c\nd\n", ".txt");
+        let tick = buf.changedtick();
+        assert_eq!(buf.join_rows(0, 2, true), Some(3));
+        assert_eq!(buf.line(0).unwrap(), "a b c");
+        assert_eq!(buf.line(1).unwrap(), "d");
+        assert_eq!(buf.changedtick(), tick + 1);
+        assert_eq!(buf.marks().changes().len(), 1);
+        assert_eq!(buf.join_rows(1, 5, true), None);
+    }
+
+    #[test]
+    fn join_drops_comment_leaders() {
+        let mut buf = buffer_with_suffix("// a\n  // b\n/// c\n/// d\n// e\nf\n", ".rs");
+        buf.join_rows(0, 1, true);
+        assert_eq!(buf.line(0).unwrap(), "// a b");
+        buf.join_rows(1, 2, false);
+        assert_eq!(buf.line(1).unwrap(), "/// c d");
+        buf.join_rows(2, 3, true);
+        assert_eq!(buf.line(2).unwrap(), "// e f");
+    }
+}
@@ -0,0 +1,182 @@
+use crate::error::DvimError;
+
+/// What a character counts as for word motions and word text objects.
+/// A "word" is a run of characters of the same non-blank class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharClass {
+    Blank,
+    Keyword,
+    Punctuation,
+}
+
+/// The set of characters that make up a keyword, parsed from a vim-style
+/// 'iskeyword' spec such as `@,48-57,_,192-255`.
+///
+/// Each comma-separated part is a single character (`_`), a character code
+/// (`48`), a range of either (`a-z`, `48-57`), or `@` for every alphabetic
+/// character. A leading `^` removes the part from the set instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeywordChars {
+    spec: String,
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Part {
+    exclude: bool,
+    kind: PartKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PartKind {
+    Alphabetic,
+    Range(u32, u32),
+}
+
+impl KeywordChars {
+    pub fn parse(spec: &str) -> Result<Self, DvimError> {
+        let invalid = || DvimError::InvalidOption {
+            name: "iskeyword".to_string(),
+            value: spec.to_string(),
+        };
+        let mut parts = Vec::new();
+        for raw in spec.split(',').filter(|p| !p.is_empty()) {
+            let (exclude, body) = match raw.strip_prefix('^') {
+                Some(rest) if !rest.is_empty() => (true, rest),
+                _ => (false, raw),
+            };
+            let kind = if body == "@" {
+                PartKind::Alphabetic
+            } else if body == "@-@" {
+                PartKind::Range('@' as u32, '@' as u32)
+            } else if let Some((lo, hi)) = split_range(body) {
+                let lo = parse_char_code(lo).ok_or_else(invalid)?;
+                let hi = parse_char_code(hi).ok_or_else(invalid)?;
+                if lo > hi {
+                    return Err(invalid());
+                }
+                PartKind::Range(lo, hi)
+            } else {
+                let code = parse_char_code(body).ok_or_else(invalid)?;
+                PartKind::Range(code, code)
+            };
+            parts.push(Part { exclude, kind });
+        }
+        Ok(Self {
+            spec: spec.to_string(),
+            parts,
+        })
+    }
+
+    /// The spec this set was parsed from.
+    pub fn spec(&self) -> &str {
+        &self.spec
+    }
+
+    /// Whether `c` is a keyword character. Later parts override earlier ones,
+    /// so `@,^x` is every letter except `x`.
+    pub fn contains(&self, c: char) -> bool {
+        let mut result = false;
+        for part in &self.parts {
+            let matches = match part.kind {
+                PartKind::Alphabetic => c.is_alphabetic(),
+                PartKind::Range(lo, hi) => (lo..=hi).contains(&(c as u32)),
+            };
+            if matches {
+                result = !part.exclude;
+            }
+        }
+        result
+    }
+
+    /// Classify `c` for word motions. With `big` set (WORD motions such as
+    /// `W`), every non-blank character is part of the same class.
+    pub fn classify(&self, c: char, big: bool) -> CharClass {
+        if c.is_whitespace() {
+            CharClass::Blank
+        } else if big || self.contains(c) {
+            CharClass::Keyword
+        } else {
+            CharClass::Punctuation
+        }
+    }
+}
+
+/// Split `a-z` or `48-57` into its two ends. A lone `-` is a character, not
+/// a range.
+fn split_range(body: &str) -> Option<(&str, &str)> {
+    let mut chars = body.char_indices();
+    let (_, first) = chars.next()?;
+    let start = first.len_utf8();
+    let dash = body[start..].find('-')? + start;
+    let (lo, hi) = (&body[..dash], &body[dash + 1..]);
+    (!hi.is_empty()).then_some((lo, hi))
+}
+
+/// A decimal character code (`48`) or a single literal character (`_`).
+fn parse_char_code(s: &str) -> Option<u32> {
+    if s.len() > 1 && s.chars().all(|c| c.is_ascii_digit()) {
+        return s.parse().ok();
+    }
+    let mut chars = s.chars();
+    let c = chars.next()?;
+    if chars.next().is_some() {
+        return None;
+    }
+    Some(c as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filetype::DEFAULT_ISKEYWORD;
+
+    #[test]
+    fn default_spec_matches_identifiers() {
+        let kw = KeywordChars::parse(DEFAULT_ISKEYWORD).unwrap();
+        assert!(kw.contains('a'));
+        assert!(kw.contains('Z'));
+        assert!(kw.contains('7'));
+        assert!(kw.contains('_'));
+        assert!(kw.contains('é'));
+        assert!(!kw.contains('-'));
+        assert!(!kw.contains('.'));
+    }
+
+    #[test]
+    fn dash_can_be_added() {
+        let kw = KeywordChars::parse("@,48-57,_,-").unwrap();
+        assert!(kw.contains('-'));
+    }
+
+    #[test]
+    fn char_range_and_exclusion() {
+        let kw = KeywordChars::parse("a-f,^c").unwrap();
+        assert!(kw.contains('a'));
+        assert!(!kw.contains('c'));
+        assert!(!kw.contains('g'));
+    }
+
+    #[test]
+    fn single_digit_is_a_literal_character() {
+        let kw = KeywordChars::parse("5").unwrap();
+        assert!(kw.contains('5'));
+        assert!(!kw.contains('\u{5}'));
+    }
+
+    #[test]
+    fn invalid_spec_is_rejected() {
+        assert!(KeywordChars::parse("z-a").is_err());
+        assert!(KeywordChars::parse("abc").is_err());
+    }
+
+    #[test]
+    fn classify_word_and_big_word() {
+        let kw = KeywordChars::parse(DEFAULT_ISKEYWORD).unwrap();
+        assert_eq!(kw.classify(' ', false), CharClass::Blank);
+        assert_eq!(kw.classify('x', false), CharClass::Keyword);
+        assert_eq!(kw.classify('.', false), CharClass::Punctuation);
+        assert_eq!(kw.classify('.', true), CharClass::Keyword);
+        assert_eq!(kw.classify('\t', true), CharClass::Blank);
+    }
+}
@@ -0,0 +1,236 @@
+//! Marks, the jump list and the change list: positions that stay on the
+//! text they were set on as the buffer is edited around them.
+
+use std::collections::BTreeMap;
+
+use super::Buffer;
+
+/// How many entries the change list keeps.
+const CHANGE_LIST_LEN: usize = 100;
+
+/// Marks that can be set with `m`. `'` is the context mark, set by jumps.
+pub fn is_settable(name: char) -> bool {
+    name.is_ascii_alphabetic() || matches!(name, '\'' | '`' | '[' | ']' | '<' | '>')
+}
+
+/// Marks and jumps, stored as char indices into the rope.
+///
+/// dvim has a single buffer, so file marks (`A`–`Z`) and the jump list live
+/// here alongside the buffer's local marks, and all of them are shifted by
+/// the same edits.
+#[derive(Debug, Clone, Default)]
+pub struct Marks {
+    named: BTreeMap<char, usize>,
+    jumps: Vec<usize>,
+    /// Where Ctrl-o/Ctrl-i are in `jumps`; `jumps.len()` when past the end.
+    jump_index: usize,
+    /// Where edits were made, oldest first.
+    changes: Vec<usize>,
+    /// Where `g;`/`g,` are in `changes`; `changes.len()` when past the end.
+    change_index: usize,
+}
+
+impl Marks {
+    pub fn get(&self, name: char) -> Option<usize> {
+        let name = if name == '`' { '\'' } else { name };
+        self.named.get(&name).copied()
+    }
+
+    pub fn set(&mut self, name: char, idx: usize) {
+        let name = if name == '`' { '\'' } else { name };
+        self.named.insert(name, idx);
+    }
+
+    /// All set marks, in name order.
+    pub fn iter(&self) -> impl Iterator<Item = (char, usize)> + '_ {
+        self.named.iter().map(|(&name, &idx)| (name, idx))
+    }
+
+    pub fn jumps(&self) -> &[usize] {
+        &self.jumps
+    }
+
+    pub fn jump_index(&self) -> usize {
+        self.jump_index
+    }
+
+    pub fn changes(&self) -> &[usize] {
+        &self.changes
+    }
+
+    pub fn change_index(&self) -> usize {
+        self.change_index
+    }
+
+    /// Move positions at or after `at` along by `len` inserted chars.
+    pub(super) fn shift_for_insert(&mut self, at: usize, len: usize) {
+        for idx in self.positions_mut() {
+            if *idx >= at {
+                *idx += len;
+            }
+        }
+    }
+
+    /// Pull positions after a removed `from..to` back, and those inside it to
+    /// where it was.
+    pub(super) fn shift_for_remove(&mut self, from: usize, to: usize) {
+        for idx in self.positions_mut() {
+            if *idx >= to {
+                *idx -= to - from;
+            } else if *idx > from {
+                *idx = from;
+            }
+        }
+    }
+
+    /// Keep positions inside a buffer of `len` chars, after a reload.
+    pub(super) fn clamp(&mut self, len: usize) {
+        for idx in self.positions_mut() {
+            *idx = (*idx).min(len.saturating_sub(1));
+        }
+    }
+
+    fn positions_mut(&mut self) -> impl Iterator<Item = &mut usize> {
+        self.named
+            .values_mut()
+            .chain(self.jumps.iter_mut())
+            .chain(self.changes.iter_mut())
+    }
+}
+
+impl Buffer {
+    pub fn marks(&self) -> &Marks {
+        &self.marks
+    }
+
+    /// Note an edit at `idx` in the `.` mark and the change list. Edits on
+    /// the same line as the last one update that entry instead of adding
+    /// another, so typing a word doesn't fill the list.
+    pub(super) fn record_change(&mut self, idx: usize) {
+        let idx = idx.min(self.rope.len_chars().saturating_sub(1));
+        self.marks.set('.', idx);
+        let line = self.rope.char_to_line(idx);
+        let rope = &self.rope;
+        let changes = &mut self.marks.changes;
+        match changes.last_mut() {
+            Some(last) if rope.char_to_line(*last) == line => *last = idx,
+            _ => {
+                changes.push(idx);
+                if changes.len() > CHANGE_LIST_LEN {
+                    changes.remove(0);
+                }
+            }
+        }
+        self.marks.change_index = self.marks.changes.len();
+    }
+
+    /// Step `count` entries back (`g;`, negative) or forward (`g,`) through
+    /// the change list, returning where to go.
+    pub fn step_change(&mut self, count: isize) -> Option<usize> {
+        let target = self.marks.change_index.checked_add_signed(count)?;
+        let idx = *self.marks.changes.get(target)?;
+        self.marks.change_index = target;
+        Some(idx)
+    }
+
+    pub fn set_mark(&mut self, name: char, idx: usize) {
+        self.marks.set(name, idx);
+    }
+
+    /// Add `idx` to the end of the jump list, dropping any older entry on
+    /// the same line so each line appears once.
+    pub fn push_jump(&mut self, idx: usize) {
+        let line = self.rope.char_to_line(idx.min(self.rope.len_chars()));
+        let rope = &self.rope;
+        self.marks
+            .jumps
+            .retain(|&j| rope.char_to_line(j.min(rope.len_chars())) != line);
+        self.marks.jumps.push(idx);
+        self.marks.jump_index = self.marks.jumps.len();
+    }
+
+    /// Step `count` entries back (`Ctrl-o`, negative) or forward (`Ctrl-i`)
+    /// through the jump list from `current`, returning where to go. Leaving
+    /// the end of the list remembers `current`, so Ctrl-i can return to it.
+    pub fn step_jump(&mut self, current: usize, count: isize) -> Option<usize> {
+        if count < 0 && self.marks.jump_index >= self.marks.jumps.len() {
+            self.push_jump(current);
+            self.marks.jump_index = self.marks.jumps.len() - 1;
+        }
+        let target = self.marks.jump_index.checked_add_signed(count)?;
+        let idx = *self.marks.jumps.get(target)?;
+        self.marks.jump_index = target;
+        Some(idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::buffer_from_str;
+
+    #[test]
+    fn marks_shift_with_edits_before_them() {
+        let mut buf = buffer_from_str("abc\ndef\n");
+        buf.set_mark('a', buf.char_index(1, 1));
+        buf.insert_text(0, 0, "xy\n");
+        assert_eq!(buf.position(buf.marks().get('a').unwrap()), (2, 1));
+        buf.delete_line(0);
+        assert_eq!(buf.position(buf.marks().get('a').unwrap()), (1, 1));
+        buf.insert_char(1, 2, 'z');
+        assert_eq!(buf.position(buf.marks().get('a').unwrap()), (1, 1));
+    }
+
+    #[test]
+    fn mark_inside_removed_text_moves_to_its_start() {
+        let mut buf = buffer_from_str("abcdef\n");
+        buf.set_mark('a', 3);
+        buf.delete_range((0, 1), (0, 5));
+        assert_eq!(buf.marks().get('a'), Some(1));
+    }
+
+    #[test]
+    fn last_change_mark() {
+        let mut buf = buffer_from_str("abc\ndef\n");
+        buf.insert_char(1, 2, 'x');
+        assert_eq!(buf.position(buf.marks().get('.').unwrap()), (1, 2));
+    }
+
+    #[test]
+    fn change_list_merges_edits_on_one_line() {
+        let mut buf = buffer_from_str("abc\ndef\nghi\n");
+        buf.insert_char(0, 0, 'x');
+        buf.insert_char(0, 1, 'y');
+        buf.insert_char(2, 0, 'z');
+        assert_eq!(buf.marks().changes(), &[1, 10]);
+        // An edit between the entries shifts the later one.
+        buf.insert_text(1, 0, "12");
+        assert_eq!(buf.marks().changes(), &[1, 12, 6]);
+    }
+
+    #[test]
+    fn change_list_steps() {
+        let mut buf = buffer_from_str("a\nb\nc\n");
+        buf.insert_char(0, 0, 'x');
+        buf.insert_char(2, 0, 'y');
+        assert_eq!(buf.step_change(-1), Some(5));
+        assert_eq!(buf.step_change(-1), Some(0));
+        assert_eq!(buf.step_change(-1), None);
+        assert_eq!(buf.step_change(1), Some(5));
+        assert_eq!(buf.step_change(1), None);
+    }
+
+    #[test]
+    fn jump_list_dedupes_lines_and_steps() {
+        let mut buf = buffer_from_str("a\nb\nc\n");
+        buf.push_jump(0);
+        buf.push_jump(2);
+        buf.push_jump(0);
+        assert_eq!(buf.marks().jumps(), &[2, 0]);
+        // Ctrl-o from line 3 remembers it, then goes back.
+        assert_eq!(buf.step_jump(4, -1), Some(0));
+        assert_eq!(buf.step_jump(0, -1), Some(2));
+        assert_eq!(buf.step_jump(2, -1), None);
+        assert_eq!(buf.step_jump(2, 2), Some(4));
+        assert_eq!(buf.step_jump(4, 1), None);
+    }
+}
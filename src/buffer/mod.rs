@@ -1,18 +1,92 @@
+mod brackets;
+mod indent;
+mod indenter;
+mod join;
+mod keyword;
+mod marks;
+mod options;
+mod pairs;
+
+use brackets::LineStates;
+pub use brackets::is_bracket;
+pub use keyword::{CharClass, KeywordChars};
+pub use marks::{Marks, is_settable as is_settable_mark};
+
+use std::cell::RefCell;
+use std::hash::{DefaultHasher, Hasher};
 use std::io::BufWriter;
-use std::path::PathBuf;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 use ropey::Rope;
 
 use crate::error::DvimError;
+use crate::filetype;
 
 /// A text buffer backed by a rope data structure.
 ///
 /// The rope stores the file contents as a balanced tree of text chunks,
 /// giving us O(log n) indexing by line and efficient future insert/delete
 /// operations — even on very large files.
+#[derive(Clone)]
 pub struct Buffer {
     rope: Rope,
     filename: PathBuf,
+    /// Whether the buffer has unsaved changes.
+    modified: bool,
+    /// Incremented on every change to the text (vim's `b:changedtick`).
+    changedtick: u64,
+    /// What the file looked like on disk when we last loaded or wrote it.
+    disk_state: Option<DiskState>,
+    /// The detected filetype ("rust", "css", ...), used for per-filetype defaults.
+    filetype: String,
+    /// Which characters count as part of a word ('iskeyword').
+    keyword_chars: KeywordChars,
+    /// Columns per level of indent ('shiftwidth').
+    shiftwidth: usize,
+    /// Columns a tab character takes up ('tabstop').
+    tabstop: usize,
+    /// Indent with spaces rather than tabs ('expandtab').
+    expandtab: bool,
+    /// Give new lines the indent of the line above ('autoindent').
+    autoindent: bool,
+    /// Indent after `{` and `:` and dedent `}` ('smartindent').
+    smartindent: bool,
+    /// The name of the indenter for this filetype ('indentexpr').
+    indentexpr: String,
+    /// Brackets and quotes whose closer is typed along with them
+    /// ('autopairs').
+    autopairs: Vec<(char, char)>,
+    /// Marks and jumps, kept in step with edits.
+    marks: Marks,
+    /// Where comments and literals stand at each line start, so bracket
+    /// matching doesn't rescan the buffer from the top.
+    line_states: RefCell<LineStates>,
+}
+
+/// A snapshot of a file's on-disk identity, used to notice when something
+/// else (a `git checkout`, a formatter) rewrites the file underneath us.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DiskState {
+    mtime: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+impl DiskState {
+    /// Stat and hash the file at `path`. Returns None if it can't be read.
+    fn read(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        let contents = std::fs::read(path).ok()?;
+        let mut hasher = DefaultHasher::new();
+        hasher.write(&contents);
+        Some(Self {
+            mtime: metadata.modified().ok(),
+            len: metadata.len(),
+            hash: hasher.finish(),
+        })
+    }
 }
 
 impl Buffer {
@@ -28,9 +102,27 @@ impl Buffer {
                 source: e,
             })?;
 
+        let disk_state = DiskState::read(&path);
+        let filetype = filetype::detect(&path);
+        let keyword_chars = KeywordChars::parse(filetype::default_iskeyword(filetype))?;
+        let indent = filetype::indent_style(filetype);
         Ok(Self {
             rope,
             filename: path,
+            modified: false,
+            changedtick: 0,
+            disk_state,
+            filetype: filetype.to_string(),
+            keyword_chars,
+            shiftwidth: indent.shiftwidth,
+            tabstop: 8,
+            expandtab: indent.expandtab,
+            autoindent: true,
+            smartindent: false,
+            indentexpr: indent.indentexpr.to_string(),
+            autopairs: pairs::parse_pairs(&filetype::auto_pairs(filetype)).unwrap_or_default(),
+            marks: Marks::default(),
+            line_states: RefCell::default(),
         })
     }
 
@@ -59,12 +151,174 @@ impl Buffer {
         self.line(idx).map_or(0, |l| l.len())
     }
 
+    /// The character at absolute char index `idx`, if any.
+    pub fn char_at(&self, idx: usize) -> Option<char> {
+        self.rope.get_char(idx)
+    }
+
+    /// The whole buffer as a string.
+    pub fn contents(&self) -> String {
+        self.rope.to_string()
+    }
+
+    /// The text from `start` up to (not including) `end`, both given as
+    /// (line, col) like `delete_range`.
+    pub fn text_range(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        let from = self.char_index(start.0, start.1);
+        let to = self.char_index(end.0, end.1).min(self.rope.len_chars());
+        if from >= to {
+            return String::new();
+        }
+        self.rope.slice(from..to).to_string()
+    }
+
+    /// Convert a (line, col) position to an absolute char index.
+    pub fn char_index(&self, line: usize, col: usize) -> usize {
+        self.rope.line_to_char(line) + col
+    }
+
+    /// Convert an absolute char index back to a (line, col) position.
+    pub fn position(&self, idx: usize) -> (usize, usize) {
+        let idx = idx.min(self.rope.len_chars());
+        let line = self.rope.char_to_line(idx);
+        (line, idx - self.rope.line_to_char(line))
+    }
+
     pub fn filename(&self) -> &std::path::Path {
         &self.filename
     }
 
-    /// Write the buffer contents back to its file.
-    pub fn write(&self) -> Result<(), DvimError> {
+    pub fn filetype(&self) -> &str {
+        &self.filetype
+    }
+
+    /// The current 'iskeyword' spec.
+    pub fn iskeyword(&self) -> &str {
+        self.keyword_chars.spec()
+    }
+
+    /// Replace 'iskeyword', rejecting specs that don't parse.
+    pub fn set_iskeyword(&mut self, spec: &str) -> Result<(), DvimError> {
+        self.keyword_chars = KeywordChars::parse(spec)?;
+        Ok(())
+    }
+
+    /// Classify `c` using this buffer's 'iskeyword'. This is the one place
+    /// word motions, word operators and word text objects decide where a
+    /// word starts and ends.
+    pub fn char_class(&self, c: char, big: bool) -> CharClass {
+        self.keyword_chars.classify(c, big)
+    }
+
+    /// Whether the buffer has been edited since it was last loaded or written.
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    /// A counter that changes whenever the text does, for noticing whether a
+    /// command edited the buffer.
+    pub fn changedtick(&self) -> u64 {
+        self.changedtick
+    }
+
+    /// Insert `text` at char index `idx`. Every insertion goes through here
+    /// so marks move along with the text after it and the change is listed.
+    fn insert_at(&mut self, idx: usize, text: &str) {
+        self.line_states
+            .get_mut()
+            .invalidate_after(self.rope.char_to_line(idx));
+        self.rope.insert(idx, text);
+        self.marks.shift_for_insert(idx, text.chars().count());
+        self.record_change(idx);
+        self.mark_changed();
+    }
+
+    /// Remove the chars `from..to`. Every removal goes through here so marks
+    /// move along with the text after it and the change is listed.
+    fn remove(&mut self, from: usize, to: usize) {
+        self.line_states
+            .get_mut()
+            .invalidate_after(self.rope.char_to_line(from));
+        self.rope.remove(from..to);
+        self.marks.shift_for_remove(from, to);
+        self.record_change(from);
+        self.mark_changed();
+    }
+
+    /// Replace each of `pieces`' char ranges with its text, as a single
+    /// change. The ranges are in the text as it is now and mustn't overlap.
+    /// Marks move along with every piece, but changedtick, the change list
+    /// and the `.` mark only see one change, at the first piece.
+    fn replace_pieces(&mut self, mut pieces: Vec<(Range<usize>, String)>) {
+        pieces.sort_by_key(|(range, _)| range.start);
+        let Some(first) = pieces.first().map(|(range, _)| range.start) else {
+            return;
+        };
+        self.line_states
+            .get_mut()
+            .invalidate_after(self.rope.char_to_line(first));
+        // From the back, so the ranges before each piece stay put.
+        for (range, text) in pieces.into_iter().rev() {
+            self.rope.remove(range.clone());
+            self.marks.shift_for_remove(range.start, range.end);
+            self.rope.insert(range.start, &text);
+            self.marks
+                .shift_for_insert(range.start, text.chars().count());
+        }
+        self.record_change(first);
+        self.mark_changed();
+    }
+
+    fn mark_changed(&mut self) {
+        self.modified = true;
+        self.changedtick += 1;
+    }
+
+    /// Whether the file on disk no longer matches what we last loaded or wrote.
+    ///
+    /// mtime and size are checked first; the content hash is only compared
+    /// when one of them differs, so a `touch` alone doesn't count as a change.
+    /// A file that has been deleted has nothing left to clobber, so it doesn't
+    /// count either.
+    pub fn changed_on_disk(&self) -> bool {
+        let Some(recorded) = &self.disk_state else {
+            return false;
+        };
+        let Some(current) = DiskState::read(&self.filename) else {
+            return false;
+        };
+        if current.mtime == recorded.mtime && current.len == recorded.len {
+            return false;
+        }
+        current.hash != recorded.hash
+    }
+
+    /// Re-read the file from disk, discarding any unsaved changes.
+    pub fn reload(&mut self) -> Result<(), DvimError> {
+        let reloaded = Self::from_file(self.filename.clone())?;
+        self.rope = reloaded.rope;
+        self.line_states.get_mut().clear();
+        self.marks.clamp(self.rope.len_chars());
+        self.disk_state = reloaded.disk_state;
+        self.modified = false;
+        self.changedtick += 1;
+        Ok(())
+    }
+
+    /// Write the buffer contents back to its file, refusing if the file was
+    /// changed on disk since we last loaded or wrote it.
+    pub fn write(&mut self) -> Result<(), DvimError> {
+        if self.changed_on_disk() {
+            return Err(DvimError::FileChanged {
+                path: self.filename.display().to_string(),
+            });
+        }
+        self.write_force()
+    }
+
+    /// Write the buffer contents back to its file, overwriting any changes
+    /// made on disk in the meantime.
+    pub fn write_force(&mut self) -> Result<(), DvimError> {
         let file = std::fs::File::create(&self.filename).map_err(|e| DvimError::FileWrite {
             path: self.filename.display().to_string(),
             source: e,
@@ -75,6 +329,8 @@ impl Buffer {
                 path: self.filename.display().to_string(),
                 source: e,
             })?;
+        self.disk_state = DiskState::read(&self.filename);
+        self.modified = false;
         Ok(())
     }
 
@@ -83,13 +339,36 @@ impl Buffer {
     /// Insert a character at the given (line, col) position.
     pub fn insert_char(&mut self, line: usize, col: usize, ch: char) {
         let char_idx = self.rope.line_to_char(line) + col;
-        self.rope.insert_char(char_idx, ch);
+        self.insert_at(char_idx, ch.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Insert `text` (which may contain newlines) at the given (line, col).
+    pub fn insert_text(&mut self, line: usize, col: usize, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let char_idx = self.rope.line_to_char(line) + col;
+        self.insert_at(char_idx, text);
     }
 
     /// Insert a newline at the given (line, col) position, splitting the line.
     pub fn insert_newline(&mut self, line: usize, col: usize) {
         let char_idx = self.rope.line_to_char(line) + col;
-        self.rope.insert_char(char_idx, '\n');
+        self.insert_at(char_idx, "\n");
+    }
+
+    /// Replace the character at (line, col) with `ch`, returning the one it
+    /// replaced. Does nothing at or past the end of the line. Marks on the
+    /// character stay on it.
+    pub fn replace_char(&mut self, line: usize, col: usize, ch: char) -> Option<char> {
+        if col >= self.line_len(line) {
+            return None;
+        }
+        let char_idx = self.rope.line_to_char(line) + col;
+        let old = self.rope.char(char_idx);
+        self.insert_at(char_idx + 1, ch.encode_utf8(&mut [0; 4]));
+        self.remove(char_idx, char_idx + 1);
+        Some(old)
     }
 
     /// Delete the entire line at `line`, including its trailing newline.
@@ -109,7 +388,18 @@ impl Buffer {
         if end - start >= self.rope.len_chars() {
             return;
         }
-        self.rope.remove(start..end);
+        self.remove(start, end);
+    }
+
+    /// Delete the text from `start` up to (not including) `end`, both given
+    /// as (line, col). A col equal to the line length addresses the newline.
+    pub fn delete_range(&mut self, start: (usize, usize), end: (usize, usize)) {
+        let from = self.rope.line_to_char(start.0) + start.1;
+        let to = self.rope.line_to_char(end.0) + end.1;
+        if from >= to {
+            return;
+        }
+        self.remove(from, to);
     }
 
     /// Delete the character at (line, col). Does nothing if the line is empty.
@@ -119,7 +409,7 @@ impl Buffer {
             return;
         }
         let char_idx = self.rope.line_to_char(line) + col;
-        self.rope.remove(char_idx..char_idx + 1);
+        self.remove(char_idx, char_idx + 1);
     }
 
     /// Delete the character before (line, col). Returns the new cursor (line, col).
@@ -132,11 +422,11 @@ impl Buffer {
             // Join with previous line: remove the newline at end of previous line
             let prev_line_len = self.line_len(line - 1);
             let char_idx = self.rope.line_to_char(line) - 1;
-            self.rope.remove(char_idx..char_idx + 1);
+            self.remove(char_idx, char_idx + 1);
             (line - 1, prev_line_len)
         } else {
             let char_idx = self.rope.line_to_char(line) + col;
-            self.rope.remove(char_idx - 1..char_idx);
+            self.remove(char_idx - 1, char_idx);
             (line, col - 1)
         }
     }
@@ -147,12 +437,20 @@ mod tests {
     use super::*;
     use std::io::Write;
 
-    fn buffer_from_str(content: &str) -> Buffer {
+    pub(super) fn buffer_from_str(content: &str) -> Buffer {
         let mut tmp = tempfile::NamedTempFile::new().unwrap();
         tmp.write_all(content.as_bytes()).unwrap();
         Buffer::from_file(tmp.path().to_path_buf()).unwrap()
     }
 
+    /// A buffer for `content` in a file ending in `suffix`, so the
+    /// filetype follows from it.
+    pub(super) fn buffer_with_suffix(content: &str, suffix: &str) -> Buffer {
+        let mut tmp = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+        tmp.write_all(content.as_bytes()).unwrap();
+        Buffer::from_file(tmp.path().to_path_buf()).unwrap()
+    }
+
     #[test]
     fn line_count_simple() {
         let buf = buffer_from_str("hello\nworld\n");
@@ -234,6 +532,16 @@ mod tests {
         assert_eq!(buf.line(0).unwrap(), "hello");
     }
 
+    #[test]
+    fn replace_char_keeps_marks() {
+        let mut buf = buffer_from_str("abc\n");
+        buf.set_mark('a', 1);
+        assert_eq!(buf.replace_char(0, 1, 'X'), Some('b'));
+        assert_eq!(buf.line(0).unwrap(), "aXc");
+        assert_eq!(buf.marks().get('a'), Some(1));
+        assert_eq!(buf.replace_char(0, 3, 'X'), None);
+    }
+
     #[test]
     fn delete_line_middle() {
         let mut buf = buffer_from_str("aaa\nbbb\nccc\n");
@@ -292,6 +600,65 @@ mod tests {
         assert_eq!(buf.line_count(), before);
     }
 
+    #[test]
+    fn delete_range_within_line() {
+        let mut buf = buffer_from_str("hello world\n");
+        buf.delete_range((0, 2), (0, 8));
+        assert_eq!(buf.line(0).unwrap(), "herld");
+    }
+
+    #[test]
+    fn delete_range_across_lines() {
+        let mut buf = buffer_from_str("abc\ndef\nghi\n");
+        buf.delete_range((0, 1), (2, 1));
+        assert_eq!(buf.line(0).unwrap(), "ahi");
+        assert_eq!(buf.line_count(), 2);
+    }
+
+    #[test]
+    fn text_range_across_lines() {
+        let buf = buffer_from_str("abc\ndef\n");
+        assert_eq!(buf.text_range((0, 1), (1, 2)), "bc\nde");
+        assert_eq!(buf.text_range((1, 0), (0, 0)), "");
+    }
+
+    #[test]
+    fn insert_text_with_newlines() {
+        let mut buf = buffer_from_str("ad\n");
+        buf.insert_text(0, 1, "b\nc");
+        assert_eq!(buf.line(0).unwrap(), "ab");
+        assert_eq!(buf.line(1).unwrap(), "cd");
+        assert!(buf.is_modified());
+    }
+
+    #[test]
+    fn iskeyword_follows_filetype() {
+        let mut tmp = tempfile::Builder::new().suffix(".css").tempfile().unwrap();
+        tmp.write_all(b"a-b\n").unwrap();
+        let buf = Buffer::from_file(tmp.path().to_path_buf()).unwrap();
+        assert_eq!(buf.filetype(), "css");
+        assert_eq!(buf.char_class('-', false), CharClass::Keyword);
+    }
+
+    #[test]
+    fn set_iskeyword_rejects_bad_spec() {
+        let mut buf = buffer_from_str("x\n");
+        assert!(buf.set_iskeyword("z-a").is_err());
+        buf.set_iskeyword("@,-").unwrap();
+        assert_eq!(buf.iskeyword(), "@,-");
+        assert_eq!(buf.char_class('-', false), CharClass::Keyword);
+    }
+
+    #[test]
+    fn char_index_and_position_round_trip() {
+        let buf = buffer_from_str("abc\ndef\n");
+        assert_eq!(buf.char_index(1, 2), 6);
+        assert_eq!(buf.position(6), (1, 2));
+        assert_eq!(buf.char_at(6), Some('f'));
+        assert_eq!(buf.char_at(3), Some('\n'));
+        assert_eq!(buf.char_at(99), None);
+    }
+
     #[test]
     fn write_round_trip() {
         let mut buf = buffer_from_str("hello\nworld\n");
@@ -302,4 +669,54 @@ mod tests {
         assert_eq!(buf2.line(0).unwrap(), "hello!");
         assert_eq!(buf2.line(1).unwrap(), "world");
     }
+
+    #[test]
+    fn edits_mark_buffer_modified() {
+        let mut buf = buffer_from_str("hello\n");
+        assert!(!buf.is_modified());
+        buf.insert_char(0, 0, 'X');
+        assert!(buf.is_modified());
+        buf.write().unwrap();
+        assert!(!buf.is_modified());
+    }
+
+    #[test]
+    fn changed_on_disk_detects_external_write() {
+        let buf = buffer_from_str("hello\n");
+        assert!(!buf.changed_on_disk());
+        std::fs::write(buf.filename(), "rewritten by a formatter\n").unwrap();
+        assert!(buf.changed_on_disk());
+    }
+
+    #[test]
+    fn write_refuses_when_changed_on_disk() {
+        let mut buf = buffer_from_str("hello\n");
+        std::fs::write(buf.filename(), "external\n").unwrap();
+        buf.insert_char(0, 0, 'X');
+        assert!(matches!(buf.write(), Err(DvimError::FileChanged { .. })));
+        assert_eq!(
+            std::fs::read_to_string(buf.filename()).unwrap(),
+            "external\n"
+        );
+    }
+
+    #[test]
+    fn write_force_overrides_external_change() {
+        let mut buf = buffer_from_str("hello\n");
+        std::fs::write(buf.filename(), "external\n").unwrap();
+        buf.write_force().unwrap();
+        assert_eq!(std::fs::read_to_string(buf.filename()).unwrap(), "hello\n");
+        assert!(!buf.changed_on_disk());
+    }
+
+    #[test]
+    fn reload_picks_up_external_change() {
+        let mut buf = buffer_from_str("hello\n");
+        buf.insert_char(0, 0, 'X');
+        std::fs::write(buf.filename(), "external\n").unwrap();
+        buf.reload().unwrap();
+        assert_eq!(buf.line(0).unwrap(), "external");
+        assert!(!buf.is_modified());
+        assert!(!buf.changed_on_disk());
+    }
 }
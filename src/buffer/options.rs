@@ -0,0 +1,113 @@
+use super::Buffer;
+use crate::error::DvimError;
+use crate::filetype;
+use crate::options::{self, Scope, Value};
+
+impl Buffer {
+    /// The value of buffer-local option `name`, or None if there's no such
+    /// option.
+    pub fn option(&self, name: &str) -> Option<Value> {
+        Some(match name {
+            "autoindent" => Value::Bool(self.autoindent()),
+            "autopairs" => Value::String(self.autopairs_spec()),
+            "expandtab" => Value::Bool(self.expandtab()),
+            "filetype" => Value::String(self.filetype().to_string()),
+            "indentexpr" => Value::String(self.indentexpr().to_string()),
+            "iskeyword" => Value::String(self.iskeyword().to_string()),
+            "shiftwidth" => Value::Number(self.shiftwidth()),
+            "smartindent" => Value::Bool(self.smartindent()),
+            "tabstop" => Value::Number(self.tabstop()),
+            _ => return None,
+        })
+    }
+
+    /// Set buffer-local option `name`, rejecting values it can't take.
+    pub fn set_option(&mut self, name: &str, value: &Value) -> Result<(), DvimError> {
+        match (name, value) {
+            ("autoindent", Value::Bool(b)) => self.set_autoindent(*b),
+            ("autopairs", Value::String(s)) => self.set_autopairs(s)?,
+            ("expandtab", Value::Bool(b)) => self.set_expandtab(*b),
+            ("filetype", Value::String(s)) => self.set_filetype(s)?,
+            ("indentexpr", Value::String(s)) => self.set_indentexpr(s)?,
+            ("iskeyword", Value::String(s)) => self.set_iskeyword(s)?,
+            ("shiftwidth", Value::Number(n)) => self.set_shiftwidth(*n)?,
+            ("smartindent", Value::Bool(b)) => self.set_smartindent(*b),
+            ("tabstop", Value::Number(n)) => self.set_tabstop(*n)?,
+            _ => return Err(DvimError::UnknownOption(name.to_string())),
+        }
+        Ok(())
+    }
+
+    /// The value buffer-local option `name` starts with for this buffer's
+    /// filetype.
+    pub fn default_option(&self, name: &str) -> Option<Value> {
+        let indent = filetype::indent_style(&self.filetype);
+        Some(match name {
+            "autoindent" => Value::Bool(true),
+            "autopairs" => Value::String(filetype::auto_pairs(&self.filetype)),
+            "expandtab" => Value::Bool(indent.expandtab),
+            "filetype" => Value::String(self.filetype.clone()),
+            "indentexpr" => Value::String(indent.indentexpr.to_string()),
+            "iskeyword" => Value::String(filetype::default_iskeyword(&self.filetype).to_string()),
+            "shiftwidth" => Value::Number(indent.shiftwidth),
+            "smartindent" => Value::Bool(false),
+            "tabstop" => Value::Number(8),
+            _ => return None,
+        })
+    }
+
+    /// Change the filetype, putting the other buffer-local options back to
+    /// that filetype's defaults.
+    pub fn set_filetype(&mut self, name: &str) -> Result<(), DvimError> {
+        self.filetype = name.to_string();
+        self.line_states.get_mut().clear();
+        let local = options::OPTIONS
+            .iter()
+            .filter(|def| def.scope == Scope::Buffer && def.name != "filetype");
+        for def in local {
+            if let Some(value) = self.default_option(def.name) {
+                self.set_option(def.name, &value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::buffer_with_suffix;
+    use crate::options::{OPTIONS, Scope, Value};
+
+    #[test]
+    fn buffer_options_start_at_their_defaults() {
+        let buf = buffer_with_suffix("", ".go");
+        for def in OPTIONS.iter().filter(|def| def.scope == Scope::Buffer) {
+            let value = buf.option(def.name);
+            assert_eq!(
+                value.as_ref().map(Value::kind),
+                Some(def.kind),
+                "{}",
+                def.name
+            );
+            assert_eq!(value, buf.default_option(def.name), "{}", def.name);
+        }
+    }
+
+    #[test]
+    fn set_filetype_resets_options() {
+        let mut buf = buffer_with_suffix("", ".txt");
+        buf.set_option("shiftwidth", &Value::Number(3)).unwrap();
+        buf.set_option("filetype", &Value::String("go".to_string()))
+            .unwrap();
+        assert_eq!(buf.filetype(), "go");
+        assert_eq!((buf.shiftwidth(), buf.expandtab()), (8, false));
+    }
+
+    #[test]
+    fn set_option_checks_kind() {
+        let mut buf = buffer_with_suffix("", ".txt");
+        assert!(buf.set_option("shiftwidth", &Value::Bool(true)).is_err());
+        assert!(buf.set_option("shiftwidth", &Value::Number(0)).is_err());
+        assert!(buf.set_option("nosuch", &Value::Bool(true)).is_err());
+    }
+}
@@ -0,0 +1,87 @@
+use super::Buffer;
+use crate::error::DvimError;
+
+impl Buffer {
+    /// The 'autopairs' spec, pairs written `(:)` and joined by commas as in
+    /// vim's 'matchpairs'.
+    pub fn autopairs_spec(&self) -> String {
+        let pairs: Vec<String> = self
+            .autopairs
+            .iter()
+            .map(|(open, close)| format!("{open}:{close}"))
+            .collect();
+        pairs.join(",")
+    }
+
+    /// Replace 'autopairs' from a spec like `(:),":"`. An empty spec turns
+    /// pairing off.
+    pub fn set_autopairs(&mut self, spec: &str) -> Result<(), DvimError> {
+        self.autopairs = parse_pairs(spec).ok_or_else(|| DvimError::InvalidOption {
+            name: "autopairs".to_string(),
+            value: spec.to_string(),
+        })?;
+        Ok(())
+    }
+
+    /// The closer `c` opens with, if it's the opener of a pair.
+    pub fn pair_closer(&self, c: char) -> Option<char> {
+        self.autopairs
+            .iter()
+            .find(|(open, _)| *open == c)
+            .map(|(_, close)| *close)
+    }
+
+    /// Whether `c` closes one of the pairs.
+    pub fn is_pair_closer(&self, c: char) -> bool {
+        self.autopairs.iter().any(|(_, close)| *close == c)
+    }
+}
+
+pub(super) fn parse_pairs(spec: &str) -> Option<Vec<(char, char)>> {
+    if spec.is_empty() {
+        return Some(Vec::new());
+    }
+    spec.split(',')
+        .map(|pair| {
+            let mut chars = pair.chars();
+            match (chars.next(), chars.next(), chars.next(), chars.next()) {
+                (Some(open), Some(':'), Some(close), None) => Some((open, close)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::buffer_with_suffix;
+
+    #[test]
+    fn autopairs_follow_filetype() {
+        assert_eq!(
+            buffer_with_suffix("", ".rs").autopairs_spec(),
+            "(:),[:],{:},\":\""
+        );
+        assert_eq!(
+            buffer_with_suffix("", ".py").autopairs_spec(),
+            "(:),[:],{:},\":\",':'"
+        );
+        assert_eq!(
+            buffer_with_suffix("", ".txt").autopairs_spec(),
+            "(:),[:],{:}"
+        );
+    }
+
+    #[test]
+    fn set_autopairs() {
+        let mut buf = buffer_with_suffix("", ".txt");
+        buf.set_autopairs("<:>,*:*").unwrap();
+        assert_eq!(buf.pair_closer('<'), Some('>'));
+        assert!(buf.is_pair_closer('*'));
+        assert_eq!(buf.pair_closer('('), None);
+        assert!(buf.set_autopairs("(:)x").is_err());
+        assert_eq!(buf.autopairs_spec(), "<:>,*:*");
+        buf.set_autopairs("").unwrap();
+        assert_eq!(buf.pair_closer('<'), None);
+    }
+}
@@ -0,0 +1,243 @@
+//! The user's config file, `$XDG_CONFIG_HOME/dvim/config.toml`. Options go
+//! in an `[options]` table, and options for one filetype in a
+//! `[filetype.<name>]` table, which wins over `[options]`. Key mappings go
+//! in a `[keymap.<mode>]` table; they aren't remapped unless they ask to be.
+//! `colorscheme` names the theme to start with:
+//!
+//! ```toml
+//! colorscheme = "onedark"
+//!
+//! [options]
+//! shiftwidth = 2
+//! autoread = true
+//! mapleader = " "
+//!
+//! [filetype.go]
+//! shiftwidth = 8
+//!
+//! [keymap.normal]
+//! "<leader>w" = ":w<CR>"
+//! Q = { keys = "<leader>w", remap = true }
+//!
+//! [keymap.insert]
+//! jk = "<Esc>"
+//! ```
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::DvimError;
+use crate::mapping::MapMode;
+use crate::options::Value;
+
+/// Option settings as `(name, value)`, names not yet checked.
+pub type Settings = Vec<(String, Value)>;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    /// Settings applied at startup.
+    pub options: Settings,
+    /// Settings for buffers of each filetype, applied after `options`.
+    pub filetypes: HashMap<String, Settings>,
+    /// Key mappings, defined after `options` are set.
+    pub keymaps: Vec<KeymapSetting>,
+    /// The theme to start with.
+    pub colorscheme: Option<String>,
+}
+
+/// A mapping from a `[keymap.<mode>]` table, its keys not yet parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeymapSetting {
+    pub mode: MapMode,
+    pub lhs: String,
+    pub rhs: String,
+    /// Whether mappings apply to `rhs`.
+    pub remap: bool,
+}
+
+impl Config {
+    /// The directory with dvim's config in: under `$XDG_CONFIG_HOME`, or
+    /// `~/.config` when that isn't set.
+    pub fn dir() -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+        Some(base.join("dvim"))
+    }
+
+    /// Where the config file lives.
+    pub fn path() -> Option<PathBuf> {
+        Some(Self::dir()?.join("config.toml"))
+    }
+
+    /// Read the config file. Having none is the same as an empty one.
+    pub fn load() -> Result<Self, DvimError> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text, &path),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(DvimError::FileRead {
+                path: path.display().to_string(),
+                source: e,
+            }),
+        }
+    }
+
+    /// Parse config file text, read from `path`.
+    pub fn parse(text: &str, path: &Path) -> Result<Self, DvimError> {
+        let error = |message: String| DvimError::Config {
+            path: path.display().to_string(),
+            message,
+        };
+        let table: toml::Table = text
+            .parse()
+            .map_err(|e: toml::de::Error| error(e.message().to_string()))?;
+        let mut config = Self::default();
+        for (key, value) in table {
+            match (key.as_str(), value) {
+                ("options", toml::Value::Table(options)) => {
+                    config.options = settings(options).map_err(error)?;
+                }
+                ("filetype", toml::Value::Table(filetypes)) => {
+                    for (filetype, options) in filetypes {
+                        let toml::Value::Table(options) = options else {
+                            return Err(error(format!("'filetype.{filetype}' should be a table")));
+                        };
+                        let options = settings(options).map_err(error)?;
+                        config.filetypes.insert(filetype, options);
+                    }
+                }
+                ("keymap", toml::Value::Table(modes)) => {
+                    for (mode, keymap) in modes {
+                        let (Some(mode), toml::Value::Table(keymap)) =
+                            (MapMode::from_name(&mode), keymap)
+                        else {
+                            return Err(error(format!("'keymap.{mode}' is not a mode's keymap")));
+                        };
+                        for (lhs, rhs) in keymap {
+                            config
+                                .keymaps
+                                .push(keymap_setting(mode, lhs, rhs).map_err(error)?);
+                        }
+                    }
+                }
+                ("colorscheme", toml::Value::String(name)) => config.colorscheme = Some(name),
+                (key, _) => return Err(error(format!("unknown table '{key}'"))),
+            }
+        }
+        Ok(config)
+    }
+}
+
+fn settings(table: toml::Table) -> Result<Settings, String> {
+    table
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                toml::Value::Boolean(b) => Value::Bool(b),
+                toml::Value::Integer(n) if n >= 0 => Value::Number(n as usize),
+                toml::Value::String(s) => Value::String(s),
+                value => return Err(format!("invalid value for '{name}': {value}")),
+            };
+            Ok((name, value))
+        })
+        .collect()
+}
+
+/// A mapping, given as the keys to run or as `{ keys = "...", remap = true }`.
+fn keymap_setting(mode: MapMode, lhs: String, rhs: toml::Value) -> Result<KeymapSetting, String> {
+    let (rhs, remap) = match rhs {
+        toml::Value::String(keys) => (keys, false),
+        toml::Value::Table(mut table) => {
+            let keys = match table.remove("keys") {
+                Some(toml::Value::String(keys)) => keys,
+                _ => return Err(format!("mapping for '{lhs}' needs keys")),
+            };
+            let remap = match table.remove("remap") {
+                None => false,
+                Some(toml::Value::Boolean(remap)) => remap,
+                Some(value) => return Err(format!("invalid value for 'remap': {value}")),
+            };
+            if let Some(key) = table.keys().next() {
+                return Err(format!("unknown key '{key}' in mapping for '{lhs}'"));
+            }
+            (keys, remap)
+        }
+        value => return Err(format!("invalid mapping for '{lhs}': {value}")),
+    };
+    Ok(KeymapSetting {
+        mode,
+        lhs,
+        rhs,
+        remap,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Config, DvimError> {
+        Config::parse(text, Path::new("config.toml"))
+    }
+
+    #[test]
+    fn parse_options_and_filetypes() {
+        let config = parse(
+            "colorscheme = \"dark\"\n\n[options]\nshiftwidth = 2\nautoread = true\n\n[filetype.go]\nexpandtab = false\n",
+        )
+        .unwrap();
+        assert_eq!(config.colorscheme.as_deref(), Some("dark"));
+        assert_eq!(
+            config.options,
+            [
+                ("autoread".to_string(), Value::Bool(true)),
+                ("shiftwidth".to_string(), Value::Number(2)),
+            ]
+        );
+        assert_eq!(
+            config.filetypes["go"],
+            [("expandtab".to_string(), Value::Bool(false))]
+        );
+    }
+
+    #[test]
+    fn parse_errors_name_the_file() {
+        let err = parse("[options]\nshiftwidth = -1\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "error in 'config.toml': invalid value for 'shiftwidth': -1"
+        );
+        assert!(parse("[colours]\n").is_err());
+        assert!(parse("[keymap.operator]\nx = \"y\"\n").is_err());
+        assert!(parse("[keymap.normal]\nx = { remap = true }\n").is_err());
+        assert!(parse("shiftwidth = ").is_err());
+    }
+
+    #[test]
+    fn parse_keymaps() {
+        let config =
+            parse("[keymap.normal]\nQ = \"gq\"\n\n[keymap.insert]\njk = { keys = \"<Esc>\", remap = true }\n")
+                .unwrap();
+        assert_eq!(
+            config.keymaps,
+            [
+                KeymapSetting {
+                    mode: MapMode::Insert,
+                    lhs: "jk".to_string(),
+                    rhs: "<Esc>".to_string(),
+                    remap: true,
+                },
+                KeymapSetting {
+                    mode: MapMode::Normal,
+                    lhs: "Q".to_string(),
+                    rhs: "gq".to_string(),
+                    remap: false,
+                },
+            ]
+        );
+    }
+}
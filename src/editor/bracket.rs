@@ -0,0 +1,127 @@
+use super::Editor;
+use crate::buffer::is_bracket;
+
+impl Editor {
+    /// Jump to the bracket matching the one under the cursor, or the first
+    /// bracket after it on the line (`%`).
+    pub fn match_bracket(&mut self) -> bool {
+        let Some(line) = self.buffer.line(self.cursor_row) else {
+            return false;
+        };
+        let Some(col) = line
+            .chars()
+            .enumerate()
+            .skip(self.cursor_col)
+            .find_map(|(i, c)| is_bracket(c).then_some(i))
+        else {
+            return false;
+        };
+        let idx = self.buffer.char_index(self.cursor_row, col);
+        self.goto_char(self.buffer.matching_bracket(idx))
+    }
+
+    /// Jump to the [count]th enclosing unmatched `target` bracket (`[(`,
+    /// `[{`, `])`, `]}`).
+    pub fn goto_unmatched_bracket(&mut self, target: char, count: usize) -> bool {
+        let idx = self.buffer.char_index(self.cursor_row, self.cursor_col);
+        self.goto_char(self.buffer.unmatched_bracket(idx, target, count))
+    }
+
+    /// Jump to the line [count] percent of the way through the file (`N%`).
+    pub fn goto_percent(&mut self, percent: usize) {
+        let lines = self.max_row() + 1;
+        self.goto_line((percent.min(100) * lines).div_ceil(100));
+        self.goto_first_non_blank();
+    }
+
+    /// The cursor's bracket and its match as (row, col) pairs, for
+    /// highlighting. Only a match on screen counts, so a redraw never
+    /// searches past the window.
+    pub fn bracket_pair_at_cursor(&self, viewport_height: usize) -> Option<[(usize, usize); 2]> {
+        let idx = self.buffer.char_index(self.cursor_row, self.cursor_col);
+        if !self.buffer.char_at(idx).is_some_and(is_bracket) {
+            return None;
+        }
+        // Every line takes at least one screen row.
+        let lines = self.scroll_offset..self.scroll_offset + viewport_height;
+        let other = self.buffer.matching_bracket_in(idx, lines)?;
+        Some([
+            (self.cursor_row, self.cursor_col),
+            self.buffer.position(other),
+        ])
+    }
+
+    fn goto_char(&mut self, idx: Option<usize>) -> bool {
+        let Some(idx) = idx else {
+            return false;
+        };
+        (self.cursor_row, self.cursor_col) = self.buffer.position(idx);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_editor;
+
+    #[test]
+    fn percent_jumps_between_pairs() {
+        let mut ed = test_editor("if (a) {\n    b;\n}\n");
+        ed.cursor_col = 7;
+        assert!(ed.match_bracket());
+        assert_eq!((ed.cursor_row, ed.cursor_col), (2, 0));
+        assert!(ed.match_bracket());
+        assert_eq!((ed.cursor_row, ed.cursor_col), (0, 7));
+    }
+
+    #[test]
+    fn percent_searches_forward_on_line() {
+        let mut ed = test_editor("call(x)\n");
+        assert!(ed.match_bracket());
+        assert_eq!(ed.cursor_col, 6);
+    }
+
+    #[test]
+    fn percent_fails_without_bracket() {
+        let mut ed = test_editor("abc\n(x)\n");
+        assert!(!ed.match_bracket());
+        assert_eq!((ed.cursor_row, ed.cursor_col), (0, 0));
+    }
+
+    #[test]
+    fn unmatched_bracket_jumps() {
+        let mut ed = test_editor("f(a, {b}, c)\n");
+        ed.cursor_col = 10;
+        assert!(ed.goto_unmatched_bracket('(', 1));
+        assert_eq!(ed.cursor_col, 1);
+        assert!(ed.goto_unmatched_bracket(')', 1));
+        assert_eq!(ed.cursor_col, 11);
+        assert!(!ed.goto_unmatched_bracket('{', 1));
+    }
+
+    #[test]
+    fn goto_percent_of_file() {
+        let mut ed = test_editor(&"x\n".repeat(10));
+        ed.goto_percent(50);
+        assert_eq!(ed.cursor_row, 4);
+        ed.goto_percent(100);
+        assert_eq!(ed.cursor_row, 9);
+    }
+
+    #[test]
+    fn bracket_pair_only_on_bracket() {
+        let mut ed = test_editor("(ab)\n");
+        assert_eq!(ed.bracket_pair_at_cursor(10), Some([(0, 0), (0, 3)]));
+        ed.cursor_col = 1;
+        assert_eq!(ed.bracket_pair_at_cursor(10), None);
+    }
+
+    #[test]
+    fn bracket_pair_stays_on_screen() {
+        let mut ed = test_editor("{\na\nb\nc\n}\n");
+        ed.cursor_row = 4;
+        assert_eq!(ed.bracket_pair_at_cursor(5), Some([(4, 0), (0, 0)]));
+        ed.scroll_offset = 2;
+        assert_eq!(ed.bracket_pair_at_cursor(3), None);
+    }
+}
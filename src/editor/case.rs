@@ -0,0 +1,116 @@
+use super::Editor;
+use super::operator::Operator;
+
+/// `c` with its case changed by `op` (one of the case operators). Letters
+/// whose other case isn't a single character (`ß`) are left alone.
+fn convert(op: Operator, c: char) -> char {
+    let upper = match op {
+        Operator::Lowercase => false,
+        Operator::Uppercase => true,
+        Operator::ToggleCase => c.is_lowercase(),
+        _ => return c,
+    };
+    let converted: String = if upper {
+        c.to_uppercase().collect()
+    } else {
+        c.to_lowercase().collect()
+    };
+    let mut chars = converted.chars();
+    match (chars.next(), chars.next()) {
+        (Some(converted), None) => converted,
+        _ => c,
+    }
+}
+
+impl Editor {
+    /// Change the case of the characters from `from` up to (not including)
+    /// `to` (`gu`, `gU`, `g~`).
+    pub(super) fn change_case(&mut self, op: Operator, from: (usize, usize), to: (usize, usize)) {
+        let start = self.buffer.char_index(from.0, from.1);
+        let end = self.buffer.char_index(to.0, to.1);
+        for idx in start..end {
+            let Some(c) = self.buffer.char_at(idx) else {
+                break;
+            };
+            let converted = convert(op, c);
+            if converted != c {
+                let (row, col) = self.buffer.position(idx);
+                self.buffer.replace_char(row, col, converted);
+            }
+        }
+    }
+
+    /// Toggle the case of [count] characters from the cursor and move past
+    /// them (`~`). Fails on an empty line.
+    pub fn toggle_case_chars(&mut self, count: usize) -> bool {
+        let row = self.cursor_row;
+        let len = self.buffer.line_len(row);
+        if len == 0 {
+            return false;
+        }
+        let end = (self.cursor_col + count.max(1)).min(len);
+        self.change_case(Operator::ToggleCase, (row, self.cursor_col), (row, end));
+        self.set_change_marks((row, self.cursor_col), (row, end - 1));
+        self.cursor_col = end.min(len - 1);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{test_editor, type_keys};
+
+    #[test]
+    fn tilde_toggles_and_advances() {
+        let mut ed = test_editor("aBcd\n");
+        type_keys(&mut ed, "3~");
+        assert_eq!(ed.buffer.line(0).unwrap(), "AbCd");
+        assert_eq!(ed.cursor_col, 3);
+        type_keys(&mut ed, "5~");
+        assert_eq!(ed.buffer.line(0).unwrap(), "AbCD");
+        assert_eq!(ed.cursor_col, 3);
+    }
+
+    #[test]
+    fn case_operators_with_motions() {
+        let mut ed = test_editor("hello World\n");
+        type_keys(&mut ed, "gUw");
+        assert_eq!(ed.buffer.line(0).unwrap(), "HELLO World");
+        type_keys(&mut ed, "wg~e");
+        assert_eq!(ed.buffer.line(0).unwrap(), "HELLO wORLD");
+        type_keys(&mut ed, "0gu$");
+        assert_eq!(ed.buffer.line(0).unwrap(), "hello world");
+        assert_eq!(ed.cursor_col, 0);
+    }
+
+    #[test]
+    fn doubled_case_operators_act_on_lines() {
+        let mut ed = test_editor("ab\ncd\nef\n");
+        type_keys(&mut ed, "2gUU");
+        assert_eq!(ed.buffer.line(1).unwrap(), "CD");
+        type_keys(&mut ed, "gugu");
+        assert_eq!(ed.buffer.line(0).unwrap(), "ab");
+        type_keys(&mut ed, "jg~g~");
+        assert_eq!(ed.buffer.line(1).unwrap(), "cd");
+        type_keys(&mut ed, "jg~~");
+        assert_eq!(ed.buffer.line(2).unwrap(), "EF");
+    }
+
+    #[test]
+    fn visual_case_commands() {
+        let mut ed = test_editor("abc def\n");
+        type_keys(&mut ed, "veU");
+        assert_eq!(ed.buffer.line(0).unwrap(), "ABC def");
+        type_keys(&mut ed, "wve~");
+        assert_eq!(ed.buffer.line(0).unwrap(), "ABC DEF");
+        type_keys(&mut ed, "Vu");
+        assert_eq!(ed.buffer.line(0).unwrap(), "abc def");
+    }
+
+    #[test]
+    fn case_change_is_repeatable() {
+        let mut ed = test_editor("one two\n");
+        type_keys(&mut ed, "gUiww.");
+        assert_eq!(ed.buffer.line(0).unwrap(), "ONE TWO");
+    }
+}
@@ -1,4 +1,6 @@
 use super::Editor;
+use super::mapping::parse_map_command;
+use crate::error::DvimError;
 use crate::mode::Mode;
 
 impl Editor {
@@ -30,13 +32,48 @@ impl Editor {
 
         // Try to parse as a line number (e.g. `:123` jumps to line 123)
         if let Ok(n) = cmd.parse::<usize>() {
-            let target = if n == 0 {
-                0
-            } else {
-                (n - 1).min(self.max_row())
-            };
-            self.cursor_row = target;
-            self.clamp_cursor_col();
+            let from = (self.cursor_row, self.cursor_col);
+            self.goto_line(n);
+            if self.cursor_row != from.0 {
+                self.record_jump(from);
+            }
+            return Ok(());
+        }
+
+        let (range, rest) = self.parse_range(&cmd);
+        if let Some((keys, remap)) = [
+            ("normal ", true),
+            ("norm ", true),
+            ("normal! ", false),
+            ("norm! ", false),
+        ]
+        .iter()
+        .find_map(|&(name, remap)| rest.strip_prefix(name).map(|keys| (keys, remap)))
+        {
+            let (first, last) = range.unwrap_or((self.cursor_row, self.cursor_row));
+            self.normal_on_lines(first, last, keys, remap);
+            return Ok(());
+        }
+
+        let (name, args) = cmd.split_once(' ').unwrap_or((&cmd, ""));
+        if matches!(name, "set" | "se" | "setlocal" | "setl") {
+            self.set_command(args)?;
+            return Ok(());
+        }
+        if let Some((modes, command)) = parse_map_command(name) {
+            self.map_command(&modes, command, args)?;
+            return Ok(());
+        }
+        if matches!(name, "colorscheme" | "colo") {
+            self.colorscheme_command(args)?;
+            return Ok(());
+        }
+        if matches!(name, "highlight" | "hi") {
+            self.highlight_command(args)?;
+            return Ok(());
+        }
+        if name == "action" {
+            self.action_command(args.trim())?;
             return Ok(());
         }
 
@@ -47,19 +84,60 @@ impl Editor {
                 self.buffer.write()?;
                 self.quit();
             }
-            "w!" => {
-                let _ = self.buffer.write();
-            }
+            "w!" => self.buffer.write_force()?,
             "q!" => self.quit(),
             "wq!" => {
-                let _ = self.buffer.write();
+                self.buffer.write_force()?;
                 self.quit();
             }
+            "e" => {
+                if self.buffer.is_modified() {
+                    return Err(DvimError::UnsavedChanges.into());
+                }
+                self.reload()?;
+            }
+            "e!" => self.reload()?,
+            "marks" => self.status_message = Some(self.marks_listing()),
+            "ju" | "jumps" => self.status_message = Some(self.jumps_listing()),
+            "changes" => self.status_message = Some(self.changes_listing()),
+            "sus" | "suspend" | "sus!" | "suspend!" | "st" | "stop" | "st!" | "stop!" => {
+                self.suspend = true;
+            }
             _ => {}
         }
 
         Ok(())
     }
+
+    /// Split a leading line range (`%`, `.`, `$`, `N` or `A,B` of those)
+    /// off `cmd`, as 0-indexed rows.
+    fn parse_range<'a>(&self, cmd: &'a str) -> (Option<(usize, usize)>, &'a str) {
+        if let Some(rest) = cmd.strip_prefix('%') {
+            return (Some((0, self.max_row())), rest);
+        }
+        let Some((first, rest)) = self.parse_address(cmd) else {
+            return (None, cmd);
+        };
+        if let Some(after_comma) = rest.strip_prefix(',')
+            && let Some((last, rest)) = self.parse_address(after_comma)
+        {
+            return (Some((first.min(last), first.max(last))), rest);
+        }
+        (Some((first, first)), rest)
+    }
+
+    /// A single line address at the start of `cmd`.
+    fn parse_address<'a>(&self, cmd: &'a str) -> Option<(usize, &'a str)> {
+        if let Some(rest) = cmd.strip_prefix('.') {
+            return Some((self.cursor_row, rest));
+        }
+        if let Some(rest) = cmd.strip_prefix('$') {
+            return Some((self.max_row(), rest));
+        }
+        let digits = cmd.chars().take_while(char::is_ascii_digit).count();
+        let n: usize = cmd[..digits].parse().ok()?;
+        Some((n.saturating_sub(1).min(self.max_row()), &cmd[digits..]))
+    }
 }
 
 #[cfg(test)]
@@ -218,4 +296,103 @@ mod tests {
         ed.execute_command().unwrap();
         assert_eq!(ed.cursor_row, 0);
     }
+
+    #[test]
+    fn execute_w_refuses_when_file_changed_on_disk() {
+        let mut ed = test_editor("hello\n");
+        std::fs::write(ed.buffer.filename(), "external\n").unwrap();
+        ed.enter_command_mode();
+        ed.command_push('w');
+        assert!(ed.execute_command().is_err());
+        let on_disk = std::fs::read_to_string(ed.buffer.filename()).unwrap();
+        assert_eq!(on_disk, "external\n");
+    }
+
+    #[test]
+    fn execute_w_bang_overwrites_file_changed_on_disk() {
+        let mut ed = test_editor("hello\n");
+        std::fs::write(ed.buffer.filename(), "external\n").unwrap();
+        ed.enter_command_mode();
+        ed.command_push('w');
+        ed.command_push('!');
+        ed.execute_command().unwrap();
+        let on_disk = std::fs::read_to_string(ed.buffer.filename()).unwrap();
+        assert_eq!(on_disk, "hello\n");
+    }
+
+    #[test]
+    fn execute_e_bang_reloads() {
+        let mut ed = test_editor("hello\n");
+        ed.insert_char('X');
+        std::fs::write(ed.buffer.filename(), "external\n").unwrap();
+        ed.enter_command_mode();
+        ed.command_push('e');
+        ed.command_push('!');
+        ed.execute_command().unwrap();
+        assert_eq!(ed.buffer.line(0).unwrap(), "external");
+        assert!(!ed.buffer.is_modified());
+    }
+
+    #[test]
+    fn execute_e_refuses_with_unsaved_changes() {
+        let mut ed = test_editor("hello\n");
+        ed.insert_char('X');
+        ed.enter_command_mode();
+        ed.command_push('e');
+        assert!(ed.execute_command().is_err());
+        assert_eq!(ed.buffer.line(0).unwrap(), "Xhello");
+    }
+
+    #[test]
+    fn execute_set_iskeyword() {
+        let mut ed = test_editor("hello\n");
+        ed.command_buffer = "set iskeyword=@,-".to_string();
+        ed.execute_command().unwrap();
+        ed.command_buffer = "set iskeyword?".to_string();
+        ed.execute_command().unwrap();
+        assert_eq!(ed.status_message.as_deref(), Some("  iskeyword=@,-"));
+        ed.command_buffer = "set iskeyword=z-a".to_string();
+        assert!(ed.execute_command().is_err());
+    }
+
+    #[test]
+    fn execute_set_indent_options() {
+        let mut ed = test_editor("hello\n");
+        ed.command_buffer = "set sw=2".to_string();
+        ed.execute_command().unwrap();
+        ed.command_buffer = "set noet".to_string();
+        ed.execute_command().unwrap();
+        assert_eq!((ed.buffer.shiftwidth(), ed.buffer.expandtab()), (2, false));
+        ed.command_buffer = "set ts?".to_string();
+        ed.execute_command().unwrap();
+        assert_eq!(ed.status_message.as_deref(), Some("  tabstop=8"));
+        ed.command_buffer = "set shiftwidth=x".to_string();
+        assert!(ed.execute_command().is_err());
+    }
+
+    #[test]
+    fn execute_set_indentexpr() {
+        let mut ed = test_editor("hello\n");
+        ed.command_buffer = "set indentexpr=brackets".to_string();
+        ed.execute_command().unwrap();
+        ed.command_buffer = "set indentexpr?".to_string();
+        ed.execute_command().unwrap();
+        assert_eq!(ed.status_message.as_deref(), Some("  indentexpr=brackets"));
+        ed.command_buffer = "set indentexpr=nope".to_string();
+        assert!(ed.execute_command().is_err());
+        ed.command_buffer = "set nosi".to_string();
+        ed.execute_command().unwrap();
+        ed.command_buffer = "set si?".to_string();
+        ed.execute_command().unwrap();
+        assert_eq!(ed.status_message.as_deref(), Some("  nosmartindent"));
+    }
+
+    #[test]
+    fn execute_suspend_asks_to_stop() {
+        let mut ed = test_editor("hello\n");
+        ed.buffer.insert_char(0, 0, 'X');
+        ed.command_buffer = "sus".to_string();
+        ed.execute_command().unwrap();
+        assert!(ed.suspend && ed.running);
+    }
 }
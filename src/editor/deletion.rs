@@ -1,4 +1,6 @@
 use super::Editor;
+use super::motion::Motion;
+use super::operator::Operator;
 
 impl Editor {
     pub fn delete_line(&mut self) {
@@ -10,73 +12,59 @@ impl Editor {
         self.clamp_cursor_col();
     }
 
-    pub fn delete_char_at_cursor(&mut self) {
-        if self.buffer.line_len(self.cursor_row) == 0 {
-            return;
-        }
-        self.buffer.delete_char_at(self.cursor_row, self.cursor_col);
-        self.clamp_cursor_col();
-    }
-
-    pub fn delete_to_end_of_line(&mut self) {
+    /// Delete [count] characters under and after the cursor (`x`), never
+    /// reaching past the end of the line. Returns false on an empty line.
+    pub fn delete_chars_at_cursor(&mut self, count: usize) -> bool {
         let line_len = self.buffer.line_len(self.cursor_row);
         if line_len == 0 {
-            return;
+            return false;
         }
-        let count = line_len - self.cursor_col;
+        let count = count.min(line_len.saturating_sub(self.cursor_col));
+        let end = (self.cursor_row, self.cursor_col + count);
+        let text = self
+            .buffer
+            .text_range((self.cursor_row, self.cursor_col), end);
+        self.store_register(text, false, false);
         for _ in 0..count {
             self.buffer.delete_char_at(self.cursor_row, self.cursor_col);
         }
         self.clamp_cursor_col();
+        true
     }
 
-    pub fn delete_word(&mut self) {
-        let line = match self.buffer.line(self.cursor_row) {
-            Some(l) => l,
-            None => return,
-        };
-        let chars: Vec<char> = line.chars().collect();
-        let len = chars.len();
-        if self.cursor_col >= len {
-            return;
-        }
-
-        let classify = |c: char| -> u8 {
-            if c.is_alphanumeric() || c == '_' {
-                0 // word
-            } else if c.is_whitespace() {
-                2 // whitespace
-            } else {
-                1 // punctuation
-            }
-        };
-
-        let start = self.cursor_col;
-        let start_class = classify(chars[start]);
-        let mut pos = start;
-
-        // Skip over the current class of characters
-        while pos < len && classify(chars[pos]) == start_class {
-            pos += 1;
+    /// Delete [count] characters before the cursor (`X`), never reaching
+    /// past the start of the line. Returns false in the first column.
+    pub fn delete_chars_before_cursor(&mut self, count: usize) -> bool {
+        if self.cursor_col == 0 {
+            return false;
         }
-
-        // If the current class was not whitespace, also skip trailing whitespace
-        if start_class != 2 {
-            while pos < len && chars[pos].is_whitespace() {
-                pos += 1;
-            }
+        let start = self.cursor_col.saturating_sub(count);
+        let text = self
+            .buffer
+            .text_range((self.cursor_row, start), (self.cursor_row, self.cursor_col));
+        self.store_register(text, false, false);
+        for _ in start..self.cursor_col {
+            self.buffer.delete_char_at(self.cursor_row, start);
         }
+        self.cursor_col = start;
+        self.clamp_cursor_col();
+        true
+    }
 
-        let count = pos - start;
-        for _ in 0..count {
-            self.buffer.delete_char_at(self.cursor_row, self.cursor_col);
+    /// Delete to the end of the line, and [count] - 1 more lines (`D`).
+    /// Returns false when there is nothing to delete.
+    pub fn delete_to_end_of_line(&mut self, count: usize) -> bool {
+        if count <= 1 && self.buffer.line_len(self.cursor_row) == 0 {
+            return false;
         }
-        self.clamp_cursor_col();
+        self.apply_operator(Operator::Delete, Motion::LineEnd, Some(count))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::motion::Motion;
+    use super::super::operator::Operator;
     use super::super::test_editor;
 
     #[test]
@@ -109,7 +97,7 @@ mod tests {
     fn delete_char_at_cursor_mid() {
         let mut ed = test_editor("hello\n");
         ed.cursor_col = 2;
-        ed.delete_char_at_cursor();
+        ed.delete_chars_at_cursor(1);
         assert_eq!(ed.buffer.line(0).unwrap(), "helo");
         assert_eq!(ed.cursor_col, 2);
     }
@@ -118,7 +106,7 @@ mod tests {
     fn delete_char_at_cursor_last_char() {
         let mut ed = test_editor("abc\n");
         ed.cursor_col = 2;
-        ed.delete_char_at_cursor();
+        ed.delete_chars_at_cursor(1);
         assert_eq!(ed.buffer.line(0).unwrap(), "ab");
         assert_eq!(ed.cursor_col, 1); // clamped
     }
@@ -127,7 +115,7 @@ mod tests {
     fn delete_char_at_cursor_empty_line_does_nothing() {
         let mut ed = test_editor("abc\n\ndef\n");
         ed.cursor_row = 1;
-        ed.delete_char_at_cursor();
+        assert!(!ed.delete_chars_at_cursor(1));
         assert_eq!(ed.buffer.line(1).unwrap(), "");
     }
 
@@ -135,7 +123,7 @@ mod tests {
     fn delete_to_end_of_line_mid() {
         let mut ed = test_editor("hello world\n");
         ed.cursor_col = 5;
-        ed.delete_to_end_of_line();
+        ed.delete_to_end_of_line(1);
         assert_eq!(ed.buffer.line(0).unwrap(), "hello");
     }
 
@@ -143,7 +131,7 @@ mod tests {
     fn delete_to_end_of_line_start() {
         let mut ed = test_editor("hello\n");
         ed.cursor_col = 0;
-        ed.delete_to_end_of_line();
+        ed.delete_to_end_of_line(1);
         assert_eq!(ed.buffer.line(0).unwrap(), "");
     }
 
@@ -151,15 +139,27 @@ mod tests {
     fn delete_to_end_of_line_empty() {
         let mut ed = test_editor("abc\n\ndef\n");
         ed.cursor_row = 1;
-        ed.delete_to_end_of_line();
+        assert!(!ed.delete_to_end_of_line(1));
         assert_eq!(ed.buffer.line(1).unwrap(), "");
     }
 
+    #[test]
+    fn delete_chars_before_cursor_stops_at_start_of_line() {
+        let mut ed = test_editor("abcdef\n");
+        ed.cursor_col = 2;
+        assert!(ed.delete_chars_before_cursor(5));
+        assert_eq!(ed.buffer.line(0).unwrap(), "cdef");
+        assert_eq!(ed.cursor_col, 0);
+        assert_eq!(ed.registers.get('"').unwrap().text, "ab");
+        assert!(!ed.delete_chars_before_cursor(1));
+        assert_eq!(ed.buffer.line(0).unwrap(), "cdef");
+    }
+
     #[test]
     fn delete_word_basic() {
         let mut ed = test_editor("hello world\n");
         ed.cursor_col = 0;
-        ed.delete_word();
+        ed.apply_operator(Operator::Delete, Motion::WordForward, None);
         assert_eq!(ed.buffer.line(0).unwrap(), "world");
     }
 
@@ -167,7 +167,7 @@ mod tests {
     fn delete_word_punctuation() {
         let mut ed = test_editor("foo.bar\n");
         ed.cursor_col = 0;
-        ed.delete_word();
+        ed.apply_operator(Operator::Delete, Motion::WordForward, None);
         assert_eq!(ed.buffer.line(0).unwrap(), ".bar");
     }
 
@@ -175,7 +175,25 @@ mod tests {
     fn delete_word_at_end() {
         let mut ed = test_editor("hello\n");
         ed.cursor_col = 3;
-        ed.delete_word();
+        ed.apply_operator(Operator::Delete, Motion::WordForward, None);
         assert_eq!(ed.buffer.line(0).unwrap(), "hel");
     }
+
+    #[test]
+    fn delete_chars_at_cursor_stops_at_end_of_line() {
+        let mut ed = test_editor("abcdef\n");
+        ed.cursor_col = 4;
+        ed.delete_chars_at_cursor(5);
+        assert_eq!(ed.buffer.line(0).unwrap(), "abcd");
+        assert_eq!(ed.cursor_col, 3);
+    }
+
+    #[test]
+    fn delete_to_end_of_line_with_count() {
+        let mut ed = test_editor("hello\nworld\nagain\n");
+        ed.cursor_col = 2;
+        ed.delete_to_end_of_line(2);
+        assert_eq!(ed.buffer.line(0).unwrap(), "he");
+        assert_eq!(ed.buffer.line(1).unwrap(), "again");
+    }
 }
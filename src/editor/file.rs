@@ -0,0 +1,98 @@
+use super::Editor;
+
+impl Editor {
+    /// Check whether the file was changed on disk behind our back. With
+    /// 'autoread' and no unsaved edits the buffer is reloaded silently;
+    /// otherwise a warning is left in the status bar.
+    pub fn check_file_changed(&mut self) {
+        if !self.buffer.changed_on_disk() {
+            return;
+        }
+        if self.global_options.autoread && !self.buffer.is_modified() {
+            if let Err(e) = self.reload() {
+                self.status_message = Some(e.to_string());
+            }
+            return;
+        }
+        self.status_message = Some(format!(
+            "\"{}\" file changed on disk; :e! to reload, :w! to overwrite",
+            self.buffer.filename().display()
+        ));
+    }
+
+    /// With 'autosave', write the buffer when the terminal loses focus if
+    /// it has unsaved edits.
+    pub fn focus_lost(&mut self) {
+        if self.global_options.autosave
+            && self.buffer.is_modified()
+            && let Err(e) = self.buffer.write()
+        {
+            self.status_message = Some(e.to_string());
+        }
+    }
+
+    /// Re-read the buffer from disk (`:e!`), keeping the cursor in range.
+    pub fn reload(&mut self) -> anyhow::Result<()> {
+        self.buffer.reload()?;
+        self.cursor_row = self.cursor_row.min(self.max_row());
+        self.clamp_cursor_col();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_editor;
+
+    #[test]
+    fn check_file_changed_warns_when_modified() {
+        let mut ed = test_editor("hello\n");
+        ed.global_options.autoread = true;
+        ed.insert_char('X');
+        std::fs::write(ed.buffer.filename(), "external\n").unwrap();
+        ed.check_file_changed();
+        assert!(ed.status_message.unwrap().contains("file changed on disk"));
+        assert_eq!(ed.buffer.line(0).unwrap(), "Xhello");
+    }
+
+    #[test]
+    fn check_file_changed_warns_without_autoread() {
+        let mut ed = test_editor("hello\n");
+        std::fs::write(ed.buffer.filename(), "external\n").unwrap();
+        ed.check_file_changed();
+        assert!(ed.status_message.is_some());
+        assert_eq!(ed.buffer.line(0).unwrap(), "hello");
+    }
+
+    #[test]
+    fn check_file_changed_autoreads_unmodified_buffer() {
+        let mut ed = test_editor("one\ntwo\nthree\n");
+        ed.global_options.autoread = true;
+        ed.cursor_row = 2;
+        std::fs::write(ed.buffer.filename(), "x\n").unwrap();
+        ed.check_file_changed();
+        assert!(ed.status_message.is_none());
+        assert_eq!(ed.buffer.line(0).unwrap(), "x");
+        assert_eq!(ed.cursor_row, 0);
+    }
+
+    #[test]
+    fn focus_lost_writes_with_autosave() {
+        let mut ed = test_editor("hello\n");
+        ed.insert_char('X');
+        ed.focus_lost();
+        assert!(ed.buffer.is_modified());
+        ed.global_options.autosave = true;
+        ed.focus_lost();
+        assert!(!ed.buffer.is_modified());
+        let written = std::fs::read_to_string(ed.buffer.filename()).unwrap();
+        assert_eq!(written, "Xhello\n");
+    }
+
+    #[test]
+    fn check_file_changed_ignores_unchanged_file() {
+        let mut ed = test_editor("hello\n");
+        ed.check_file_changed();
+        assert!(ed.status_message.is_none());
+    }
+}
@@ -0,0 +1,158 @@
+//! `:colorscheme` and `:highlight`: switching themes, and looking at or
+//! changing one highlight group.
+
+use super::Editor;
+use crate::error::DvimError;
+use crate::theme::{self, Highlight, Theme};
+
+impl Editor {
+    /// Switch to the theme called `name`.
+    pub fn set_colorscheme(&mut self, name: &str) -> Result<(), DvimError> {
+        let truecolor = self.theme.truecolor;
+        self.theme = Theme::load(name)?;
+        self.theme.truecolor = truecolor;
+        Ok(())
+    }
+
+    /// `:colorscheme [name]`: show the theme's name, or switch theme.
+    pub(super) fn colorscheme_command(&mut self, args: &str) -> Result<(), DvimError> {
+        match args.trim() {
+            "" => self.status_message = Some(self.theme.name.clone()),
+            name => self.set_colorscheme(name)?,
+        }
+        Ok(())
+    }
+
+    /// `:highlight` lists every group, `:hi Group` shows one, and
+    /// `:hi Group guifg=... guibg=... gui=...` changes it. `:hi link A B`
+    /// makes A look like B, `:hi clear Group` makes it plain, and `:hi
+    /// clear` goes back to the theme as loaded.
+    pub(super) fn highlight_command(&mut self, args: &str) -> Result<(), DvimError> {
+        let args: Vec<&str> = args.split_whitespace().collect();
+        match args.as_slice() {
+            [] => {
+                let lines: Vec<String> = self
+                    .theme
+                    .group_names()
+                    .into_iter()
+                    .map(|name| self.describe_group(name))
+                    .collect();
+                self.status_message = Some(lines.join("\n"));
+            }
+            ["clear"] => {
+                let name = self.theme.name.clone();
+                self.set_colorscheme(&name)?;
+            }
+            ["clear", group] => self.theme.set(group, Highlight::default()),
+            ["link", from, to] => {
+                let highlight = match *to {
+                    "NONE" => Highlight::default(),
+                    to => Highlight {
+                        link: Some(to.to_string()),
+                        ..Highlight::default()
+                    },
+                };
+                self.theme.set(from, highlight);
+            }
+            [group] => {
+                if self.theme.get(group).is_none() {
+                    return Err(DvimError::UnknownGroup(group.to_string()));
+                }
+                self.status_message = Some(self.describe_group(group));
+            }
+            [group, settings @ ..] => {
+                // Setting colors breaks any link the group had.
+                let mut highlight = self.theme.get(group).cloned().unwrap_or_default();
+                highlight.link = None;
+                for &setting in settings {
+                    set_highlight_key(&mut highlight, setting)?;
+                }
+                self.theme.set(group, highlight);
+            }
+        }
+        Ok(())
+    }
+
+    fn describe_group(&self, name: &str) -> String {
+        let highlight = self.theme.get(name).cloned().unwrap_or_default();
+        format!("{name:<16}{}", highlight.describe())
+    }
+}
+
+/// Apply one `key=value` of `:hi` to `highlight`. The gui and cterm keys
+/// are the same: colors are drawn as near as the terminal can get.
+fn set_highlight_key(highlight: &mut Highlight, setting: &str) -> Result<(), DvimError> {
+    let invalid = || DvimError::InvalidArgument(setting.to_string());
+    let (key, value) = setting.split_once('=').ok_or_else(invalid)?;
+    let color = || match value {
+        "NONE" => Ok(None),
+        value => theme::parse_color(value).map(Some).ok_or_else(invalid),
+    };
+    match key {
+        "guifg" | "ctermfg" => highlight.fg = color()?,
+        "guibg" | "ctermbg" => highlight.bg = color()?,
+        "gui" | "cterm" => {
+            highlight.modifiers = theme::parse_attributes(value).ok_or_else(invalid)?
+        }
+        _ => return Err(invalid()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Editor, test_editor};
+    use ratatui::style::{Color, Modifier};
+
+    fn run(ed: &mut Editor, cmd: &str) -> anyhow::Result<()> {
+        ed.command_buffer = cmd.to_string();
+        ed.execute_command()
+    }
+
+    #[test]
+    fn hi_shows_and_changes_groups() {
+        let mut ed = test_editor("x\n");
+        run(&mut ed, "hi LineNr").unwrap();
+        assert_eq!(
+            ed.status_message.as_deref(),
+            Some("LineNr          fg=DarkGray")
+        );
+        run(&mut ed, "hi LineNr guifg=#ff8000 gui=bold,italic").unwrap();
+        let line_nr = ed.theme.resolve("LineNr");
+        assert_eq!(line_nr.fg, Some(Color::Rgb(255, 128, 0)));
+        assert_eq!(line_nr.modifiers, Modifier::BOLD | Modifier::ITALIC);
+        run(&mut ed, "hi link ModeInsert LineNr").unwrap();
+        assert_eq!(ed.theme.resolve("ModeInsert"), line_nr);
+        run(&mut ed, "hi clear").unwrap();
+        assert_eq!(ed.theme.resolve("LineNr").fg, Some(Color::DarkGray));
+    }
+
+    #[test]
+    fn hi_errors() {
+        let mut ed = test_editor("x\n");
+        let err = |ed: &mut Editor, cmd| run(ed, cmd).unwrap_err().to_string();
+        assert_eq!(
+            err(&mut ed, "hi Nosuch"),
+            "highlight group not found: Nosuch"
+        );
+        assert_eq!(
+            err(&mut ed, "hi LineNr guifg=nocolor"),
+            "invalid argument: guifg=nocolor"
+        );
+        assert_eq!(err(&mut ed, "hi LineNr blink"), "invalid argument: blink");
+    }
+
+    #[test]
+    fn colorscheme_switches_themes() {
+        let mut ed = test_editor("x\n");
+        run(&mut ed, "colorscheme").unwrap();
+        assert_eq!(ed.status_message.as_deref(), Some("default"));
+        assert_eq!(
+            run(&mut ed, "colo ../nosuch").unwrap_err().to_string(),
+            "cannot find color scheme '../nosuch'"
+        );
+        run(&mut ed, "hi Visual guibg=Red").unwrap();
+        run(&mut ed, "colorscheme default").unwrap();
+        assert_eq!(ed.theme.resolve("Visual").bg, Some(Color::Gray));
+    }
+}
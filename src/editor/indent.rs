@@ -0,0 +1,90 @@
+use super::Editor;
+use super::operator::Operator;
+
+impl Editor {
+    /// Apply one of the indent operators (`>`, `<`, `=`) to rows
+    /// `first..=last`, leaving the cursor on the first non-blank of the
+    /// first row.
+    pub(super) fn indent_lines(&mut self, op: Operator, first: usize, last: usize) {
+        let sw = self.buffer.shiftwidth();
+        match op {
+            Operator::Reindent => self.buffer.reindent(first, last),
+            _ => {
+                let widths: Vec<usize> = (first..=last)
+                    .map(|row| {
+                        let width = self.buffer.indent_width(row);
+                        // Shifting never adds indent to empty lines.
+                        if self.buffer.line_len(row) == 0 {
+                            width
+                        } else if op == Operator::ShiftRight {
+                            width + sw
+                        } else {
+                            width.saturating_sub(sw)
+                        }
+                    })
+                    .collect();
+                self.buffer.set_indents(first, &widths);
+            }
+        }
+        let end = (last, self.buffer.line_len(last).saturating_sub(1));
+        self.set_change_marks((first, 0), end);
+        self.cursor_row = first;
+        self.goto_first_non_blank();
+
+        let lines = last - first + 1;
+        if lines > 2 {
+            self.status_message = Some(match op {
+                Operator::ShiftRight => format!("{lines} lines >ed 1 time"),
+                Operator::ShiftLeft => format!("{lines} lines <ed 1 time"),
+                _ => format!("{lines} lines indented "),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{test_editor, type_keys};
+
+    #[test]
+    fn shift_lines_with_count() {
+        let mut ed = test_editor("a\n\nb\nc\n");
+        type_keys(&mut ed, "3>>");
+        assert_eq!(ed.buffer.line(0).unwrap(), "    a");
+        assert_eq!(ed.buffer.line(1).unwrap(), "");
+        assert_eq!(ed.buffer.line(2).unwrap(), "    b");
+        assert_eq!(ed.buffer.line(3).unwrap(), "c");
+        assert_eq!(ed.cursor_col, 4);
+        assert_eq!(ed.status_message.as_deref(), Some("3 lines >ed 1 time"));
+        type_keys(&mut ed, "<j");
+        assert_eq!(ed.buffer.line(0).unwrap(), "a");
+        type_keys(&mut ed, "<<");
+        assert_eq!(ed.buffer.line(0).unwrap(), "a");
+    }
+
+    #[test]
+    fn shift_follows_shiftwidth() {
+        let mut ed = test_editor("a\n");
+        ed.buffer.set_shiftwidth(2).unwrap();
+        type_keys(&mut ed, ">>.");
+        assert_eq!(ed.buffer.line(0).unwrap(), "    a");
+    }
+
+    #[test]
+    fn shift_visual_selection() {
+        let mut ed = test_editor("a\nb\nc\n");
+        type_keys(&mut ed, "vj>");
+        assert_eq!(ed.buffer.line(1).unwrap(), "    b");
+        assert_eq!(ed.buffer.line(2).unwrap(), "c");
+    }
+
+    #[test]
+    fn reindent_operator() {
+        let mut ed = test_editor("a\n      b\n  c\n");
+        type_keys(&mut ed, "j=j");
+        assert_eq!(ed.buffer.line(1).unwrap(), "b");
+        assert_eq!(ed.buffer.line(2).unwrap(), "c");
+        type_keys(&mut ed, "gg>>j==");
+        assert_eq!(ed.buffer.line(1).unwrap(), "    b");
+    }
+}
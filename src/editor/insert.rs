@@ -1,6 +1,17 @@
 use super::Editor;
+use super::literal::Literal;
+use crate::buffer::CharClass;
 use crate::mode::Mode;
 
+/// An insert-mode key waiting for the one after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertPrefix {
+    /// `Ctrl-r`, waiting for a register name.
+    Register,
+    /// `Ctrl-v`, waiting for the character to insert.
+    Literal(Literal),
+}
+
 impl Editor {
     pub fn enter_insert_mode(&mut self) {
         self.mode = Mode::Insert;
@@ -20,15 +31,19 @@ impl Editor {
         self.cursor_row += 1;
         self.cursor_col = 0;
         self.mode = Mode::Insert;
+        self.autoindent_line();
     }
 
     pub fn enter_insert_mode_open_above(&mut self) {
         self.buffer.insert_newline(self.cursor_row, 0);
         self.cursor_col = 0;
         self.mode = Mode::Insert;
+        self.autoindent_line();
     }
 
     pub fn exit_insert_mode(&mut self) {
+        self.drop_unused_autoindent();
+        self.set_mark_at('^', (self.cursor_row, self.cursor_col));
         self.mode = Mode::Normal;
         if self.cursor_col > 0 {
             self.cursor_col -= 1;
@@ -37,15 +52,66 @@ impl Editor {
     }
 
     pub fn insert_char(&mut self, ch: char) {
+        let at_line_start = self
+            .buffer
+            .line(self.cursor_row)
+            .unwrap_or_default()
+            .chars()
+            .take(self.cursor_col)
+            .all(char::is_whitespace);
         self.buffer
             .insert_char(self.cursor_row, self.cursor_col, ch);
         self.cursor_col += 1;
+        self.autoindented = false;
+        // A closing bracket typed at the start of a line can change its indent.
+        if at_line_start
+            && let Some(indenter) = self.buffer.indenter()
+            && indenter.reindents_on(ch)
+        {
+            let width = indenter.indent(&self.buffer, self.cursor_row);
+            self.buffer.set_indent(self.cursor_row, width);
+            self.cursor_col = self.indent_chars(self.cursor_row) + 1;
+        }
     }
 
     pub fn insert_newline(&mut self) {
+        self.drop_unused_autoindent();
         self.buffer.insert_newline(self.cursor_row, self.cursor_col);
         self.cursor_row += 1;
         self.cursor_col = 0;
+        self.autoindent_line();
+    }
+
+    /// Indent the cursor line for typing on, replacing any whitespace it
+    /// starts with, and put the cursor after the indent.
+    fn autoindent_line(&mut self) {
+        let Some(indenter) = self.buffer.indenter() else {
+            return;
+        };
+        let width = indenter.indent(&self.buffer, self.cursor_row);
+        self.buffer.set_indent(self.cursor_row, width);
+        self.cursor_col = self.indent_chars(self.cursor_row);
+        self.autoindented = width > 0;
+    }
+
+    /// An indent added for a new line is taken away again if nothing gets
+    /// typed on the line.
+    pub(super) fn drop_unused_autoindent(&mut self) {
+        if self.autoindented && self.buffer.is_blank(self.cursor_row) {
+            self.buffer.set_indent(self.cursor_row, 0);
+            self.cursor_col = 0;
+        }
+        self.autoindented = false;
+    }
+
+    /// How many whitespace characters `row` starts with.
+    fn indent_chars(&self, row: usize) -> usize {
+        self.buffer
+            .line(row)
+            .unwrap_or_default()
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .count()
     }
 
     pub fn delete_char_back(&mut self) {
@@ -55,12 +121,139 @@ impl Editor {
         self.cursor_row = new_line;
         self.cursor_col = new_col;
     }
+
+    /// Delete the character under the cursor (`<Del>`). At the end of a
+    /// line, join the next line onto it.
+    pub fn delete_char_forward(&mut self) {
+        let (row, col) = (self.cursor_row, self.cursor_col);
+        if col < self.buffer.line_len(row) {
+            self.buffer.delete_char_at(row, col);
+        } else if row < self.max_row() {
+            self.buffer.delete_range((row, col), (row + 1, 0));
+        }
+    }
+
+    /// Delete the word before the cursor (`Ctrl-w`): any blanks, then a run
+    /// of keyword or punctuation characters. At the start of a line, join
+    /// it onto the line above.
+    pub fn delete_word_back(&mut self) {
+        let (row, col) = (self.cursor_row, self.cursor_col);
+        if col == 0 {
+            self.delete_char_back();
+            return;
+        }
+        let line = self.buffer.line(row).unwrap_or_default();
+        let classes: Vec<CharClass> = line
+            .chars()
+            .take(col)
+            .map(|c| self.char_class(c, false))
+            .collect();
+        let mut start = col;
+        while start > 0 && classes[start - 1] == CharClass::Blank {
+            start -= 1;
+        }
+        if let Some(&word) = start.checked_sub(1).map(|i| &classes[i]) {
+            while start > 0 && classes[start - 1] == word {
+                start -= 1;
+            }
+        }
+        self.buffer.delete_range((row, start), (row, col));
+        self.cursor_col = start;
+    }
+
+    /// Delete the text before the cursor on its line (`Ctrl-u`), keeping
+    /// the indent unless the cursor is already in it. At the start of a
+    /// line, join it onto the line above.
+    pub fn delete_line_back(&mut self) {
+        let (row, col) = (self.cursor_row, self.cursor_col);
+        if col == 0 {
+            self.delete_char_back();
+            return;
+        }
+        let indent = self.indent_chars(row);
+        let start = if col > indent { indent } else { 0 };
+        self.buffer.delete_range((row, start), (row, col));
+        self.cursor_col = start;
+    }
+
+    /// Indent the cursor line by a shiftwidth (`Ctrl-t`), or dedent it
+    /// (`Ctrl-d`), to a multiple of shiftwidth. The cursor stays on the
+    /// same text.
+    pub fn shift_insert_line(&mut self, right: bool) {
+        let row = self.cursor_row;
+        let sw = self.buffer.shiftwidth();
+        let width = self.buffer.indent_width(row);
+        let width = if right {
+            width / sw * sw + sw
+        } else {
+            width.saturating_sub(1) / sw * sw
+        };
+        let old = self.indent_chars(row);
+        self.buffer.set_indent(row, width);
+        self.cursor_col = self.cursor_col.saturating_sub(old) + self.indent_chars(row);
+    }
+
+    /// Insert the text in register `name` (`Ctrl-r`), leaving the cursor
+    /// after it. Fails if the register is empty.
+    pub fn insert_register(&mut self, name: char) -> bool {
+        let Some(register) = self.registers.get(name) else {
+            return false;
+        };
+        let text = register.text.clone();
+        let start = self.buffer.char_index(self.cursor_row, self.cursor_col);
+        self.buffer
+            .insert_text(self.cursor_row, self.cursor_col, &text);
+        (self.cursor_row, self.cursor_col) = self.buffer.position(start + text.chars().count());
+        self.autoindented = false;
+        true
+    }
+
+    /// Insert `ch` as it is (`Ctrl-v`): no reindenting, and a newline
+    /// breaks the line without autoindenting the new one.
+    pub fn insert_literal(&mut self, ch: char) {
+        if ch == '\n' {
+            self.buffer.insert_newline(self.cursor_row, self.cursor_col);
+            self.cursor_row += 1;
+            self.cursor_col = 0;
+        } else {
+            self.buffer
+                .insert_char(self.cursor_row, self.cursor_col, ch);
+            self.cursor_col += 1;
+        }
+        self.autoindented = false;
+    }
+
+    /// Run one normal-mode command, then carry on inserting (`Ctrl-o`).
+    pub fn insert_one_command(&mut self) {
+        self.drop_unused_autoindent();
+        self.mode = Mode::Normal;
+        self.insert_normal = true;
+    }
+
+    /// Go back to insert mode once the command started by `Ctrl-o` is
+    /// done. Called by `handle_key` after each key of the command.
+    pub(super) fn finish_one_command(&mut self) {
+        match self.mode {
+            // The command started inserting by itself (`A`, `cw`).
+            Mode::Insert | Mode::Replace => self.insert_normal = false,
+            Mode::Normal if self.nothing_pending() => {
+                self.insert_normal = false;
+                self.mode = Mode::Insert;
+            }
+            _ => {}
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::super::test_editor;
+    use super::super::{Editor, ctrl, editor_with_suffix, handle_key, test_editor, type_keys};
     use crate::mode::Mode;
+    use crossterm::event::{KeyCode, KeyEvent};
+
+    fn press(ed: &mut Editor, code: KeyCode) {
+        handle_key(ed, KeyEvent::from(code), 20).unwrap();
+    }
 
     #[test]
     fn enter_insert_mode_keeps_cursor() {
@@ -155,4 +348,145 @@ mod tests {
         assert_eq!(ed.cursor_col, 2);
         assert_eq!(ed.buffer.line(0).unwrap(), "helo");
     }
+
+    #[test]
+    fn new_lines_are_autoindented() {
+        let mut ed = test_editor("    abc\n");
+        ed.enter_insert_mode_open_below();
+        assert_eq!((ed.cursor_row, ed.cursor_col), (1, 4));
+        ed.insert_char('x');
+        ed.insert_newline();
+        assert_eq!(ed.buffer.line(2).unwrap(), "    ");
+        // An indent nothing was typed after goes away again.
+        ed.exit_insert_mode();
+        assert_eq!(ed.buffer.line(1).unwrap(), "    x");
+        assert_eq!(ed.buffer.line(2).unwrap(), "");
+    }
+
+    #[test]
+    fn noautoindent_starts_at_column_zero() {
+        let mut ed = test_editor("    abc\n");
+        ed.buffer.set_autoindent(false);
+        ed.enter_insert_mode_open_below();
+        assert_eq!(ed.cursor_col, 0);
+    }
+
+    #[test]
+    fn brackets_indent_and_dedent() {
+        let mut ed = editor_with_suffix("fn f() {}\n", ".rs");
+        ed.enter_insert_mode();
+        ed.cursor_col = 8;
+        ed.insert_newline();
+        assert_eq!(ed.buffer.line(1).unwrap(), "}");
+        ed.enter_insert_mode_open_above();
+        assert_eq!(ed.cursor_col, 4);
+        ed.insert_newline();
+        ed.insert_char('}');
+        assert_eq!(ed.buffer.line(2).unwrap(), "}");
+        assert_eq!(ed.cursor_col, 1);
+    }
+
+    #[test]
+    fn ctrl_w_deletes_words_back() {
+        let mut ed = test_editor("a\nfoo.bar  baz\n");
+        type_keys(&mut ed, "j$a");
+        ctrl(&mut ed, 'w');
+        assert_eq!(ed.buffer.line(1).unwrap(), "foo.bar  ");
+        ctrl(&mut ed, 'w');
+        assert_eq!(ed.buffer.line(1).unwrap(), "foo.");
+        ctrl(&mut ed, 'w');
+        ctrl(&mut ed, 'w');
+        assert_eq!(ed.buffer.line(1).unwrap(), "");
+        ctrl(&mut ed, 'w');
+        assert_eq!(ed.buffer.line(0).unwrap(), "a");
+        assert_eq!((ed.cursor_row, ed.cursor_col), (0, 1));
+    }
+
+    #[test]
+    fn ctrl_u_keeps_the_indent_once() {
+        let mut ed = test_editor("    abc def\n");
+        type_keys(&mut ed, "$a");
+        ctrl(&mut ed, 'u');
+        assert_eq!(ed.buffer.line(0).unwrap(), "    ");
+        ctrl(&mut ed, 'u');
+        assert_eq!(ed.buffer.line(0).unwrap(), "");
+    }
+
+    #[test]
+    fn ctrl_t_and_ctrl_d_shift_to_shiftwidth() {
+        let mut ed = test_editor("  x\n");
+        type_keys(&mut ed, "$a");
+        ctrl(&mut ed, 't');
+        assert_eq!(ed.buffer.line(0).unwrap(), "    x");
+        assert_eq!(ed.cursor_col, 5);
+        ctrl(&mut ed, 't');
+        ctrl(&mut ed, 'd');
+        assert_eq!(ed.buffer.line(0).unwrap(), "    x");
+        ctrl(&mut ed, 'd');
+        ctrl(&mut ed, 'd');
+        assert_eq!(ed.buffer.line(0).unwrap(), "x");
+        assert_eq!(ed.cursor_col, 1);
+    }
+
+    #[test]
+    fn ctrl_r_inserts_a_register() {
+        let mut ed = test_editor("word\n");
+        type_keys(&mut ed, "yiw$a ");
+        ctrl(&mut ed, 'r');
+        type_keys(&mut ed, "0");
+        assert_eq!(ed.buffer.line(0).unwrap(), "word word");
+        assert_eq!(ed.cursor_col, 9);
+        ctrl(&mut ed, 'r');
+        type_keys(&mut ed, "z");
+        assert!(ed.command_failed);
+        assert_eq!(ed.mode, Mode::Insert);
+    }
+
+    #[test]
+    fn ctrl_v_inserts_literally() {
+        let mut ed = test_editor("\n");
+        type_keys(&mut ed, "i");
+        ctrl(&mut ed, 'v');
+        press(&mut ed, KeyCode::Tab);
+        ctrl(&mut ed, 'v');
+        type_keys(&mut ed, "u00e9");
+        ctrl(&mut ed, 'v');
+        type_keys(&mut ed, "65x");
+        ctrl(&mut ed, 'v');
+        press(&mut ed, KeyCode::Esc);
+        assert_eq!(ed.mode, Mode::Insert);
+        assert_eq!(ed.buffer.line(0).unwrap(), "\té\x41x\x1b");
+    }
+
+    #[test]
+    fn ctrl_o_runs_one_command() {
+        let mut ed = test_editor("abc\n");
+        type_keys(&mut ed, "$a");
+        ctrl(&mut ed, 'o');
+        assert_eq!(ed.mode, Mode::Normal);
+        type_keys(&mut ed, "0");
+        assert_eq!((ed.mode, ed.cursor_col), (Mode::Insert, 0));
+        type_keys(&mut ed, "x");
+        ctrl(&mut ed, 'o');
+        type_keys(&mut ed, "2l");
+        type_keys(&mut ed, "y\x1b");
+        assert_eq!(ed.buffer.line(0).unwrap(), "xabyc");
+        // The whole insert, command and all, is one change for `.`.
+        type_keys(&mut ed, "$.");
+        assert_eq!(ed.buffer.line(0).unwrap(), "xxaybyc");
+    }
+
+    #[test]
+    fn delete_home_and_end() {
+        let mut ed = test_editor("abc\ndef\n");
+        type_keys(&mut ed, "i");
+        press(&mut ed, KeyCode::End);
+        assert_eq!(ed.cursor_col, 3);
+        press(&mut ed, KeyCode::Delete);
+        assert_eq!(ed.buffer.line(0).unwrap(), "abcdef");
+        press(&mut ed, KeyCode::Home);
+        press(&mut ed, KeyCode::Delete);
+        assert_eq!(ed.buffer.line(0).unwrap(), "bcdef");
+        assert_eq!(ed.cursor_col, 0);
+    }
 }
@@ -0,0 +1,83 @@
+use super::Editor;
+use crate::mode::Mode;
+
+impl Editor {
+    /// Join [count] lines from the cursor, at least two (`J`, or `gJ` when
+    /// `spaces` is false). A count past the end of the buffer joins what's
+    /// there; fails on the last line.
+    pub fn join_lines(&mut self, count: usize, spaces: bool) -> bool {
+        let first = self.cursor_row;
+        if first >= self.max_row() {
+            return false;
+        }
+        let last = (first + count.max(2) - 1).min(self.max_row());
+        self.join_rows(first, last, spaces);
+        true
+    }
+
+    /// Join the lines of the visual selection, or the line after it when
+    /// only one is selected, and leave visual mode.
+    pub fn join_visual(&mut self, spaces: bool) -> bool {
+        let first = self.cursor_row.min(self.visual_start.0);
+        let last = self.cursor_row.max(self.visual_start.0);
+        self.exit_visual_mode();
+        if first >= self.max_row() {
+            return false;
+        }
+        self.join_rows(first, last.max(first + 1), spaces);
+        true
+    }
+
+    /// Join rows `first..=last` into one, leaving the cursor where the last
+    /// two met.
+    fn join_rows(&mut self, first: usize, last: usize, spaces: bool) {
+        let col = self.buffer.join_rows(first, last, spaces).unwrap_or(0);
+        let end = (first, self.buffer.line_len(first).saturating_sub(1));
+        self.set_change_marks((first, 0), end);
+        self.mode = Mode::Normal;
+        self.cursor_row = first;
+        self.cursor_col = col;
+        self.clamp_cursor_col();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{test_editor, type_keys};
+
+    #[test]
+    fn join_with_count() {
+        let mut ed = test_editor("a\n  b\n  c\nd\n");
+        type_keys(&mut ed, "3J");
+        assert_eq!(ed.buffer.line(0).unwrap(), "a b c");
+        assert_eq!(ed.cursor_col, 3);
+        type_keys(&mut ed, "9J");
+        assert_eq!(ed.buffer.line(0).unwrap(), "a b c d");
+        type_keys(&mut ed, "J");
+        assert!(ed.command_failed);
+    }
+
+    #[test]
+    fn join_without_spaces() {
+        let mut ed = test_editor("a\n  b\n");
+        type_keys(&mut ed, "gJ");
+        assert_eq!(ed.buffer.line(0).unwrap(), "a  b");
+    }
+
+    #[test]
+    fn join_visual_lines() {
+        let mut ed = test_editor("a\nb\nc\nd\n");
+        type_keys(&mut ed, "jVjJ");
+        assert_eq!(ed.buffer.line(1).unwrap(), "b c");
+        assert_eq!(ed.buffer.line(2).unwrap(), "d");
+        type_keys(&mut ed, "kvgJ");
+        assert_eq!(ed.buffer.line(0).unwrap(), "ab c");
+    }
+
+    #[test]
+    fn join_is_repeatable() {
+        let mut ed = test_editor("a\nb\nc\n");
+        type_keys(&mut ed, "J.");
+        assert_eq!(ed.buffer.line(0).unwrap(), "a b c");
+    }
+}
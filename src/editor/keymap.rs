@@ -1,4 +1,13 @@
 use super::Editor;
+use super::insert::InsertPrefix;
+use super::literal::{Literal, LiteralStep};
+use super::macros::RegisterPrefix;
+use super::marks::MarkPrefix;
+use super::motion::{FindChar, FindKind, Motion};
+use super::operator::Operator;
+use super::register::Registers;
+use super::scroll::ScreenPlace;
+use super::text_object::TextObject;
 use crate::mode::Mode;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
@@ -7,84 +16,595 @@ pub fn handle_key(
     key: KeyEvent,
     viewport_height: usize,
 ) -> anyhow::Result<()> {
+    editor.record_macro_key(key);
+    editor.map_key(key, viewport_height);
+    Ok(())
+}
+
+/// Handle `key` as itself, once any mapping has been applied.
+pub(super) fn dispatch_key(editor: &mut Editor, key: KeyEvent, viewport_height: usize) {
+    editor.status_message = None;
+    editor.command_failed = false;
+    editor.viewport_height = viewport_height;
+    editor.record_key(key);
+    let one_command = editor.insert_normal;
     match editor.mode {
         Mode::Normal => handle_normal_key(editor, key, viewport_height),
         Mode::Insert => handle_insert_key(editor, key, viewport_height),
+        Mode::Replace => handle_replace_key(editor, key),
         Mode::Command => handle_command_key(editor, key),
+        Mode::Visual | Mode::VisualLine => handle_visual_key(editor, key, viewport_height),
     }
-    Ok(())
+    if one_command && editor.insert_normal {
+        editor.finish_one_command();
+    }
+    editor.note_command_key(key);
+    editor.finish_recording();
 }
 
 fn handle_normal_key(editor: &mut Editor, key: KeyEvent, viewport_height: usize) {
-    // Handle 'd' prefix for dd/dw commands
-    if editor.pending_d {
-        editor.pending_d = false;
-        match key.code {
-            KeyCode::Char('d') => editor.delete_line(),
-            KeyCode::Char('w') => editor.delete_word(),
-            _ => {}
-        }
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+    // A command waiting for its next key gets it, whatever it is.
+    if let Some(pending) = editor.pending.take() {
+        handle_pending_key(editor, pending, key, viewport_height);
         return;
     }
 
-    // Handle 'g' prefix for gg command
-    if editor.pending_g {
-        editor.pending_g = false;
-        if key.code == KeyCode::Char('g') {
-            editor.goto_top();
+    // Counts: a leading 0 is the `0` motion, not part of a count.
+    if let KeyCode::Char(c @ '0'..='9') = key.code
+        && !ctrl
+        && (c != '0' || editor.pending_count.is_some())
+    {
+        let digit = c.to_digit(10).unwrap_or(0) as usize;
+        let count = editor.pending_count.unwrap_or(0);
+        editor.pending_count = Some(count.saturating_mul(10).saturating_add(digit));
+        return;
+    }
+
+    // Operators wait for a motion or text object.
+    if let Some(op) = operator_for_key(key) {
+        start_operator(editor, op);
+        return;
+    }
+
+    // `guu`, `gUU` and `g~~` are short for `gugu`, `gUgU` and `g~g~`.
+    if let (Some(pending), KeyCode::Char(c)) = (editor.pending_op, key.code)
+        && !ctrl
+        && case_operator_for_key(c) == Some(pending)
+    {
+        start_operator(editor, pending);
+        return;
+    }
+
+    // Prefixes that keep any pending operator and count alive.
+    match key.code {
+        KeyCode::Char('g') if !ctrl => {
+            editor.pending = Some(Pending::Prefix(Prefix::G));
+            return;
+        }
+        KeyCode::Char('z') if !ctrl => {
+            editor.pending = Some(Pending::Prefix(Prefix::Z));
+            return;
+        }
+        KeyCode::Char(c @ ('[' | ']')) if !ctrl => {
+            editor.pending = Some(Pending::Prefix(Prefix::Bracket(c)));
+            return;
+        }
+        KeyCode::Char('w') if ctrl && editor.pending_op.is_none() => {
+            editor.pending = Some(Pending::Prefix(Prefix::Window));
+            return;
+        }
+        KeyCode::Char('f') if !ctrl => {
+            editor.pending = Some(Pending::Find(FindKind::Forward));
+            return;
         }
+        KeyCode::Char('F') if !ctrl => {
+            editor.pending = Some(Pending::Find(FindKind::Backward));
+            return;
+        }
+        KeyCode::Char('t') if !ctrl => {
+            editor.pending = Some(Pending::Find(FindKind::TillForward));
+            return;
+        }
+        KeyCode::Char('T') if !ctrl => {
+            editor.pending = Some(Pending::Find(FindKind::TillBackward));
+            return;
+        }
+        KeyCode::Char('"') if !ctrl && editor.pending_op.is_none() => {
+            editor.pending = Some(Pending::Register(RegisterPrefix::Select));
+            return;
+        }
+        KeyCode::Char('q') if !ctrl && editor.pending_op.is_none() => {
+            if editor.macros.recording.is_some() {
+                editor.stop_recording();
+            } else {
+                editor.pending = Some(Pending::Register(RegisterPrefix::Record));
+            }
+            return;
+        }
+        KeyCode::Char('m') if !ctrl && editor.pending_op.is_none() => {
+            editor.pending = Some(Pending::Mark(MarkPrefix::Set));
+            return;
+        }
+        KeyCode::Char('\'') if !ctrl => {
+            editor.pending = Some(Pending::Mark(MarkPrefix::Jump { exact: false }));
+            return;
+        }
+        KeyCode::Char('`') if !ctrl => {
+            editor.pending = Some(Pending::Mark(MarkPrefix::Jump { exact: true }));
+            return;
+        }
+        KeyCode::Char('@') if !ctrl && editor.pending_op.is_none() => {
+            editor.pending = Some(Pending::Register(RegisterPrefix::Play));
+            return;
+        }
+        KeyCode::Char(c @ ('i' | 'a')) if !ctrl && editor.pending_op.is_some() => {
+            editor.pending = Some(Pending::Object(c == 'i'));
+            return;
+        }
+        _ => {}
+    }
+
+    // `%` jumps to the matching bracket, but `N%` goes N percent into the file.
+    if key.code == KeyCode::Char('%') {
+        let motion = match take_count(editor) {
+            Some(percent) => Motion::FilePercent(percent),
+            None => Motion::MatchBracket,
+        };
+        run_motion(editor, motion);
+        return;
+    }
+
+    if let Some(motion) = motion_for_key(key, viewport_height) {
+        run_motion(editor, motion);
         return;
     }
 
+    // Anything else abandons a pending operator. Visual mode handles its own
+    // commands before getting here.
+    if editor.pending_op.is_some() || editor.mode != Mode::Normal {
+        editor.cancel_pending();
+        return;
+    }
+
+    let count = take_count(editor);
     match key.code {
+        // Jump list
+        KeyCode::Char('o') if ctrl => {
+            let steps = count.unwrap_or(1) as isize;
+            editor.command_failed = !editor.step_jump_list(-steps);
+        }
+        KeyCode::Tab | KeyCode::Char('i') if ctrl || key.code == KeyCode::Tab => {
+            let steps = count.unwrap_or(1) as isize;
+            editor.command_failed = !editor.step_jump_list(steps);
+        }
+
         // Command mode
         KeyCode::Char(':') => editor.enter_command_mode(),
 
+        // Replace
+        KeyCode::Char('r') if !ctrl => {
+            editor.pending = Some(Pending::Replace);
+            editor.pending_count = count;
+            return;
+        }
+        KeyCode::Char('R') => editor.enter_replace_mode(),
+        KeyCode::Char('~') => {
+            editor.command_failed = !editor.toggle_case_chars(count.unwrap_or(1));
+        }
+
         // Enter insert mode
         KeyCode::Char('i') => editor.enter_insert_mode(),
         KeyCode::Char('a') => editor.enter_insert_mode_append(),
         KeyCode::Char('o') => editor.enter_insert_mode_open_below(),
         KeyCode::Char('O') => editor.enter_insert_mode_open_above(),
 
-        // Movement
-        KeyCode::Char('h') | KeyCode::Left => editor.move_left(),
-        KeyCode::Char('j') | KeyCode::Down => editor.move_down(),
-        KeyCode::Char('k') | KeyCode::Up => editor.move_up(),
-        KeyCode::Char('l') | KeyCode::Right => editor.move_right(),
+        // Visual mode
+        KeyCode::Char('v') => editor.enter_visual_mode(false),
+        KeyCode::Char('V') => editor.enter_visual_mode(true),
 
-        // Jump to top/bottom
-        KeyCode::Char('g') => editor.pending_g = true,
-        KeyCode::Char('G') => editor.goto_bottom(),
+        // Scrolling
+        KeyCode::Char(c @ ('d' | 'u')) if ctrl => {
+            editor.command_failed = !editor.scroll_half_page(c == 'd', count, viewport_height);
+        }
+        KeyCode::Char(c @ ('e' | 'y')) if ctrl => {
+            let lines = count.unwrap_or(1);
+            editor.command_failed = !editor.scroll_lines(c == 'e', lines, viewport_height);
+        }
+        KeyCode::Char('z') if ctrl => editor.suspend = true,
+        KeyCode::Char(c @ ('f' | 'b')) if ctrl => {
+            let pages = count.unwrap_or(1);
+            editor.command_failed = !editor.scroll_pages(c == 'f', pages, viewport_height);
+        }
 
-        // Viewport-relative jumps
-        KeyCode::Char('H') => editor.goto_viewport_top(),
-        KeyCode::Char('M') => editor.goto_viewport_middle(viewport_height),
-        KeyCode::Char('L') => editor.goto_viewport_bottom(viewport_height),
+        KeyCode::Char('J') => {
+            editor.command_failed = !editor.join_lines(count.unwrap_or(1), true);
+        }
+
+        // Normal mode deletion
+        KeyCode::Char('D') => {
+            editor.command_failed = !editor.delete_to_end_of_line(count.unwrap_or(1));
+        }
+        KeyCode::Char('x') => {
+            editor.command_failed = !editor.delete_chars_at_cursor(count.unwrap_or(1));
+        }
+        KeyCode::Char('X') => {
+            editor.command_failed = !editor.delete_chars_before_cursor(count.unwrap_or(1));
+        }
+
+        // Repeat the last change
+        KeyCode::Char('.') => editor.repeat_last_change(count, viewport_height),
+
+        // Put
+        KeyCode::Char('p') => editor.put(true, count.unwrap_or(1)),
+        KeyCode::Char('P') => editor.put(false, count.unwrap_or(1)),
+
+        KeyCode::Esc => editor.cancel_pending(),
+
+        _ => {}
+    }
+    editor.selected_register = None;
+}
+
+/// The key after one that waits for it: an `f` target, a register or mark
+/// name, the second key of a `g` command.
+fn handle_pending_key(
+    editor: &mut Editor,
+    pending: Pending,
+    key: KeyEvent,
+    viewport_height: usize,
+) {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match (pending, key.code) {
+        (Pending::Find(kind), KeyCode::Char(ch)) => {
+            run_motion(editor, Motion::FindChar(FindChar { kind, ch }));
+        }
+
+        // `r` takes the character to replace with.
+        (Pending::Replace, code) => {
+            let ch = match code {
+                KeyCode::Char(c) if !ctrl => Some(c),
+                KeyCode::Enter => Some('\n'),
+                _ => None,
+            };
+            if let Some(ch) = ch {
+                let count = take_count(editor).unwrap_or(1);
+                editor.command_failed = !editor.replace_chars(ch, count);
+            }
+            editor.cancel_pending();
+        }
+
+        (Pending::Register(prefix), KeyCode::Char(name)) => match prefix {
+            RegisterPrefix::Play => {
+                let count = take_count(editor).unwrap_or(1);
+                editor.play_macro(name, count, viewport_height);
+            }
+            _ if !Registers::is_valid_name(name) => editor.cancel_pending(),
+            RegisterPrefix::Select => editor.selected_register = Some(name),
+            RegisterPrefix::Record => editor.start_recording(name),
+        },
+
+        (Pending::Mark(MarkPrefix::Set), KeyCode::Char(name)) => {
+            editor.command_failed = !editor.set_mark(name);
+            editor.cancel_pending();
+        }
+        (Pending::Mark(MarkPrefix::Jump { exact }), KeyCode::Char(name)) => {
+            run_motion(editor, Motion::Mark { name, exact });
+        }
+
+        // `i`/`a` after an operator or in visual mode take the text object key.
+        (Pending::Object(inner), KeyCode::Char(c)) => match TextObject::from_key(c, inner) {
+            Some(object) => run_text_object(editor, object),
+            None => editor.cancel_pending(),
+        },
+
+        (Pending::Prefix(Prefix::G), _) => handle_g_key(editor, key),
+        (Pending::Prefix(Prefix::Bracket(c)), _) => handle_bracket_key(editor, c, key),
+        (Pending::Prefix(Prefix::Z), _) => handle_z_key(editor, key),
+        // With one window there's nothing for `Ctrl-w` commands to act on.
+        (Pending::Prefix(Prefix::Window), _) => editor.cancel_pending(),
+
+        // Any other key abandons the command.
+        _ => editor.cancel_pending(),
+    }
+}
+
+/// The key after `g`: gg, ge, gE, g;, g,, gJ, gr and the case operators.
+fn handle_g_key(editor: &mut Editor, key: KeyEvent) {
+    match key.code {
+        KeyCode::Char('g') => run_motion(editor, Motion::Top),
+        KeyCode::Char(c) if let Some(op) = case_operator_for_key(c) => {
+            start_operator(editor, op);
+        }
+        KeyCode::Char('J') if editor.pending_op.is_none() => {
+            let count = take_count(editor).unwrap_or(1);
+            editor.command_failed = if editor.mode.is_visual() {
+                !editor.join_visual(false)
+            } else {
+                !editor.join_lines(count, false)
+            };
+            editor.cancel_pending();
+        }
+        // There are no virtual columns, so `gr` is the same as `r`.
+        KeyCode::Char('r') if editor.pending_op.is_none() => {
+            editor.pending = Some(Pending::Replace)
+        }
+        KeyCode::Char('j') | KeyCode::Down => run_motion(editor, Motion::DisplayDown),
+        KeyCode::Char('k') | KeyCode::Up => run_motion(editor, Motion::DisplayUp),
+        KeyCode::Char('0') | KeyCode::Home => run_motion(editor, Motion::DisplayLineStart),
+        KeyCode::Char('$') | KeyCode::End => run_motion(editor, Motion::DisplayLineEnd),
+        KeyCode::Char('e') => run_motion(editor, Motion::WordEndBackward),
+        KeyCode::Char('E') => run_motion(editor, Motion::BigWordEndBackward),
+        KeyCode::Char(c @ (';' | ',')) if editor.pending_op.is_none() => {
+            let steps = take_count(editor).unwrap_or(1) as isize;
+            let steps = if c == ';' { -steps } else { steps };
+            editor.command_failed = !editor.step_change_list(steps);
+            editor.cancel_pending();
+        }
+        _ => editor.cancel_pending(),
+    }
+}
 
-        // Deletion
-        KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-            editor.scroll_half_page_down(viewport_height);
+/// The key after `z`: scrolling the view.
+fn handle_z_key(editor: &mut Editor, key: KeyEvent) {
+    if editor.pending_op.is_some() {
+        editor.cancel_pending();
+        return;
+    }
+    let count = take_count(editor);
+    let columns = count.unwrap_or(1) as isize;
+    // `z<CR>`, `z.` and `z-` are `zt`, `zz` and `zb` that also go to the
+    // first non-blank; all of them take a line number as a count.
+    let place = match key.code {
+        KeyCode::Char('t') | KeyCode::Enter => Some(ScreenPlace::Top),
+        KeyCode::Char('z' | '.') => Some(ScreenPlace::Middle),
+        KeyCode::Char('b' | '-') => Some(ScreenPlace::Bottom),
+        _ => None,
+    };
+    if let Some(place) = place {
+        if let Some(line) = count {
+            editor.goto_line(line);
         }
-        KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-            editor.scroll_half_page_up(viewport_height);
+        if matches!(key.code, KeyCode::Enter | KeyCode::Char('.' | '-')) {
+            editor.goto_first_non_blank();
         }
+        editor.scroll_cursor_to(place, editor.viewport_height);
+        editor.cancel_pending();
+        return;
+    }
+    match key.code {
+        KeyCode::Char('l') | KeyCode::Right => editor.scroll_sideways(columns),
+        KeyCode::Char('h') | KeyCode::Left => editor.scroll_sideways(-columns),
+        KeyCode::Char('L') => editor.scroll_half_width(true),
+        KeyCode::Char('H') => editor.scroll_half_width(false),
+        KeyCode::Char('s') => editor.scroll_cursor_to_left(),
+        KeyCode::Char('e') => editor.scroll_cursor_to_right(),
+        _ => {}
+    }
+    editor.cancel_pending();
+}
+
+/// The key after `[` or `]`: section and bracket motions.
+fn handle_bracket_key(editor: &mut Editor, prefix: char, key: KeyEvent) {
+    let motion = match (prefix, key.code) {
+        (']', KeyCode::Char(']')) => Some(Motion::SectionForward),
+        ('[', KeyCode::Char('[')) => Some(Motion::SectionBackward),
+        (']', KeyCode::Char('[')) => Some(Motion::SectionEndForward),
+        ('[', KeyCode::Char(']')) => Some(Motion::SectionEndBackward),
+        ('[', KeyCode::Char(c @ ('(' | '{'))) => Some(Motion::UnmatchedBracket(c)),
+        (']', KeyCode::Char(c @ (')' | '}'))) => Some(Motion::UnmatchedBracket(c)),
+        _ => None,
+    };
+    match motion {
+        Some(motion) => run_motion(editor, motion),
+        None => editor.cancel_pending(),
+    }
+}
+
+/// A command key waiting for the key after it. Only one waits at a time;
+/// an operator or count typed before it is kept alongside.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pending {
+    /// `g`, `z`, `[`, `]` or `Ctrl-w`, waiting for the rest of the command.
+    Prefix(Prefix),
+    /// `f`/`F`/`t`/`T`, waiting for the character to find.
+    Find(FindKind),
+    /// `i` or `a` (true for `i`), waiting for the text object it selects.
+    Object(bool),
+    /// `"`, `q` or `@`, waiting for a register name.
+    Register(RegisterPrefix),
+    /// `m`, `'` or `` ` ``, waiting for a mark name.
+    Mark(MarkPrefix),
+    /// `r`, waiting for the replacement character.
+    Replace,
+    /// `Ctrl-r` or `Ctrl-v` in insert mode.
+    Insert(InsertPrefix),
+}
+
+impl Pending {
+    /// Whether this gives up after 'timeoutlen' without its next key. The
+    /// prefixes of two-key commands do; a command waiting for its argument
+    /// (an `f` target, a mark name) waits as long as it takes.
+    pub fn times_out(self) -> bool {
+        matches!(
+            self,
+            Pending::Prefix(_) | Pending::Register(RegisterPrefix::Select)
+        )
+    }
+}
+
+/// A key that starts a two-key command, waiting for the second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prefix {
+    G,
+    Z,
+    /// `[` or `]`.
+    Bracket(char),
+    /// `Ctrl-w`.
+    Window,
+}
+
+/// The motion a normal-mode key stands for, if any.
+fn motion_for_key(key: KeyEvent, viewport_height: usize) -> Option<Motion> {
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        return None;
+    }
+    let motion = match key.code {
+        KeyCode::Char('h') | KeyCode::Left => Motion::Left,
+        KeyCode::Char('j') | KeyCode::Down => Motion::Down,
+        KeyCode::Char('k') | KeyCode::Up => Motion::Up,
+        KeyCode::Char('l') | KeyCode::Right => Motion::Right,
+
+        // Jump to bottom (top is `gg`)
+        KeyCode::Char('G') => Motion::Bottom,
+
+        // Viewport-relative jumps
+        KeyCode::Char('H') => Motion::ViewportTop,
+        KeyCode::Char('M') => Motion::ViewportMiddle(viewport_height),
+        KeyCode::Char('L') => Motion::ViewportBottom(viewport_height),
 
         // Word motions
-        KeyCode::Char('w') => editor.move_word_forward(),
-        KeyCode::Char('b') => editor.move_word_backward(),
-        KeyCode::Char('e') => editor.move_word_end(),
+        KeyCode::Char('w') => Motion::WordForward,
+        KeyCode::Char('b') => Motion::WordBackward,
+        KeyCode::Char('e') => Motion::WordEnd,
+        KeyCode::Char('W') => Motion::BigWordForward,
+        KeyCode::Char('B') => Motion::BigWordBackward,
+        KeyCode::Char('E') => Motion::BigWordEnd,
 
         // Line position motions
-        KeyCode::Char('0') => editor.goto_line_start(),
-        KeyCode::Char('$') => editor.goto_line_end(),
-        KeyCode::Char('^') => editor.goto_first_non_blank(),
+        KeyCode::Char('0') => Motion::LineStart,
+        KeyCode::Char('$') => Motion::LineEnd,
+        KeyCode::Char('^') => Motion::FirstNonBlank,
 
-        // Normal mode deletion
-        KeyCode::Char('d') => editor.pending_d = true,
-        KeyCode::Char('D') => editor.delete_to_end_of_line(),
-        KeyCode::Char('x') => editor.delete_char_at_cursor(),
+        // Paragraph and sentence motions
+        KeyCode::Char('}') => Motion::ParagraphForward,
+        KeyCode::Char('{') => Motion::ParagraphBackward,
+        KeyCode::Char(')') => Motion::SentenceForward,
+        KeyCode::Char('(') => Motion::SentenceBackward,
 
-        _ => {}
+        // Character find repeats
+        KeyCode::Char(';') => Motion::RepeatFind,
+        KeyCode::Char(',') => Motion::RepeatFindReverse,
+
+        _ => return None,
+    };
+    Some(motion)
+}
+
+fn operator_for_key(key: KeyEvent) -> Option<Operator> {
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        return None;
+    }
+    match key.code {
+        KeyCode::Char('d') => Some(Operator::Delete),
+        KeyCode::Char('c') => Some(Operator::Change),
+        KeyCode::Char('y') => Some(Operator::Yank),
+        KeyCode::Char('>') => Some(Operator::ShiftRight),
+        KeyCode::Char('<') => Some(Operator::ShiftLeft),
+        KeyCode::Char('=') => Some(Operator::Reindent),
+        _ => None,
+    }
+}
+
+/// `u`, `U` and `~` after `g` (or doubled, or in visual mode).
+fn case_operator_for_key(c: char) -> Option<Operator> {
+    match c {
+        'u' => Some(Operator::Lowercase),
+        'U' => Some(Operator::Uppercase),
+        '~' => Some(Operator::ToggleCase),
+        _ => None,
+    }
+}
+
+/// Wait for the text `op` applies to, or apply it to whole lines when it's
+/// typed twice (`dd`, `gUgU`).
+fn start_operator(editor: &mut Editor, op: Operator) {
+    match editor.pending_op {
+        Some(pending) if pending == op => {
+            editor.pending_op = None;
+            let count = take_count(editor).unwrap_or(1);
+            editor.apply_operator_lines(op, count);
+        }
+        Some(_) => editor.cancel_pending(),
+        None => {
+            editor.pending_op = Some(op);
+            editor.op_count = editor.pending_count.take();
+        }
+    }
+}
+
+/// Move by `motion`, or apply the pending operator over it.
+fn run_motion(editor: &mut Editor, motion: Motion) {
+    let count = take_count(editor);
+    let from = (editor.cursor_row, editor.cursor_col);
+    let moved = match editor.pending_op.take() {
+        Some(op) => editor.apply_operator(op, motion, count),
+        None => {
+            let moved = editor.apply_motion(motion, count);
+            if moved && motion.is_jump() {
+                editor.record_jump(from);
+            }
+            moved
+        }
+    };
+    editor.command_failed = !moved;
+    editor.selected_register = None;
+}
+
+/// Select a text object in visual mode, or apply the pending operator to it.
+fn run_text_object(editor: &mut Editor, object: TextObject) {
+    let count = take_count(editor).unwrap_or(1);
+    let found = if editor.mode.is_visual() {
+        editor.select_text_object(object, count)
+    } else if let Some(op) = editor.pending_op.take() {
+        editor.apply_operator_object(op, object, count)
+    } else {
+        false
+    };
+    editor.command_failed = !found;
+    editor.selected_register = None;
+}
+
+/// Consume the typed count, multiplying in any count given before the
+/// operator (`2d3w` deletes six words).
+fn take_count(editor: &mut Editor) -> Option<usize> {
+    match (editor.op_count.take(), editor.pending_count.take()) {
+        (None, None) => None,
+        (a, b) => Some(a.unwrap_or(1).saturating_mul(b.unwrap_or(1))),
+    }
+}
+
+/// Visual mode shares counts, prefixes and motions with normal mode, but
+/// operators act on the selection straight away.
+fn handle_visual_key(editor: &mut Editor, key: KeyEvent, viewport_height: usize) {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    if editor.pending.is_some() || ctrl {
+        handle_normal_key(editor, key, viewport_height);
+        return;
+    }
+
+    if let Some(op) = operator_for_key(key) {
+        editor.pending_count = None;
+        editor.apply_operator_visual(op);
+        editor.selected_register = None;
+        return;
+    }
+    match key.code {
+        KeyCode::Esc => editor.abandon_command(),
+        KeyCode::Char('x') => editor.apply_operator_visual(Operator::Delete),
+        KeyCode::Char('J') => editor.command_failed = !editor.join_visual(true),
+        KeyCode::Char(c) if let Some(op) = case_operator_for_key(c) => {
+            editor.pending_count = None;
+            editor.apply_operator_visual(op);
+        }
+        KeyCode::Char('v') if editor.mode == Mode::Visual => editor.exit_visual_mode(),
+        KeyCode::Char('V') if editor.mode == Mode::VisualLine => editor.exit_visual_mode(),
+        KeyCode::Char('v') => editor.mode = Mode::Visual,
+        KeyCode::Char('V') => editor.mode = Mode::VisualLine,
+        KeyCode::Char('o') => editor.visual_swap_ends(),
+        KeyCode::Char(c @ ('i' | 'a')) => editor.pending = Some(Pending::Object(c == 'i')),
+        _ => handle_normal_key(editor, key, viewport_height),
     }
 }
 
@@ -92,8 +612,9 @@ fn handle_command_key(editor: &mut Editor, key: KeyEvent) {
     match key.code {
         KeyCode::Esc => editor.exit_command_mode(),
         KeyCode::Enter => {
-            // Ignore errors for now — could display in status bar later
-            let _ = editor.execute_command();
+            if let Err(e) = editor.execute_command() {
+                editor.status_message = Some(e.to_string());
+            }
         }
         KeyCode::Backspace => editor.command_pop(),
         KeyCode::Char(c) => editor.command_push(c),
@@ -101,20 +622,117 @@ fn handle_command_key(editor: &mut Editor, key: KeyEvent) {
     }
 }
 
-fn handle_insert_key(editor: &mut Editor, key: KeyEvent, _viewport_height: usize) {
+fn handle_replace_key(editor: &mut Editor, key: KeyEvent) {
+    match key.code {
+        KeyCode::Esc => editor.exit_insert_mode(),
+        KeyCode::Enter => editor.replace_mode_newline(),
+        KeyCode::Backspace => editor.replace_mode_backspace(),
+        KeyCode::Char(c) => editor.replace_mode_char(c),
+
+        // Moving starts a new stretch of replacing that Backspace can't
+        // undo past.
+        KeyCode::Left | KeyCode::Down | KeyCode::Up | KeyCode::Right => {
+            editor.overtyped.clear();
+            match key.code {
+                KeyCode::Left => editor.move_left(),
+                KeyCode::Down => editor.move_down(),
+                KeyCode::Up => editor.move_up(),
+                _ => editor.move_right(),
+            }
+        }
+        _ => {}
+    }
+}
+
+fn handle_insert_key(editor: &mut Editor, key: KeyEvent, viewport_height: usize) {
+    // `Ctrl-r` takes a register name, `Ctrl-v` the character to insert.
+    if let Some(Pending::Insert(prefix)) = editor.pending.take() {
+        match prefix {
+            InsertPrefix::Register => {
+                if let KeyCode::Char(name) = key.code {
+                    editor.command_failed = !editor.insert_register(name);
+                }
+                return;
+            }
+            InsertPrefix::Literal(literal) => match literal.step(key) {
+                LiteralStep::More(next) => {
+                    editor.pending = Some(Pending::Insert(InsertPrefix::Literal(next)));
+                    return;
+                }
+                LiteralStep::Insert { ch, again } => {
+                    if let Some(ch) = ch {
+                        editor.insert_literal(ch);
+                    }
+                    if !again {
+                        return;
+                    }
+                }
+            },
+        }
+    }
+
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
     match key.code {
         KeyCode::Esc => editor.exit_insert_mode(),
-        KeyCode::Enter => editor.insert_newline(),
-        KeyCode::Backspace => editor.delete_char_back(),
+        KeyCode::Enter => {
+            let opened = editor.open_pair_block();
+            if !opened {
+                editor.insert_newline();
+            }
+        }
+        KeyCode::Backspace => {
+            let deleted = editor.delete_empty_pair();
+            if !deleted {
+                editor.delete_char_back();
+            }
+        }
+        KeyCode::Delete => editor.delete_char_forward(),
 
-        // Arrow keys still navigate
-        KeyCode::Left => editor.move_left(),
-        KeyCode::Down => editor.move_down(),
-        KeyCode::Up => editor.move_up(),
-        KeyCode::Right => editor.move_right(),
+        KeyCode::Char('w') if ctrl => editor.delete_word_back(),
+        KeyCode::Char('u') if ctrl => editor.delete_line_back(),
+        KeyCode::Char('t') if ctrl => editor.shift_insert_line(true),
+        KeyCode::Char('d') if ctrl => editor.shift_insert_line(false),
+        KeyCode::Char('r') if ctrl => {
+            editor.pending = Some(Pending::Insert(InsertPrefix::Register))
+        }
+        KeyCode::Char('v' | 'q') if ctrl => {
+            editor.pending = Some(Pending::Insert(InsertPrefix::Literal(Literal::Key)));
+        }
+        KeyCode::Char('o') if ctrl => editor.insert_one_command(),
+
+        // Arrow keys and friends still navigate
+        KeyCode::Left
+        | KeyCode::Down
+        | KeyCode::Up
+        | KeyCode::Right
+        | KeyCode::Home
+        | KeyCode::End
+        | KeyCode::PageUp
+        | KeyCode::PageDown => {
+            editor.drop_unused_autoindent();
+            match key.code {
+                KeyCode::Left => editor.move_left(),
+                KeyCode::Down => editor.move_down(),
+                KeyCode::Up => editor.move_up(),
+                KeyCode::Right => editor.move_right(),
+                KeyCode::Home => editor.cursor_col = 0,
+                KeyCode::End => editor.cursor_col = editor.buffer.line_len(editor.cursor_row),
+                KeyCode::PageUp => {
+                    editor.scroll_pages(false, 1, viewport_height);
+                }
+                _ => {
+                    editor.scroll_pages(true, 1, viewport_height);
+                }
+            }
+        }
 
         // Printable characters
-        KeyCode::Char(c) => editor.insert_char(c),
+        KeyCode::Char(c) if !ctrl => {
+            let paired = editor.insert_pair(c);
+            if !paired {
+                editor.insert_char(c);
+            }
+        }
 
         _ => {}
     }
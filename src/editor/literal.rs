@@ -0,0 +1,184 @@
+//! `Ctrl-v` in insert mode: the next key is inserted as it is, or a
+//! character code typed in decimal, octal (`o`), hex (`x`) or as a unicode
+//! codepoint (`u`, `U`).
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// A `Ctrl-v` waiting for the key to insert, or partway through a code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Literal {
+    Key,
+    Code {
+        radix: u32,
+        max_digits: usize,
+        digits: usize,
+        value: u32,
+    },
+}
+
+/// What a key typed after `Ctrl-v` comes to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiteralStep {
+    /// The code isn't finished yet.
+    More(Literal),
+    /// Insert the character, if there is one. With `again` set the key
+    /// ended the code without being part of it, and is handled as usual.
+    Insert { ch: Option<char>, again: bool },
+}
+
+impl Literal {
+    pub fn step(self, key: KeyEvent) -> LiteralStep {
+        match self {
+            Literal::Key => first_key(key),
+            Literal::Code {
+                radix,
+                max_digits,
+                digits,
+                value,
+            } => {
+                let digit = match key.code {
+                    KeyCode::Char(c) => c.to_digit(radix),
+                    _ => None,
+                };
+                let Some(digit) = digit else {
+                    let ch = (digits > 0).then(|| char::from_u32(value)).flatten();
+                    return LiteralStep::Insert { ch, again: true };
+                };
+                let value = value * radix + digit;
+                if digits + 1 == max_digits {
+                    return LiteralStep::Insert {
+                        ch: char::from_u32(value),
+                        again: false,
+                    };
+                }
+                LiteralStep::More(Literal::Code {
+                    radix,
+                    max_digits,
+                    digits: digits + 1,
+                    value,
+                })
+            }
+        }
+    }
+}
+
+fn code(radix: u32, max_digits: usize) -> LiteralStep {
+    LiteralStep::More(Literal::Code {
+        radix,
+        max_digits,
+        digits: 0,
+        value: 0,
+    })
+}
+
+/// The key right after `Ctrl-v`: a code prefix, or the key itself.
+fn first_key(key: KeyEvent) -> LiteralStep {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let ch = match key.code {
+        KeyCode::Char(c) if ctrl => control_char(c),
+        KeyCode::Char('u') => return code(16, 4),
+        KeyCode::Char('U') => return code(16, 8),
+        KeyCode::Char('x' | 'X') => return code(16, 2),
+        KeyCode::Char('o' | 'O') => return code(8, 3),
+        KeyCode::Char(c @ '0'..='9') => {
+            return LiteralStep::More(Literal::Code {
+                radix: 10,
+                max_digits: 3,
+                digits: 1,
+                value: c as u32 - '0' as u32,
+            });
+        }
+        KeyCode::Char(c) => Some(c),
+        KeyCode::Tab => Some('\t'),
+        KeyCode::Enter => Some('\n'),
+        KeyCode::Esc => Some('\x1b'),
+        KeyCode::Backspace => Some('\x08'),
+        _ => None,
+    };
+    LiteralStep::Insert { ch, again: false }
+}
+
+/// The control character typed as `Ctrl-c` (`Ctrl-a` is `\x01`).
+fn control_char(c: char) -> Option<char> {
+    match c.to_ascii_uppercase() {
+        c @ '@'..='_' => Some((c as u8 & 0x1f) as char),
+        '?' => Some('\x7f'),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Literal, LiteralStep};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    /// Feed `keys` to a fresh `Ctrl-v`, returning the last step.
+    fn feed(keys: &[KeyEvent]) -> LiteralStep {
+        let mut literal = Literal::Key;
+        let mut step = LiteralStep::More(literal);
+        for &key in keys {
+            step = literal.step(key);
+            if let LiteralStep::More(next) = step {
+                literal = next;
+            }
+        }
+        step
+    }
+
+    fn chars(s: &str) -> Vec<KeyEvent> {
+        s.chars()
+            .map(|c| KeyEvent::from(KeyCode::Char(c)))
+            .collect()
+    }
+
+    #[test]
+    fn keys_are_taken_literally() {
+        let tab = feed(&[KeyEvent::from(KeyCode::Tab)]);
+        assert_eq!(
+            tab,
+            LiteralStep::Insert {
+                ch: Some('\t'),
+                again: false
+            }
+        );
+        let ctrl_a = KeyEvent::new(KeyCode::Char('a'), KeyModifiers::CONTROL);
+        assert_eq!(
+            feed(&[ctrl_a]),
+            LiteralStep::Insert {
+                ch: Some('\x01'),
+                again: false
+            }
+        );
+    }
+
+    #[test]
+    fn codes_end_after_their_digits() {
+        for (keys, ch) in [("u00e9", 'é'), ("065", 'A'), ("x41", 'A'), ("o101", 'A')] {
+            assert_eq!(
+                feed(&chars(keys)),
+                LiteralStep::Insert {
+                    ch: Some(ch),
+                    again: false
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn short_codes_end_at_a_non_digit() {
+        assert_eq!(
+            feed(&chars("u3bg")),
+            LiteralStep::Insert {
+                ch: Some('\u{3b}'),
+                again: true
+            }
+        );
+        assert_eq!(
+            feed(&chars("ug")),
+            LiteralStep::Insert {
+                ch: None,
+                again: true
+            }
+        );
+    }
+}
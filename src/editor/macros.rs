@@ -0,0 +1,254 @@
+//! Macro recording (`q{reg}`) and playback (`@{reg}`, `:normal`). Recorded
+//! keys are kept in the register as key notation, so a macro can be edited
+//! by putting the register, changing the text and yanking it back.
+
+use crossterm::event::KeyEvent;
+
+use super::register::{Register, Registers};
+use super::{Editor, handle_key, notation};
+use crate::error::DvimError;
+
+/// How many keys macros and `:normal` may feed in before playback is cut
+/// off, so a macro that calls itself without ever failing can't hang.
+const MAX_FED_KEYS: usize = 100_000;
+
+#[derive(Debug, Default)]
+pub struct Macros {
+    /// The register being recorded into.
+    pub recording: Option<char>,
+    keys: Vec<KeyEvent>,
+    /// The register last played, for `@@`.
+    last_played: Option<char>,
+    /// Keys handled since the outermost macro or `:normal` started.
+    fed: usize,
+}
+
+/// What a register-name key (`"a`, `qa`, `@a`) is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterPrefix {
+    /// `"`: use the register for the next yank, delete or put.
+    Select,
+    /// `q`: record a macro into it.
+    Record,
+    /// `@`: play the macro in it.
+    Play,
+}
+
+impl Editor {
+    /// Note a key typed by the user while recording. Keys fed in by macros
+    /// or `:normal` aren't recorded again.
+    pub(super) fn record_macro_key(&mut self, key: KeyEvent) {
+        if self.macros.recording.is_some() && self.feeding == 0 {
+            self.macros.keys.push(key);
+        }
+    }
+
+    pub fn start_recording(&mut self, name: char) {
+        self.macros.recording = Some(name);
+        self.macros.keys.clear();
+    }
+
+    /// Stop recording (`q`) and store the keys, minus that `q`.
+    pub fn stop_recording(&mut self) {
+        let Some(name) = self.macros.recording.take() else {
+            return;
+        };
+        let mut keys = std::mem::take(&mut self.macros.keys);
+        keys.pop();
+        let text = notation::encode(&keys);
+        self.registers.set(
+            name,
+            Register {
+                text,
+                linewise: false,
+            },
+        );
+    }
+
+    /// Play the macro in register `name` [count] times (`@a`, `@@`). Stops
+    /// at the first command that fails, including inside nested macros.
+    pub fn play_macro(&mut self, name: char, count: usize, viewport_height: usize) {
+        let name = if name == '@' {
+            match self.macros.last_played {
+                Some(last) => last,
+                None => {
+                    self.command_failed = true;
+                    return;
+                }
+            }
+        } else {
+            name
+        };
+        if !Registers::is_valid_name(name) {
+            self.command_failed = true;
+            return;
+        }
+        let Some(register) = self.registers.get(name) else {
+            self.command_failed = true;
+            return;
+        };
+        self.macros.last_played = Some(name);
+        let keys = notation::parse(&register.text).repeat(count.max(1));
+
+        // Inside another macro, queue the keys to run next rather than
+        // recursing, so a macro that ends by calling itself runs in constant
+        // stack space.
+        if self.feeding > 0 {
+            for &key in keys.iter().rev() {
+                self.typeahead.push_front(key);
+            }
+            return;
+        }
+        self.feed_keys(&keys, viewport_height);
+    }
+
+    /// Run `keys` through `handle_key` as if typed, along with any keys
+    /// they queue up in turn. Returns false, dropping the rest, if a
+    /// command fails or too many keys have been fed in.
+    pub fn feed_keys(&mut self, keys: &[KeyEvent], viewport_height: usize) -> bool {
+        if self.feeding == 0 {
+            self.macros.fed = 0;
+        }
+        let base = self.typeahead.len();
+        for &key in keys.iter().rev() {
+            self.typeahead.push_front(key);
+        }
+        self.feeding += 1;
+        let mut ok = true;
+        while self.typeahead.len() > base {
+            let Some(key) = self.typeahead.pop_front() else {
+                break;
+            };
+            self.macros.fed += 1;
+            if self.macros.fed > MAX_FED_KEYS {
+                self.command_failed = true;
+                self.status_message = Some(DvimError::TooManyKeys(MAX_FED_KEYS).to_string());
+            } else {
+                let _ = handle_key(self, key, viewport_height);
+            }
+            if self.command_failed {
+                let extra = self.typeahead.len().saturating_sub(base);
+                self.typeahead.drain(..extra);
+                ok = false;
+                break;
+            }
+        }
+        self.feeding -= 1;
+        ok
+    }
+
+    /// Run `keys` in normal mode on each line from `first` to `last`
+    /// (`:normal`), applying mappings unless `remap` is false (`:normal!`).
+    /// Whatever command is left unfinished on a line is abandoned, like vim
+    /// ending it with `<Esc>`.
+    pub fn normal_on_lines(&mut self, first: usize, last: usize, keys: &str, remap: bool) {
+        let keys: Vec<KeyEvent> = keys
+            .chars()
+            .map(|c| KeyEvent::from(crossterm::event::KeyCode::Char(c)))
+            .collect();
+        let viewport_height = self.viewport_height;
+        for row in first..=last.min(self.max_row()) {
+            self.cursor_row = row;
+            self.cursor_col = 0;
+            if remap {
+                self.feed_keys(&keys, viewport_height);
+                self.flush_pending_keys(viewport_height);
+            } else {
+                self.feed_keys_noremap(&keys, viewport_height);
+            }
+            self.command_failed = false;
+            self.abandon_command();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::register::Register;
+    use super::super::{test_editor, type_keys};
+
+    #[test]
+    fn record_and_play() {
+        let mut ed = test_editor("a\nb\nc\nd\n");
+        type_keys(&mut ed, "qa$a!\x1bjq");
+        assert_eq!(ed.registers.get('a').unwrap().text, "$a!<Esc>j");
+        type_keys(&mut ed, "@a");
+        type_keys(&mut ed, "@@");
+        assert_eq!(ed.buffer.line(2).unwrap(), "c!");
+        assert_eq!(ed.cursor_row, 3);
+    }
+
+    #[test]
+    fn play_with_count() {
+        let mut ed = test_editor("abcdef\n");
+        type_keys(&mut ed, "qqxq2@q");
+        assert_eq!(ed.buffer.line(0).unwrap(), "def");
+    }
+
+    #[test]
+    fn recursive_macro_stops_on_failed_motion() {
+        let mut ed = test_editor("1\n2\n3\n4\n");
+        type_keys(&mut ed, "qaqqa0i-\x1bj@aq@a");
+        for row in 0..4 {
+            assert!(ed.buffer.line(row).unwrap().starts_with('-'));
+        }
+        assert!(!ed.buffer.line(3).unwrap().starts_with("--"));
+    }
+
+    #[test]
+    fn self_calling_macro_stops_at_an_empty_line() {
+        let mut ed = test_editor("abcdef\nxyz\n");
+        type_keys(&mut ed, "qaqqax@aq@a");
+        assert_eq!(ed.buffer.line(0).unwrap(), "");
+        assert_eq!(ed.buffer.line(1).unwrap(), "xyz");
+        assert!(ed.typeahead.is_empty());
+    }
+
+    #[test]
+    fn endless_macro_is_cut_off() {
+        let mut ed = test_editor("a\nb\n");
+        type_keys(&mut ed, "qaqqajk@aq@a");
+        assert!(ed.command_failed);
+        assert!(ed.typeahead.is_empty());
+        assert_eq!(ed.feeding, 0);
+        assert!(
+            ed.status_message
+                .as_deref()
+                .unwrap()
+                .starts_with("stopped after")
+        );
+    }
+
+    #[test]
+    fn macro_edited_as_text() {
+        let mut ed = test_editor("x\n");
+        ed.registers.set(
+            'b',
+            Register {
+                text: "$a<lt>ok><Esc>".to_string(),
+                linewise: false,
+            },
+        );
+        type_keys(&mut ed, "@b");
+        assert_eq!(ed.buffer.line(0).unwrap(), "x<ok>");
+    }
+
+    #[test]
+    fn recorded_macro_can_be_put_as_text() {
+        let mut ed = test_editor("x\n\n");
+        type_keys(&mut ed, "qaxq");
+        type_keys(&mut ed, "j\"ap");
+        assert_eq!(ed.buffer.line(1).unwrap(), "x");
+    }
+
+    #[test]
+    fn normal_on_each_line() {
+        let mut ed = test_editor("a\nb\nc\n");
+        type_keys(&mut ed, ":%normal $a;\n");
+        assert_eq!(ed.buffer.line(0).unwrap(), "a;");
+        assert_eq!(ed.buffer.line(2).unwrap(), "c;");
+        type_keys(&mut ed, ":2,3normal x\n");
+        assert_eq!(ed.buffer.line(1).unwrap(), ";");
+        assert_eq!(ed.buffer.line(0).unwrap(), "a;");
+    }
+}
@@ -0,0 +1,639 @@
+//! Applying key mappings to typed keys, and the `:map` family of commands
+//! that define them. Keys that could be the start of a mapping are held
+//! back until they can't be the start of a longer one, or until
+//! 'timeoutlen' passes without another key. A built-in prefix (`g`, `z`,
+//! `[`, `]`, `Ctrl-w`, `"`) waits for the rest of its command just as long.
+
+use std::time::{Duration, Instant};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::keymap::{Pending, dispatch_key};
+use super::{Editor, notation};
+use crate::config::KeymapSetting;
+use crate::error::DvimError;
+use crate::mapping::{self, Action, MapMode, Mapping, Rhs};
+use crate::mode::Mode;
+
+/// How deeply mappings can run one another before we give up on them as
+/// recursive.
+const MAX_MAP_DEPTH: usize = 100;
+
+/// How many characters of pending keys 'showcmd' shows.
+const SHOWCMD_WIDTH: usize = 10;
+
+#[derive(Debug, Default)]
+pub struct PendingKeys {
+    /// Typed keys that start a mapping, waiting to see which one.
+    pub keys: Vec<KeyEvent>,
+    /// The keys of the command being typed, for 'showcmd'.
+    pub command: Vec<KeyEvent>,
+    /// When the last of `keys` was typed, or a built-in prefix started
+    /// waiting, for 'timeoutlen'.
+    since: Option<Instant>,
+    /// How many `noremap` mappings are running their keys, which aren't
+    /// mapped again.
+    noremap: usize,
+    /// How many mappings are running inside one another.
+    depth: usize,
+}
+
+/// What a `:map` family command does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapCommand {
+    Map { noremap: bool },
+    Unmap,
+}
+
+/// The `:map` family, with the shortest abbreviation of each.
+const MAP_COMMANDS: &[(&str, usize)] = &[
+    ("map", 3),
+    ("noremap", 2),
+    ("unmap", 3),
+    ("nmap", 2),
+    ("nnoremap", 2),
+    ("nunmap", 3),
+    ("vmap", 2),
+    ("vnoremap", 2),
+    ("vunmap", 2),
+    ("imap", 2),
+    ("inoremap", 3),
+    ("iunmap", 2),
+    ("cmap", 2),
+    ("cnoremap", 3),
+    ("cunmap", 2),
+];
+
+/// The modes and kind of `:map` family command `name`, if it is one. The
+/// commands without a mode letter cover normal and visual mode.
+pub fn parse_map_command(name: &str) -> Option<(Vec<MapMode>, MapCommand)> {
+    let &(full, _) = MAP_COMMANDS
+        .iter()
+        .find(|&&(full, min)| name.len() >= min && full.starts_with(name))?;
+    let (modes, base) = match MapMode::ALL
+        .into_iter()
+        .find_map(|mode| full.strip_prefix(mode.letter()).map(|base| (mode, base)))
+    {
+        Some((mode, base)) if base != "oremap" => (vec![mode], base),
+        _ => (vec![MapMode::Normal, MapMode::Visual], full),
+    };
+    let command = match base {
+        "unmap" => MapCommand::Unmap,
+        base => MapCommand::Map {
+            noremap: base == "noremap",
+        },
+    };
+    Some((modes, command))
+}
+
+impl Editor {
+    /// Whose mappings apply to the next key: none while it's the argument
+    /// of a command (`f`'s character, a register name) or the motion of an
+    /// operator.
+    fn map_mode(&self) -> Option<MapMode> {
+        let awaiting_argument = self.pending_op.is_some()
+            || self
+                .pending
+                .is_some_and(|pending| !matches!(pending, Pending::Prefix(_)));
+        if awaiting_argument {
+            return None;
+        }
+        Some(match self.mode {
+            Mode::Normal => MapMode::Normal,
+            Mode::Visual | Mode::VisualLine => MapMode::Visual,
+            Mode::Insert | Mode::Replace => MapMode::Insert,
+            Mode::Command => MapMode::Command,
+        })
+    }
+
+    /// Handle `key`, applying mappings. Called by `handle_key`.
+    pub(super) fn map_key(&mut self, key: KeyEvent, viewport_height: usize) {
+        let remap = self.pending_keys.noremap == 0 && !self.dot.is_replaying();
+        let Some(mode) = self.map_mode().filter(|_| remap) else {
+            self.resolve_pending_keys(viewport_height);
+            dispatch_key(self, key, viewport_height);
+            return;
+        };
+        self.pending_keys.keys.push(key);
+        if self
+            .keymaps
+            .get(mode)
+            .lookup(&self.pending_keys.keys)
+            .longer
+        {
+            self.pending_keys.since = Some(Instant::now());
+            return;
+        }
+        self.resolve_pending_keys(viewport_height);
+    }
+
+    /// Handle the held-back keys now that no more are coming: run the
+    /// longest mapping they start with, or the first key as itself, and
+    /// then the keys after it.
+    fn resolve_pending_keys(&mut self, viewport_height: usize) {
+        let keys = std::mem::take(&mut self.pending_keys.keys);
+        self.pending_keys.since = None;
+        let Some(&first) = keys.first() else {
+            return;
+        };
+        let keymap = self.map_mode().map(|mode| self.keymaps.get(mode));
+        let found = keymap.and_then(|keymap| {
+            (1..=keys.len())
+                .rev()
+                .find_map(|n| keymap.get(&keys[..n]).map(|mapping| (n, mapping.clone())))
+        });
+        let rest = match found {
+            Some((n, mapping)) => {
+                self.run_mapping(&keys[..n], &mapping, viewport_height);
+                &keys[n..]
+            }
+            None => {
+                dispatch_key(self, first, viewport_height);
+                &keys[1..]
+            }
+        };
+        for &key in rest {
+            self.map_key(key, viewport_height);
+        }
+    }
+
+    /// Note `key` as part of the command being typed, or forget the
+    /// command's keys once it's complete. Called by `dispatch_key` after
+    /// the key is handled.
+    pub(super) fn note_command_key(&mut self, key: KeyEvent) {
+        let partial = match self.mode {
+            Mode::Normal | Mode::Visual | Mode::VisualLine => !self.nothing_pending(),
+            Mode::Insert | Mode::Replace => self.pending.is_some(),
+            Mode::Command => false,
+        };
+        if self.pending.is_some_and(Pending::times_out) {
+            self.pending_keys.since = Some(Instant::now());
+        }
+        if partial {
+            self.pending_keys.command.push(key);
+        } else {
+            self.pending_keys.command.clear();
+        }
+    }
+
+    /// What 'showcmd' shows: the end of the command being typed and any
+    /// keys held back for a mapping, with control keys as `^W`.
+    pub fn showcmd(&self) -> String {
+        let keys = self
+            .pending_keys
+            .command
+            .iter()
+            .chain(&self.pending_keys.keys);
+        let text: String = keys
+            .map(|&key| match key.code {
+                KeyCode::Char(c) if key.modifiers == KeyModifiers::CONTROL => {
+                    format!("^{}", c.to_ascii_uppercase())
+                }
+                _ => notation::encode(&[key]),
+            })
+            .collect();
+        let skip = text.chars().count().saturating_sub(SHOWCMD_WIDTH);
+        text.chars().skip(skip).collect()
+    }
+
+    /// How much longer to wait for the rest of a mapping or built-in
+    /// prefix, if one is waiting. Keys starting with `<Esc>` wait
+    /// 'ttimeoutlen' instead of 'timeoutlen'.
+    pub fn key_timeout_left(&self) -> Option<Duration> {
+        let since = self.pending_keys.since?;
+        let escape = self
+            .pending_keys
+            .keys
+            .first()
+            .is_some_and(|key| key.code == KeyCode::Esc);
+        let millis = if escape {
+            self.global_options.ttimeoutlen
+        } else {
+            self.global_options.timeoutlen
+        };
+        let timeout = Duration::from_millis(millis as u64);
+        Some(timeout.saturating_sub(since.elapsed()))
+    }
+
+    /// Stop waiting once the timeout has passed: held-back keys run as
+    /// themselves, and a built-in prefix on its own is dropped.
+    pub fn flush_pending_keys(&mut self, viewport_height: usize) {
+        if !self.pending_keys.keys.is_empty() {
+            self.resolve_pending_keys(viewport_height);
+            return;
+        }
+        self.pending_keys.since = None;
+        if self.pending.is_some_and(Pending::times_out) {
+            self.cancel_pending();
+            self.pending_keys.command.clear();
+        }
+    }
+
+    fn run_mapping(&mut self, lhs: &[KeyEvent], mapping: &Mapping, viewport_height: usize) {
+        if self.pending_keys.depth >= MAX_MAP_DEPTH {
+            self.command_failed = true;
+            self.status_message = Some(DvimError::RecursiveMapping.to_string());
+            return;
+        }
+        self.pending_keys.depth += 1;
+        match &mapping.rhs {
+            Rhs::Action(action) => self.run_action(action, viewport_height),
+            Rhs::Keys(keys) if mapping.noremap => {
+                self.feed_keys_noremap(keys, viewport_height);
+            }
+            // Like vim, a right-hand side that starts with the left-hand
+            // side doesn't map those keys again.
+            Rhs::Keys(keys) if keys.starts_with(lhs) => {
+                let ok = self.feed_keys_noremap(&keys[..lhs.len()], viewport_height);
+                if ok {
+                    self.feed_keys(&keys[lhs.len()..], viewport_height);
+                }
+            }
+            Rhs::Keys(keys) => {
+                self.feed_keys(keys, viewport_height);
+            }
+        }
+        self.pending_keys.depth -= 1;
+    }
+
+    /// Run `keys` as typed, without applying mappings to them.
+    pub(super) fn feed_keys_noremap(&mut self, keys: &[KeyEvent], viewport_height: usize) -> bool {
+        self.pending_keys.noremap += 1;
+        let ok = self.feed_keys(keys, viewport_height);
+        self.pending_keys.noremap -= 1;
+        ok
+    }
+
+    /// Carry out `action`. From insert mode it runs as if after `Ctrl-o`.
+    pub fn run_action(&mut self, action: &Action, viewport_height: usize) {
+        let mut keys = notation::parse(action.keys);
+        if self.mode.is_insert() {
+            keys.insert(0, KeyEvent::new(KeyCode::Char('o'), KeyModifiers::CONTROL));
+        }
+        self.feed_keys_noremap(&keys, viewport_height);
+    }
+
+    /// `:action {name}`.
+    pub(super) fn action_command(&mut self, name: &str) -> Result<(), DvimError> {
+        let action =
+            mapping::find_action(name).ok_or_else(|| DvimError::UnknownAction(name.to_string()))?;
+        self.run_action(action, self.viewport_height);
+        Ok(())
+    }
+
+    /// Run a `:map` family command on `args`: list mappings, add one or
+    /// remove one.
+    pub(super) fn map_command(
+        &mut self,
+        modes: &[MapMode],
+        command: MapCommand,
+        args: &str,
+    ) -> Result<(), DvimError> {
+        let args = args.trim();
+        let (lhs, rhs) = match args.split_once(char::is_whitespace) {
+            Some((lhs, rhs)) => (lhs, rhs.trim_start()),
+            None => (args, ""),
+        };
+        match command {
+            MapCommand::Unmap => self.remove_mapping(modes, lhs),
+            MapCommand::Map { .. } if rhs.is_empty() => {
+                self.status_message = Some(self.mappings_listing(modes, lhs));
+                Ok(())
+            }
+            MapCommand::Map { noremap } => self.add_mapping(modes, lhs, rhs, noremap),
+        }
+    }
+
+    /// Map `lhs` to `rhs` in each of `modes`. Both are in key notation,
+    /// with `<leader>` standing for 'mapleader'; `rhs` can instead be an
+    /// action, `<Action>(name)`.
+    pub fn add_mapping(
+        &mut self,
+        modes: &[MapMode],
+        lhs: &str,
+        rhs: &str,
+        noremap: bool,
+    ) -> Result<(), DvimError> {
+        let lhs = self.parse_keys(lhs);
+        if lhs.is_empty() {
+            return Err(DvimError::InvalidArgument("''".to_string()));
+        }
+        let rhs = match parse_action(rhs) {
+            Some(name) => {
+                let action = mapping::find_action(name)
+                    .ok_or_else(|| DvimError::UnknownAction(name.to_string()))?;
+                if let Some(mode) = modes
+                    .iter()
+                    .find(|&&mode| !matches!(mode, MapMode::Normal | MapMode::Insert))
+                {
+                    return Err(DvimError::InvalidArgument(format!(
+                        "{} can't be mapped in {}-mode",
+                        rhs,
+                        mode.letter()
+                    )));
+                }
+                Rhs::Action(action)
+            }
+            None => Rhs::Keys(self.parse_keys(rhs)),
+        };
+        for &mode in modes {
+            let mapping = Mapping {
+                rhs: rhs.clone(),
+                noremap,
+            };
+            self.keymaps.get_mut(mode).insert(&lhs, mapping);
+        }
+        Ok(())
+    }
+
+    fn remove_mapping(&mut self, modes: &[MapMode], lhs: &str) -> Result<(), DvimError> {
+        let keys = self.parse_keys(lhs);
+        let mut removed = false;
+        for &mode in modes {
+            removed |= self.keymaps.get_mut(mode).remove(&keys);
+        }
+        if !removed {
+            return Err(DvimError::NoSuchMapping(lhs.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Keys written in key notation, with `<leader>` standing for
+    /// 'mapleader'.
+    fn parse_keys(&self, text: &str) -> Vec<KeyEvent> {
+        let leader = &self.global_options.mapleader;
+        let mut expanded = String::new();
+        let mut rest = text;
+        while let Some(at) = rest.to_ascii_lowercase().find("<leader>") {
+            expanded.push_str(&rest[..at]);
+            expanded.push_str(leader);
+            rest = &rest[at + "<leader>".len()..];
+        }
+        expanded.push_str(rest);
+        notation::parse(&expanded)
+    }
+
+    /// The mappings in `modes` whose left-hand side starts with `prefix`,
+    /// one per line as `n  lhs  * rhs`, `*` marking those not remapped.
+    fn mappings_listing(&self, modes: &[MapMode], prefix: &str) -> String {
+        let prefix = self.parse_keys(prefix);
+        let mut lines = Vec::new();
+        for &mode in modes {
+            let mut mappings: Vec<(String, &Mapping)> = self
+                .keymaps
+                .get(mode)
+                .mappings()
+                .into_iter()
+                .filter(|(lhs, _)| lhs.starts_with(&prefix))
+                .map(|(lhs, mapping)| (notation::encode(&lhs), mapping))
+                .collect();
+            mappings.sort_by(|a, b| a.0.cmp(&b.0));
+            for (lhs, mapping) in mappings {
+                let rhs = match &mapping.rhs {
+                    Rhs::Keys(keys) => notation::encode(keys),
+                    Rhs::Action(action) => format!("<Action>({})", action.name),
+                };
+                let star = if mapping.noremap { '*' } else { ' ' };
+                lines.push(format!("{}  {lhs:<12} {star} {rhs}", mode.letter()));
+            }
+        }
+        if lines.is_empty() {
+            return "No mapping found".to_string();
+        }
+        lines.join("\n")
+    }
+
+    /// Add the config file's mappings, returning the errors from those
+    /// that couldn't be.
+    pub(super) fn apply_keymap_config(&mut self, keymaps: &[KeymapSetting]) -> Vec<DvimError> {
+        keymaps
+            .iter()
+            .filter_map(|setting| {
+                self.add_mapping(&[setting.mode], &setting.lhs, &setting.rhs, !setting.remap)
+                    .err()
+            })
+            .collect()
+    }
+}
+
+/// The name in `<Action>(name)`, if that's what `rhs` is.
+fn parse_action(rhs: &str) -> Option<&str> {
+    let prefix = rhs
+        .get(..8)
+        .filter(|p| p.eq_ignore_ascii_case("<action>"))?;
+    rhs[prefix.len()..].strip_prefix('(')?.strip_suffix(')')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Editor, handle_key, test_editor, type_keys};
+    use super::{MapCommand, parse_map_command};
+    use crate::mapping::MapMode;
+    use crate::mode::Mode;
+    use crossterm::event::{KeyCode, KeyEvent};
+    use std::time::Duration;
+
+    fn run(ed: &mut Editor, cmd: &str) -> anyhow::Result<()> {
+        ed.command_buffer = cmd.to_string();
+        ed.execute_command()
+    }
+
+    #[test]
+    fn map_command_names() {
+        let noremap = MapCommand::Map { noremap: true };
+        assert_eq!(
+            parse_map_command("nn"),
+            Some((vec![MapMode::Normal], noremap))
+        );
+        assert_eq!(
+            parse_map_command("no"),
+            Some((vec![MapMode::Normal, MapMode::Visual], noremap))
+        );
+        assert_eq!(
+            parse_map_command("iunmap"),
+            Some((vec![MapMode::Insert], MapCommand::Unmap))
+        );
+        assert_eq!(parse_map_command("m"), None);
+        assert_eq!(parse_map_command("normal"), None);
+    }
+
+    #[test]
+    fn noremap_runs_keys_as_they_are() {
+        let mut ed = test_editor("abc\n");
+        run(&mut ed, "nnoremap x dd").unwrap();
+        run(&mut ed, "nnoremap Q x").unwrap();
+        type_keys(&mut ed, "Q");
+        assert_eq!(ed.buffer.line(0).unwrap(), "bc");
+    }
+
+    #[test]
+    fn map_is_recursive() {
+        let mut ed = test_editor("abc\ndef\n");
+        run(&mut ed, "nnoremap x dd").unwrap();
+        run(&mut ed, "nmap Q x").unwrap();
+        type_keys(&mut ed, "Q");
+        assert_eq!(ed.buffer.line(0).unwrap(), "def");
+        // `.` repeats what ran, not the mapping.
+        assert_eq!(ed.dot.last.as_ref().unwrap().keys.len(), 2);
+    }
+
+    #[test]
+    fn rhs_starting_with_lhs_is_not_remapped() {
+        let mut ed = test_editor("a\nb\nc\n");
+        run(&mut ed, "nmap j jj").unwrap();
+        type_keys(&mut ed, "j");
+        assert_eq!(ed.cursor_row, 2);
+    }
+
+    #[test]
+    fn recursive_mapping_stops() {
+        let mut ed = test_editor("\n");
+        run(&mut ed, "nmap x ix<Esc>x").unwrap();
+        type_keys(&mut ed, "x");
+        assert_eq!(ed.status_message.as_deref(), Some("recursive mapping"));
+        assert_eq!(ed.buffer.line(0).unwrap().len(), super::MAX_MAP_DEPTH);
+    }
+
+    #[test]
+    fn ambiguous_keys_wait() {
+        let mut ed = test_editor("abc\n");
+        run(&mut ed, "inoremap jk <Esc>").unwrap();
+        type_keys(&mut ed, "ijk");
+        assert_eq!(ed.mode, Mode::Normal);
+        assert_eq!(ed.buffer.line(0).unwrap(), "abc");
+        type_keys(&mut ed, "ij");
+        assert_eq!(ed.buffer.line(0).unwrap(), "abc");
+        assert!(ed.key_timeout_left().is_some());
+        ed.flush_pending_keys(20);
+        assert_eq!(ed.buffer.line(0).unwrap(), "jabc");
+        type_keys(&mut ed, "jx");
+        assert_eq!(ed.buffer.line(0).unwrap(), "jjxabc");
+    }
+
+    #[test]
+    fn longest_match_then_the_rest() {
+        let mut ed = test_editor("abcdef\n");
+        run(&mut ed, "nnoremap a x").unwrap();
+        run(&mut ed, "nnoremap ab dd").unwrap();
+        type_keys(&mut ed, "al");
+        assert_eq!(ed.buffer.line(0).unwrap(), "bcdef");
+        assert_eq!(ed.cursor_col, 1);
+    }
+
+    #[test]
+    fn mappings_skip_command_arguments() {
+        let mut ed = test_editor("axb\n");
+        run(&mut ed, "nnoremap x dd").unwrap();
+        type_keys(&mut ed, "fxrx");
+        assert_eq!(ed.buffer.line(0).unwrap(), "axb");
+        type_keys(&mut ed, "dx");
+        assert_eq!(ed.buffer.line(0).unwrap(), "axb");
+    }
+
+    #[test]
+    fn leader_and_count() {
+        let mut ed = test_editor("1\n2\n3\n4\n");
+        run(&mut ed, "set mapleader=,").unwrap();
+        run(&mut ed, "nnoremap <leader>d dd").unwrap();
+        type_keys(&mut ed, "2,d");
+        assert_eq!(ed.buffer.line(0).unwrap(), "3");
+        run(&mut ed, "nmap ,").unwrap();
+        assert_eq!(ed.status_message.as_deref(), Some("n  ,d           * dd"));
+    }
+
+    #[test]
+    fn unmap() {
+        let mut ed = test_editor("abc\n");
+        run(&mut ed, "map Q x").unwrap();
+        run(&mut ed, "vunmap Q").unwrap();
+        run(&mut ed, "map").unwrap();
+        assert_eq!(ed.status_message.as_deref(), Some("n  Q              x"));
+        run(&mut ed, "unmap Q").unwrap();
+        assert_eq!(
+            run(&mut ed, "unmap Q").unwrap_err().to_string(),
+            "no such mapping: Q"
+        );
+    }
+
+    #[test]
+    fn actions() {
+        let mut ed = test_editor("a b\nc\nd\n");
+        run(&mut ed, "nnoremap Q <Action>(join-lines)").unwrap();
+        run(&mut ed, "inoremap <C-l> <Action>(line-start)").unwrap();
+        type_keys(&mut ed, "Q");
+        assert_eq!(ed.buffer.line(0).unwrap(), "a b c");
+        type_keys(&mut ed, "$i");
+        handle_key(
+            &mut ed,
+            KeyEvent::new(KeyCode::Char('l'), crossterm::event::KeyModifiers::CONTROL),
+            20,
+        )
+        .unwrap();
+        type_keys(&mut ed, "!");
+        assert_eq!(ed.buffer.line(0).unwrap(), "!a b c");
+        type_keys(&mut ed, "\x1b:action delete-line\n");
+        assert_eq!(ed.buffer.line(0).unwrap(), "d");
+        assert!(run(&mut ed, "action nosuch").is_err());
+        assert!(run(&mut ed, "vnoremap Q <Action>(join-lines)").is_err());
+    }
+
+    #[test]
+    fn showcmd_shows_the_command_being_typed() {
+        let mut ed = test_editor("abc\ndef\n");
+        run(&mut ed, "nnoremap ,x dd").unwrap();
+        type_keys(&mut ed, "\"a2d");
+        assert_eq!(ed.showcmd(), "\"a2d");
+        type_keys(&mut ed, "\x1b2,");
+        assert_eq!(ed.showcmd(), "2,");
+        type_keys(&mut ed, "x");
+        assert_eq!(ed.showcmd(), "");
+        handle_key(
+            &mut ed,
+            KeyEvent::new(KeyCode::Char('w'), crossterm::event::KeyModifiers::CONTROL),
+            20,
+        )
+        .unwrap();
+        assert_eq!(ed.showcmd(), "^W");
+        // With one window, `Ctrl-w` commands do nothing.
+        type_keys(&mut ed, "j");
+        assert_eq!((ed.cursor_row, ed.showcmd().as_str()), (0, ""));
+    }
+
+    #[test]
+    fn builtin_prefixes_time_out() {
+        let mut ed = test_editor("abc\ndef\n");
+        type_keys(&mut ed, "dg");
+        assert_eq!(ed.showcmd(), "dg");
+        assert!(ed.key_timeout_left().is_some());
+        ed.flush_pending_keys(20);
+        assert!(ed.is_idle());
+        assert_eq!((ed.showcmd().as_str(), ed.key_timeout_left()), ("", None));
+        type_keys(&mut ed, "j");
+        assert_eq!(ed.cursor_row, 1);
+        // A command waiting for its argument waits as long as it takes.
+        type_keys(&mut ed, "f");
+        assert_eq!(ed.key_timeout_left(), None);
+    }
+
+    #[test]
+    fn escape_sequences_wait_ttimeoutlen() {
+        let mut ed = test_editor("abc\n");
+        run(&mut ed, "inoremap <Esc>j <Esc>dd").unwrap();
+        run(&mut ed, "set ttimeoutlen=0").unwrap();
+        type_keys(&mut ed, "i\x1b");
+        assert_eq!(ed.key_timeout_left(), Some(Duration::ZERO));
+        ed.flush_pending_keys(20);
+        assert_eq!(ed.mode, Mode::Normal);
+        assert_eq!(ed.buffer.line(0).unwrap(), "abc");
+    }
+
+    #[test]
+    fn normal_bang_ignores_mappings() {
+        let mut ed = test_editor("abc\ndef\n");
+        run(&mut ed, "nnoremap x dd").unwrap();
+        run(&mut ed, "normal! x").unwrap();
+        assert_eq!(ed.buffer.line(0).unwrap(), "bc");
+        run(&mut ed, "normal x").unwrap();
+        assert_eq!(ed.buffer.line(0).unwrap(), "def");
+    }
+}
@@ -0,0 +1,276 @@
+use super::Editor;
+use crate::buffer::is_settable_mark;
+
+/// What a mark-name key is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkPrefix {
+    /// `m`: set the mark.
+    Set,
+    /// `'` (line) or `` ` `` (exact position): jump to it.
+    Jump { exact: bool },
+}
+
+impl Editor {
+    fn cursor_index(&self) -> usize {
+        self.buffer.char_index(self.cursor_row, self.cursor_col)
+    }
+
+    /// Set mark `name` at the cursor (`m{a-zA-Z}`). Returns false for a name
+    /// that can't be set.
+    pub fn set_mark(&mut self, name: char) -> bool {
+        if !is_settable_mark(name) {
+            return false;
+        }
+        self.buffer.set_mark(name, self.cursor_index());
+        true
+    }
+
+    /// Set mark `name` at a (row, col) position, for marks the editor keeps
+    /// up to date itself (`^`, `[`, `]`, `<`, `>`).
+    pub(super) fn set_mark_at(&mut self, name: char, (row, col): (usize, usize)) {
+        let idx = self.buffer.char_index(row, col);
+        self.buffer.set_mark(name, idx);
+    }
+
+    /// Move to mark `name`: its exact position for `` ` ``, or the first
+    /// non-blank of its line for `'`.
+    pub fn goto_mark(&mut self, name: char, exact: bool) -> bool {
+        let Some(idx) = self.buffer.marks().get(name) else {
+            self.status_message = Some(format!("mark not set: {name}"));
+            return false;
+        };
+        (self.cursor_row, self.cursor_col) = self.buffer.position(idx);
+        self.cursor_row = self.cursor_row.min(self.max_row());
+        if exact {
+            self.clamp_cursor_col();
+        } else {
+            self.goto_first_non_blank();
+        }
+        true
+    }
+
+    /// Remember `from` before a jump: in the jump list, and as the `'` mark
+    /// so `''` can return to it.
+    pub fn record_jump(&mut self, from: (usize, usize)) {
+        let idx = self.buffer.char_index(from.0, from.1);
+        self.buffer.push_jump(idx);
+        self.buffer.set_mark('\'', idx);
+    }
+
+    /// Go [count] entries back (`Ctrl-o`) or forward (`Ctrl-i`) in the jump
+    /// list.
+    pub fn step_jump_list(&mut self, count: isize) -> bool {
+        let current = self.cursor_index();
+        let Some(idx) = self.buffer.step_jump(current, count) else {
+            return false;
+        };
+        (self.cursor_row, self.cursor_col) = self.buffer.position(idx);
+        self.cursor_row = self.cursor_row.min(self.max_row());
+        self.clamp_cursor_col();
+        true
+    }
+
+    /// Go [count] entries back (`g;`) or forward (`g,`) in the change list.
+    pub fn step_change_list(&mut self, count: isize) -> bool {
+        let Some(idx) = self.buffer.step_change(count) else {
+            let end = if count < 0 { "start" } else { "end" };
+            self.status_message = Some(format!("at {end} of changelist"));
+            return false;
+        };
+        (self.cursor_row, self.cursor_col) = self.buffer.position(idx);
+        self.cursor_row = self.cursor_row.min(self.max_row());
+        self.clamp_cursor_col();
+        true
+    }
+
+    /// The `:marks` listing.
+    pub fn marks_listing(&self) -> String {
+        let mut out = String::from("mark line  col text");
+        for (name, idx) in self.buffer.marks().iter() {
+            out.push('\n');
+            out.push_str(&self.position_line(&format!(" {name}"), idx));
+        }
+        out
+    }
+
+    /// The `:jumps` listing, numbered by distance from the current entry,
+    /// with `>` marking where Ctrl-o/Ctrl-i are.
+    pub fn jumps_listing(&self) -> String {
+        let marks = self.buffer.marks();
+        let current = marks.jump_index();
+        let mut out = String::from(" jump line  col text");
+        for (i, &idx) in marks.jumps().iter().enumerate() {
+            let pointer = if i == current { '>' } else { ' ' };
+            let label = format!("{pointer}{:>4}", current.abs_diff(i));
+            out.push('\n');
+            out.push_str(&self.position_line(&label, idx));
+        }
+        if current >= marks.jumps().len() {
+            out.push_str("\n>");
+        }
+        out
+    }
+
+    /// The `:changes` listing, laid out like `:jumps`.
+    pub fn changes_listing(&self) -> String {
+        let marks = self.buffer.marks();
+        let current = marks.change_index();
+        let mut out = String::from("change line  col text");
+        for (i, &idx) in marks.changes().iter().enumerate() {
+            let pointer = if i == current { '>' } else { ' ' };
+            let label = format!("{pointer}{:>5}", current.abs_diff(i));
+            out.push('\n');
+            out.push_str(&self.position_line(&label, idx));
+        }
+        if current >= marks.changes().len() {
+            out.push_str("\n>");
+        }
+        out
+    }
+
+    fn position_line(&self, label: &str, idx: usize) -> String {
+        let (row, col) = self.buffer.position(idx);
+        let text = self.buffer.line(row).unwrap_or_default();
+        format!("{label:<4} {:>4} {col:>4} {}", row + 1, text.trim())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::motion::Motion;
+    use super::super::operator::Operator;
+    use super::super::{ctrl, handle_key, test_editor, type_keys};
+    use crossterm::event::{KeyCode, KeyEvent};
+
+    #[test]
+    fn set_and_jump_to_mark() {
+        let mut ed = test_editor("  one\ntwo\nthree\n");
+        ed.cursor_col = 3;
+        type_keys(&mut ed, "majj");
+        type_keys(&mut ed, "`a");
+        assert_eq!((ed.cursor_row, ed.cursor_col), (0, 3));
+        type_keys(&mut ed, "G'a");
+        assert_eq!((ed.cursor_row, ed.cursor_col), (0, 2));
+    }
+
+    #[test]
+    fn marks_follow_edits() {
+        let mut ed = test_editor("a\nb\nc\n");
+        ed.cursor_row = 2;
+        ed.set_mark('x');
+        ed.cursor_row = 0;
+        ed.apply_operator_lines(Operator::Delete, 1);
+        assert!(ed.goto_mark('x', true));
+        assert_eq!(ed.buffer.line(ed.cursor_row).unwrap(), "c");
+    }
+
+    #[test]
+    fn delete_to_mark_is_linewise() {
+        let mut ed = test_editor("a\nb\nc\nd\n");
+        type_keys(&mut ed, "majjd'a");
+        assert_eq!(ed.buffer.line(0).unwrap(), "d");
+    }
+
+    #[test]
+    fn unset_mark_fails() {
+        let mut ed = test_editor("a\n");
+        assert!(!ed.apply_motion(
+            Motion::Mark {
+                name: 'q',
+                exact: true
+            },
+            None
+        ));
+    }
+
+    #[test]
+    fn jumps_and_back() {
+        let mut ed = test_editor("a\nb\nc\nd\ne\n");
+        type_keys(&mut ed, "G");
+        type_keys(&mut ed, ":2\n");
+        ctrl(&mut ed, 'o');
+        assert_eq!(ed.cursor_row, 4);
+        ctrl(&mut ed, 'o');
+        assert_eq!(ed.cursor_row, 0);
+        handle_key(&mut ed, KeyEvent::from(KeyCode::Tab), 20).unwrap();
+        assert_eq!(ed.cursor_row, 4);
+        ctrl(&mut ed, 'i');
+        assert_eq!(ed.cursor_row, 1);
+    }
+
+    #[test]
+    fn change_list_navigation() {
+        let mut ed = test_editor("a\nb\nc\nd\n");
+        type_keys(&mut ed, "xjjx");
+        type_keys(&mut ed, "G");
+        type_keys(&mut ed, "g;");
+        assert_eq!(ed.cursor_row, 2);
+        type_keys(&mut ed, "g;");
+        assert_eq!(ed.cursor_row, 0);
+        type_keys(&mut ed, "g;");
+        assert!(ed.command_failed);
+        assert_eq!(ed.status_message.as_deref(), Some("at start of changelist"));
+        type_keys(&mut ed, "g,");
+        assert_eq!(ed.cursor_row, 2);
+        type_keys(&mut ed, "gg2g;");
+        assert_eq!(ed.cursor_row, 0);
+    }
+
+    #[test]
+    fn last_change_mark_jump() {
+        let mut ed = test_editor("a\nb\nc\n");
+        type_keys(&mut ed, "jxgg'.");
+        assert_eq!(ed.cursor_row, 1);
+    }
+
+    #[test]
+    fn context_mark_returns_from_jump() {
+        let mut ed = test_editor("a\nb\nc\n");
+        type_keys(&mut ed, "jG''");
+        assert_eq!(ed.cursor_row, 1);
+        type_keys(&mut ed, "''");
+        assert_eq!(ed.cursor_row, 2);
+    }
+
+    #[test]
+    fn percent_is_a_jump() {
+        let mut ed = test_editor("(\n)\n");
+        type_keys(&mut ed, "%");
+        assert_eq!(ed.cursor_row, 1);
+        type_keys(&mut ed, "``");
+        assert_eq!(ed.cursor_row, 0);
+    }
+
+    #[test]
+    fn special_marks() {
+        let mut ed = test_editor("abc def\n");
+        type_keys(&mut ed, "wiX\x1b");
+        assert_eq!(ed.buffer.marks().get('^'), Some(5));
+        assert_eq!(ed.buffer.marks().get('.'), Some(4));
+        type_keys(&mut ed, "0vly");
+        assert_eq!(ed.buffer.marks().get('<'), Some(0));
+        assert_eq!(ed.buffer.marks().get('>'), Some(1));
+        assert_eq!(ed.buffer.marks().get('['), Some(0));
+        assert_eq!(ed.buffer.marks().get(']'), Some(1));
+    }
+
+    #[test]
+    fn putting_nothing_keeps_the_change_marks() {
+        let mut ed = test_editor("abc\n");
+        type_keys(&mut ed, "lyl");
+        type_keys(&mut ed, "0y0P");
+        assert_eq!(ed.buffer.line(0).unwrap(), "babc");
+        assert_eq!(ed.buffer.marks().get('['), Some(0));
+        assert_eq!(ed.buffer.marks().get(']'), Some(0));
+    }
+
+    #[test]
+    fn listings() {
+        let mut ed = test_editor("a\nb\n");
+        type_keys(&mut ed, "jmbgg");
+        let marks = ed.marks_listing();
+        assert!(marks.contains("\n b      2    0 b"), "{marks}");
+        let jumps = ed.jumps_listing();
+        assert!(jumps.ends_with("\n>"), "{jumps}");
+    }
+}
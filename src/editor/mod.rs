@@ -1,27 +1,124 @@
+mod bracket;
+mod case;
 mod command;
 mod deletion;
+mod file;
+mod highlight;
+mod indent;
 mod insert;
+mod join;
 mod keymap;
+mod literal;
+mod macros;
+mod mapping;
+mod marks;
+mod motion;
+mod mouse;
 mod movement;
+mod notation;
+mod operator;
+mod options;
+mod pairs;
+mod register;
+mod repeat;
+mod replace;
+mod scroll;
+mod structure;
+mod text_object;
+mod visual;
+mod wrap;
 
 pub use keymap::handle_key;
+pub use text_object::TextRange;
+pub use wrap::cell_width;
+
+use std::collections::VecDeque;
+
+use crossterm::event::KeyEvent;
 
 use crate::buffer::Buffer;
+use crate::config::Config;
+use crate::mapping::Keymaps;
 use crate::mode::Mode;
+use crate::options::{GlobalOptions, WindowOptions};
+use crate::theme::Theme;
+use keymap::Pending;
+use macros::Macros;
+use mapping::PendingKeys;
+use motion::FindChar;
+use operator::Operator;
+use register::Registers;
+use repeat::DotRecorder;
+use replace::Overtyped;
 
 pub struct Editor {
     pub buffer: Buffer,
     pub cursor_row: usize,
     pub cursor_col: usize,
     pub scroll_offset: usize,
+    /// The first column shown when 'wrap' is off.
+    pub left_col: usize,
     pub mode: Mode,
     pub running: bool,
-    /// Tracks whether the previous key was 'g' (for the gg command).
-    pub pending_g: bool,
-    /// Tracks whether the previous key was 'd' (for the dd command).
-    pub pending_d: bool,
+    /// Set by `Ctrl-z` and `:suspend` for the main loop to stop the
+    /// process.
+    pub suspend: bool,
+    /// A command key waiting for the key after it: the rest of a `g` or
+    /// `z` command, an `f` target, a register or mark name.
+    pub pending: Option<Pending>,
+    /// An operator (`d`, `c`, `y`, `gu`...) waiting for the motion it applies to.
+    pub pending_op: Option<Operator>,
+    /// The count typed before `pending_op`, multiplied into the motion count.
+    pub op_count: Option<usize>,
+    /// A count being typed before a command (the `3` in `3w`).
+    pub pending_count: Option<usize>,
+    /// Set while a normal-mode command typed after `Ctrl-o` runs, to go
+    /// back to insert mode when it's done.
+    pub insert_normal: bool,
+    /// The register named with `"x` for the next yank, delete or put.
+    pub selected_register: Option<char>,
+    /// The last character find, repeated by `;` and `,`.
+    pub last_find: Option<FindChar>,
+    /// Set while the cursor line holds only the indent given to it as a new
+    /// line, which is removed again if nothing is typed there.
+    pub autoindented: bool,
+    /// What replace mode typed over, so Backspace can put it back.
+    pub overtyped: Vec<Overtyped>,
+    /// The end of a visual selection that stays put while the cursor moves.
+    pub visual_start: (usize, usize),
+    /// Yanked and deleted text.
+    pub registers: Registers,
+    /// Records changes for `.` to repeat.
+    pub dot: DotRecorder,
+    /// Macro recording state.
+    pub macros: Macros,
+    /// Keys queued by macros, waiting to be handled.
+    pub typeahead: VecDeque<KeyEvent>,
+    /// How many macros or `:normal` commands are feeding in keys.
+    pub feeding: usize,
+    /// Key mappings for each mode.
+    pub keymaps: Keymaps,
+    /// Keys held back while they could be the start of a mapping.
+    pub pending_keys: PendingKeys,
+    /// Set when the last key's command couldn't be carried out (a motion
+    /// that went nowhere, a missing text object), stopping any macro.
+    pub command_failed: bool,
+    /// The text area height as of the last key, for commands that run keys.
+    pub viewport_height: usize,
+    /// The width of the text area beside the gutter as of the last draw,
+    /// for laying out wrapped lines. Zero until it's known.
+    pub text_width: usize,
     /// The text being typed in command mode (after ':').
     pub command_buffer: String,
+    /// A message or error to show in the status bar until the next keypress.
+    pub status_message: Option<String>,
+    pub global_options: GlobalOptions,
+    /// The options local to the (only) window.
+    pub window_options: WindowOptions,
+    /// The config file's settings, kept to reapply when the filetype changes.
+    pub config: Config,
+    /// The colors everything is drawn in.
+    pub theme: Theme,
 }
 
 impl Editor {
@@ -31,11 +128,36 @@ impl Editor {
             cursor_row: 0,
             cursor_col: 0,
             scroll_offset: 0,
+            left_col: 0,
             mode: Mode::Normal,
             running: true,
-            pending_g: false,
-            pending_d: false,
+            suspend: false,
+            pending: None,
+            pending_op: None,
+            op_count: None,
+            pending_count: None,
+            insert_normal: false,
+            selected_register: None,
+            last_find: None,
+            autoindented: false,
+            overtyped: Vec::new(),
+            visual_start: (0, 0),
+            registers: Registers::default(),
+            dot: DotRecorder::default(),
+            macros: Macros::default(),
+            typeahead: VecDeque::new(),
+            feeding: 0,
+            keymaps: Keymaps::default(),
+            pending_keys: PendingKeys::default(),
+            command_failed: false,
+            viewport_height: 0,
+            text_width: 0,
             command_buffer: String::new(),
+            status_message: None,
+            global_options: GlobalOptions::default(),
+            window_options: WindowOptions::default(),
+            config: Config::default(),
+            theme: Theme::default(),
         }
     }
 
@@ -43,6 +165,42 @@ impl Editor {
         self.running = false;
     }
 
+    /// Whether we're in normal mode with no command partly typed.
+    pub(crate) fn is_idle(&self) -> bool {
+        self.mode == Mode::Normal && !self.insert_normal && self.nothing_pending()
+    }
+
+    /// Whether no normal-mode command is partly typed.
+    fn nothing_pending(&self) -> bool {
+        self.pending_op.is_none()
+            && self.op_count.is_none()
+            && self.pending_count.is_none()
+            && self.pending.is_none()
+            && self.selected_register.is_none()
+    }
+
+    /// Drop any half-typed command.
+    pub(crate) fn cancel_pending(&mut self) {
+        self.pending_op = None;
+        self.op_count = None;
+        self.pending_count = None;
+        self.pending = None;
+        self.selected_register = None;
+    }
+
+    /// Drop any half-typed command and get back to normal mode, as if
+    /// `<Esc>` had been pressed until nothing was left.
+    pub(crate) fn abandon_command(&mut self) {
+        self.cancel_pending();
+        self.insert_normal = false;
+        match self.mode {
+            Mode::Normal => {}
+            Mode::Insert | Mode::Replace => self.exit_insert_mode(),
+            Mode::Command => self.exit_command_mode(),
+            Mode::Visual | Mode::VisualLine => self.exit_visual_mode(),
+        }
+    }
+
     /// The last valid cursor row (skips the trailing empty line ropey adds).
     pub(crate) fn max_row(&self) -> usize {
         let count = self.buffer.line_count();
@@ -54,11 +212,11 @@ impl Editor {
     }
 
     /// Clamp cursor_col so it doesn't extend past the current line length.
-    /// In Normal mode the cursor sits on the last char; in Insert mode it can
-    /// be one past the end (append position).
+    /// In Normal mode the cursor sits on the last char; in Insert and Replace
+    /// mode it can be one past the end (append position).
     pub(crate) fn clamp_cursor_col(&mut self) {
         let line_len = self.buffer.line_len(self.cursor_row);
-        if self.mode == Mode::Insert {
+        if self.mode.is_insert() {
             self.cursor_col = self.cursor_col.min(line_len);
         } else if line_len == 0 {
             self.cursor_col = 0;
@@ -70,9 +228,40 @@ impl Editor {
 
 #[cfg(test)]
 pub(crate) fn test_editor(content: &str) -> Editor {
+    editor_with_suffix(content, "")
+}
+
+/// An editor on `content` in a file ending in `suffix`, so the filetype
+/// follows from it.
+#[cfg(test)]
+pub(crate) fn editor_with_suffix(content: &str, suffix: &str) -> Editor {
     use std::io::Write;
-    let mut tmp = tempfile::NamedTempFile::new().unwrap();
+    let mut tmp = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
     tmp.write_all(content.as_bytes()).unwrap();
     let buf = Buffer::from_file(tmp.path().to_path_buf()).unwrap();
     Editor::new(buf)
 }
+
+/// Type `keys` into `editor`, with `\x1b` for Esc, `\n` for Enter and
+/// `\x08` for Backspace.
+#[cfg(test)]
+pub(crate) fn type_keys(editor: &mut Editor, keys: &str) {
+    use crossterm::event::{KeyCode, KeyEvent};
+    for c in keys.chars() {
+        let key = match c {
+            '\x1b' => KeyEvent::from(KeyCode::Esc),
+            '\n' => KeyEvent::from(KeyCode::Enter),
+            '\x08' => KeyEvent::from(KeyCode::Backspace),
+            c => KeyEvent::from(KeyCode::Char(c)),
+        };
+        handle_key(editor, key, 20).unwrap();
+    }
+}
+
+/// Type Ctrl-`c` into `editor`.
+#[cfg(test)]
+pub(crate) fn ctrl(editor: &mut Editor, c: char) {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+    handle_key(editor, key, 20).unwrap();
+}
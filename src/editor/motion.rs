@@ -0,0 +1,326 @@
+use super::Editor;
+
+/// A cursor motion. Motions can be used on their own to move the cursor, or
+/// as the target of an operator (`dw`, `ct)`), in which case the text between
+/// the old and new cursor positions is operated on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    WordForward,
+    WordBackward,
+    WordEnd,
+    /// `ge`
+    WordEndBackward,
+    BigWordForward,
+    BigWordBackward,
+    BigWordEnd,
+    /// `gE`
+    BigWordEndBackward,
+    /// `}`
+    ParagraphForward,
+    /// `{`
+    ParagraphBackward,
+    /// `)`
+    SentenceForward,
+    /// `(`
+    SentenceBackward,
+    /// `]]`
+    SectionForward,
+    /// `[[`
+    SectionBackward,
+    /// `][`
+    SectionEndForward,
+    /// `[]`
+    SectionEndBackward,
+    LineStart,
+    LineEnd,
+    FirstNonBlank,
+    /// `gj`
+    DisplayDown,
+    /// `gk`
+    DisplayUp,
+    /// `g0`
+    DisplayLineStart,
+    /// `g$`
+    DisplayLineEnd,
+    /// `gg`, or line [count] when a count is given.
+    Top,
+    /// `G`, or line [count] when a count is given.
+    Bottom,
+    ViewportTop,
+    /// `M`, carrying the viewport height it was typed with.
+    ViewportMiddle(usize),
+    /// `L`, carrying the viewport height it was typed with.
+    ViewportBottom(usize),
+    FindChar(FindChar),
+    /// `;`
+    RepeatFind,
+    /// `,`
+    RepeatFindReverse,
+    /// `%` without a count.
+    MatchBracket,
+    /// `N%`: line N percent of the way through the file.
+    FilePercent(usize),
+    /// `[(`, `[{`, `])` or `]}`, carrying the bracket to find.
+    UnmatchedBracket(char),
+    /// `'x` (to the line) or `` `x `` (to the exact position).
+    Mark {
+        name: char,
+        exact: bool,
+    },
+}
+
+/// How an operator treats the text a motion moves over (see vim's
+/// `:help exclusive` and `:help linewise`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotionKind {
+    /// The character under the end position is not included.
+    Exclusive,
+    /// The character under the end position is included.
+    Inclusive,
+    /// Whole lines from the start row to the end row are included.
+    Linewise,
+}
+
+/// The direction and landing spot of an in-line character search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindKind {
+    /// `f`: forward, onto the character.
+    Forward,
+    /// `F`: backward, onto the character.
+    Backward,
+    /// `t`: forward, stopping just before the character.
+    TillForward,
+    /// `T`: backward, stopping just after the character.
+    TillBackward,
+}
+
+impl FindKind {
+    pub fn is_forward(self) -> bool {
+        matches!(self, FindKind::Forward | FindKind::TillForward)
+    }
+
+    pub fn is_till(self) -> bool {
+        matches!(self, FindKind::TillForward | FindKind::TillBackward)
+    }
+
+    /// The same search in the opposite direction (used by `,`).
+    pub fn reversed(self) -> Self {
+        match self {
+            FindKind::Forward => FindKind::Backward,
+            FindKind::Backward => FindKind::Forward,
+            FindKind::TillForward => FindKind::TillBackward,
+            FindKind::TillBackward => FindKind::TillForward,
+        }
+    }
+}
+
+/// A complete `f{char}`-style search, remembered for `;` and `,`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FindChar {
+    pub kind: FindKind,
+    pub ch: char,
+}
+
+impl Motion {
+    pub fn kind(self, editor: &Editor) -> MotionKind {
+        match self {
+            Motion::Up
+            | Motion::Down
+            | Motion::Top
+            | Motion::Bottom
+            | Motion::ViewportTop
+            | Motion::ViewportMiddle(_)
+            | Motion::ViewportBottom(_)
+            | Motion::FilePercent(_)
+            | Motion::Mark { exact: false, .. } => MotionKind::Linewise,
+            Motion::WordEnd
+            | Motion::WordEndBackward
+            | Motion::BigWordEnd
+            | Motion::BigWordEndBackward
+            | Motion::LineEnd
+            | Motion::DisplayLineEnd
+            | Motion::MatchBracket => MotionKind::Inclusive,
+            Motion::FindChar(find) => find_kind(find.kind),
+            Motion::RepeatFind | Motion::RepeatFindReverse => match editor.last_find {
+                Some(last) if self == Motion::RepeatFindReverse => find_kind(last.kind.reversed()),
+                Some(last) => find_kind(last.kind),
+                None => MotionKind::Exclusive,
+            },
+            _ => MotionKind::Exclusive,
+        }
+    }
+
+    /// Whether moving this way is a jump, remembered in the jump list and
+    /// the `'` mark.
+    pub fn is_jump(self) -> bool {
+        matches!(
+            self,
+            Motion::Top
+                | Motion::Bottom
+                | Motion::ViewportTop
+                | Motion::ViewportMiddle(_)
+                | Motion::ViewportBottom(_)
+                | Motion::ParagraphForward
+                | Motion::ParagraphBackward
+                | Motion::SentenceForward
+                | Motion::SentenceBackward
+                | Motion::SectionForward
+                | Motion::SectionBackward
+                | Motion::SectionEndForward
+                | Motion::SectionEndBackward
+                | Motion::MatchBracket
+                | Motion::FilePercent(_)
+                | Motion::Mark { .. }
+        )
+    }
+}
+
+/// Forward finds include the target character; backward ones stop short of
+/// the cursor's original position.
+fn find_kind(kind: FindKind) -> MotionKind {
+    if kind.is_forward() {
+        MotionKind::Inclusive
+    } else {
+        MotionKind::Exclusive
+    }
+}
+
+impl Editor {
+    /// Move the cursor by `motion`, `count` times where that makes sense.
+    /// Returns false if the motion couldn't be made (e.g. `fx` with no `x`
+    /// on the line), in which case any operator should be abandoned.
+    pub fn apply_motion(&mut self, motion: Motion, count: Option<usize>) -> bool {
+        let n = count.unwrap_or(1).max(1);
+        let before = (self.cursor_row, self.cursor_col);
+        match motion {
+            Motion::Left => (0..n).for_each(|_| self.move_left()),
+            Motion::Right => (0..n).for_each(|_| self.move_right()),
+            Motion::Up => (0..n).for_each(|_| self.move_up()),
+            Motion::Down => (0..n).for_each(|_| self.move_down()),
+            Motion::WordForward => (0..n).for_each(|_| self.move_word_forward()),
+            Motion::WordBackward => (0..n).for_each(|_| self.move_word_backward()),
+            Motion::WordEnd => (0..n).for_each(|_| self.move_word_end()),
+            Motion::WordEndBackward => (0..n).for_each(|_| self.move_word_end_backward()),
+            Motion::BigWordForward => (0..n).for_each(|_| self.move_big_word_forward()),
+            Motion::BigWordBackward => (0..n).for_each(|_| self.move_big_word_backward()),
+            Motion::BigWordEnd => (0..n).for_each(|_| self.move_big_word_end()),
+            Motion::BigWordEndBackward => {
+                (0..n).for_each(|_| self.move_big_word_end_backward());
+            }
+            Motion::ParagraphForward => (0..n).for_each(|_| self.move_paragraph_forward()),
+            Motion::ParagraphBackward => (0..n).for_each(|_| self.move_paragraph_backward()),
+            Motion::SentenceForward => (0..n).for_each(|_| self.move_sentence_forward()),
+            Motion::SentenceBackward => (0..n).for_each(|_| self.move_sentence_backward()),
+            Motion::SectionForward => (0..n).for_each(|_| self.move_section_forward()),
+            Motion::SectionBackward => (0..n).for_each(|_| self.move_section_backward()),
+            Motion::SectionEndForward => (0..n).for_each(|_| self.move_section_end_forward()),
+            Motion::SectionEndBackward => {
+                (0..n).for_each(|_| self.move_section_end_backward());
+            }
+            Motion::LineStart => {
+                self.goto_line_start();
+                return true;
+            }
+            Motion::LineEnd => {
+                (1..n).for_each(|_| self.move_down());
+                self.goto_line_end();
+                return true;
+            }
+            Motion::FirstNonBlank => {
+                self.goto_first_non_blank();
+                return true;
+            }
+            Motion::DisplayDown => (0..n).for_each(|_| self.move_display_row(true)),
+            Motion::DisplayUp => (0..n).for_each(|_| self.move_display_row(false)),
+            Motion::DisplayLineStart => {
+                self.goto_display_row_start();
+                return true;
+            }
+            Motion::DisplayLineEnd => {
+                (1..n).for_each(|_| self.move_display_row(true));
+                self.goto_display_row_end();
+                return true;
+            }
+            Motion::Top | Motion::Bottom => {
+                match count {
+                    Some(line) => self.goto_line(line),
+                    None if motion == Motion::Top => self.goto_top(),
+                    None => self.goto_bottom(),
+                }
+                return true;
+            }
+            Motion::ViewportTop => {
+                self.goto_viewport_top();
+                (1..n).for_each(|_| self.move_down());
+                return true;
+            }
+            Motion::ViewportMiddle(height) => {
+                self.goto_viewport_middle(height);
+                return true;
+            }
+            Motion::ViewportBottom(height) => {
+                self.goto_viewport_bottom(height);
+                (1..n).for_each(|_| self.move_up());
+                return true;
+            }
+            Motion::FindChar(find) => return self.find_char(find, n),
+            Motion::RepeatFind => return self.repeat_find(false, n),
+            Motion::RepeatFindReverse => return self.repeat_find(true, n),
+            Motion::MatchBracket => return self.match_bracket(),
+            Motion::FilePercent(percent) => {
+                self.goto_percent(percent);
+                return true;
+            }
+            Motion::UnmatchedBracket(target) => return self.goto_unmatched_bracket(target, n),
+            Motion::Mark { name, exact } => return self.goto_mark(name, exact),
+        }
+        (self.cursor_row, self.cursor_col) != before
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_editor;
+    use super::*;
+
+    #[test]
+    fn apply_motion_repeats_with_count() {
+        let mut ed = test_editor("one two three four\n");
+        assert!(ed.apply_motion(Motion::WordForward, Some(3)));
+        assert_eq!(ed.cursor_col, 14);
+    }
+
+    #[test]
+    fn apply_motion_reports_failure_when_stuck() {
+        let mut ed = test_editor("a\nb\n");
+        assert!(!ed.apply_motion(Motion::Up, None));
+    }
+
+    #[test]
+    fn apply_motion_bottom_with_count_goes_to_line() {
+        let mut ed = test_editor("a\nb\nc\nd\n");
+        ed.apply_motion(Motion::Bottom, Some(2));
+        assert_eq!(ed.cursor_row, 1);
+    }
+
+    #[test]
+    fn apply_motion_paragraph_with_count() {
+        let mut ed = test_editor("a\n\nb\n\nc\n");
+        assert!(ed.apply_motion(Motion::ParagraphForward, Some(2)));
+        assert_eq!(ed.cursor_row, 3);
+    }
+
+    #[test]
+    fn find_motion_kinds() {
+        let ed = test_editor("abc\n");
+        let f = |kind| Motion::FindChar(FindChar { kind, ch: 'x' });
+        assert_eq!(f(FindKind::Forward).kind(&ed), MotionKind::Inclusive);
+        assert_eq!(f(FindKind::TillForward).kind(&ed), MotionKind::Inclusive);
+        assert_eq!(f(FindKind::Backward).kind(&ed), MotionKind::Exclusive);
+        assert_eq!(f(FindKind::TillBackward).kind(&ed), MotionKind::Exclusive);
+    }
+}
@@ -0,0 +1,167 @@
+//! The mouse, with 'mouse' set: clicking moves the cursor, dragging
+//! selects, and the wheel scrolls.
+
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+
+use super::Editor;
+use crate::mode::Mode;
+
+/// How many lines a turn of the wheel scrolls.
+const WHEEL_LINES: usize = 3;
+
+impl Editor {
+    /// Whether 'mouse' covers the current mode.
+    fn mouse_active(&self) -> bool {
+        let flag = match self.mode {
+            Mode::Normal => 'n',
+            Mode::Visual | Mode::VisualLine => 'v',
+            Mode::Insert | Mode::Replace => 'i',
+            Mode::Command => 'c',
+        };
+        let mouse = &self.global_options.mouse;
+        mouse.contains('a') || mouse.contains(flag)
+    }
+
+    /// Handle a mouse event. `text_left` is the screen column the text
+    /// starts at, past the gutter.
+    pub fn handle_mouse(&mut self, event: MouseEvent, text_left: usize, viewport_height: usize) {
+        if !self.mouse_active() {
+            return;
+        }
+        let y = event.row as usize;
+        let x = (event.column as usize).saturating_sub(text_left);
+        match event.kind {
+            MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {
+                let down = event.kind == MouseEventKind::ScrollDown;
+                self.scroll_lines(down, WHEEL_LINES, viewport_height);
+            }
+            // Below the text area there's only the status line, and with a
+            // single window clicking it does nothing.
+            _ if y >= viewport_height => {}
+            MouseEventKind::Down(MouseButton::Left) => self.click(x, y),
+            MouseEventKind::Drag(MouseButton::Left) => self.drag(x, y),
+            _ => {}
+        }
+    }
+
+    /// Move the cursor to what's shown at (`x`, `y`), dropping any
+    /// half-typed command or selection.
+    fn click(&mut self, x: usize, y: usize) {
+        self.status_message = None;
+        match self.mode {
+            Mode::Command => return,
+            Mode::Visual | Mode::VisualLine => self.exit_visual_mode(),
+            Mode::Insert | Mode::Replace => self.drop_unused_autoindent(),
+            Mode::Normal => {}
+        }
+        self.cancel_pending();
+        (self.cursor_row, self.cursor_col) = self.position_at(x, y);
+        self.clamp_cursor_col();
+    }
+
+    /// Select from where the button went down to (`x`, `y`).
+    fn drag(&mut self, x: usize, y: usize) {
+        match self.mode {
+            Mode::Normal => self.enter_visual_mode(false),
+            Mode::Visual | Mode::VisualLine => {}
+            _ => return,
+        }
+        (self.cursor_row, self.cursor_col) = self.position_at(x, y);
+        self.clamp_cursor_col();
+    }
+
+    /// The buffer position shown at screen row `y` and text column `x`, or
+    /// the nearest one to it. Below the last line is the last line.
+    fn position_at(&self, x: usize, y: usize) -> (usize, usize) {
+        let mut top = 0;
+        for row in self.scroll_offset..=self.max_row() {
+            let rows = self.display_rows(row);
+            if y < top + rows.len() {
+                let index = y - top;
+                let display = rows[index];
+                let col = self.display_col(row, &display, x);
+                // Past the end of a row that wraps is its last character.
+                if index + 1 < rows.len() {
+                    return (row, col.min(display.end - 1));
+                }
+                return (row, col);
+            }
+            top += rows.len();
+        }
+        (self.max_row(), x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Editor, test_editor};
+    use crate::mode::Mode;
+    use crossterm::event::{KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+
+    fn mouse(ed: &mut Editor, kind: MouseEventKind, column: u16, row: u16) {
+        let event = MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        };
+        ed.handle_mouse(event, 4, 10);
+    }
+
+    fn click(ed: &mut Editor, column: u16, row: u16) {
+        mouse(ed, MouseEventKind::Down(MouseButton::Left), column, row);
+    }
+
+    #[test]
+    fn clicks_need_the_mouse_option() {
+        let mut ed = test_editor("one\ntwo\n");
+        click(&mut ed, 5, 1);
+        assert_eq!((ed.cursor_row, ed.cursor_col), (0, 0));
+        ed.global_options.mouse = "a".to_string();
+        click(&mut ed, 5, 1);
+        assert_eq!((ed.cursor_row, ed.cursor_col), (1, 1));
+        ed.global_options.mouse = "i".to_string();
+        click(&mut ed, 4, 0);
+        assert_eq!((ed.cursor_row, ed.cursor_col), (1, 1));
+    }
+
+    #[test]
+    fn clicks_map_through_gutter_scroll_and_wrap() {
+        let mut ed = test_editor("abcdefghij\nxy\nz\n");
+        ed.global_options.mouse = "a".to_string();
+        ed.text_width = 4;
+        click(&mut ed, 5, 1);
+        assert_eq!((ed.cursor_row, ed.cursor_col), (0, 5));
+        click(&mut ed, 9, 3);
+        assert_eq!((ed.cursor_row, ed.cursor_col), (1, 1));
+        click(&mut ed, 0, 9);
+        assert_eq!((ed.cursor_row, ed.cursor_col), (2, 0));
+        ed.scroll_offset = 1;
+        click(&mut ed, 4, 0);
+        assert_eq!((ed.cursor_row, ed.cursor_col), (1, 0));
+    }
+
+    #[test]
+    fn dragging_selects() {
+        let mut ed = test_editor("one two\nthree\n");
+        ed.global_options.mouse = "a".to_string();
+        click(&mut ed, 5, 0);
+        mouse(&mut ed, MouseEventKind::Drag(MouseButton::Left), 6, 1);
+        assert_eq!(ed.mode, Mode::Visual);
+        assert_eq!(ed.visual_start, (0, 1));
+        assert_eq!((ed.cursor_row, ed.cursor_col), (1, 2));
+        click(&mut ed, 4, 0);
+        assert_eq!(ed.mode, Mode::Normal);
+    }
+
+    #[test]
+    fn the_wheel_scrolls() {
+        let content: String = (0..20).map(|n| format!("{n}\n")).collect();
+        let mut ed = test_editor(&content);
+        ed.global_options.mouse = "a".to_string();
+        mouse(&mut ed, MouseEventKind::ScrollDown, 0, 0);
+        assert_eq!((ed.scroll_offset, ed.cursor_row), (3, 3));
+        mouse(&mut ed, MouseEventKind::ScrollUp, 0, 0);
+        assert_eq!(ed.scroll_offset, 0);
+    }
+}
@@ -1,5 +1,6 @@
 use super::Editor;
-use crate::mode::Mode;
+use super::motion::FindChar;
+use crate::buffer::CharClass;
 
 impl Editor {
     pub fn move_left(&mut self) {
@@ -21,7 +22,7 @@ impl Editor {
 
     pub fn move_right(&mut self) {
         let line_len = self.buffer.line_len(self.cursor_row);
-        let max_col = if self.mode == Mode::Insert {
+        let max_col = if self.mode.is_insert() {
             line_len
         } else if line_len > 0 {
             line_len - 1
@@ -43,67 +44,61 @@ impl Editor {
         self.clamp_cursor_col();
     }
 
+    /// Jump to 1-indexed line `n`, clamped to the buffer (`:n`, `nG`).
+    pub fn goto_line(&mut self, n: usize) {
+        self.cursor_row = n.saturating_sub(1).min(self.max_row());
+        self.clamp_cursor_col();
+    }
+
     pub fn goto_viewport_top(&mut self) {
         self.cursor_row = self.scroll_offset;
         self.clamp_cursor_col();
     }
 
+    /// `M`: to the line in the middle of the screen rows in use.
     pub fn goto_viewport_middle(&mut self, viewport_height: usize) {
-        let top = self.scroll_offset;
-        let bottom = (self.scroll_offset + viewport_height - 1).min(self.max_row());
-        self.cursor_row = (top + bottom) / 2;
+        let lines = self.visible_lines(viewport_height);
+        let middle = (lines.iter().map(|&(_, rows)| rows).sum::<usize>()).saturating_sub(1) / 2;
+        let mut used = 0;
+        for (row, rows) in lines {
+            self.cursor_row = row;
+            used += rows;
+            if used > middle {
+                break;
+            }
+        }
         self.clamp_cursor_col();
     }
 
+    /// `L`: to the last line wholly on screen.
     pub fn goto_viewport_bottom(&mut self, viewport_height: usize) {
-        let bottom = self.scroll_offset + viewport_height - 1;
-        self.cursor_row = bottom.min(self.max_row());
-        self.clamp_cursor_col();
-    }
-
-    pub fn scroll_half_page_down(&mut self, viewport_height: usize) {
-        let half = viewport_height / 2;
-        let max_row = self.max_row();
-        self.cursor_row = (self.cursor_row + half).min(max_row);
-        self.clamp_cursor_col();
-    }
-
-    pub fn scroll_half_page_up(&mut self, viewport_height: usize) {
-        let half = viewport_height / 2;
-        self.cursor_row = self.cursor_row.saturating_sub(half);
-        self.clamp_cursor_col();
-    }
-
-    /// Ensure scroll_offset keeps the cursor visible within the viewport.
-    pub fn adjust_scroll(&mut self, viewport_height: usize) {
-        if self.cursor_row < self.scroll_offset {
-            self.scroll_offset = self.cursor_row;
-        }
-        if self.cursor_row >= self.scroll_offset + viewport_height {
-            self.scroll_offset = self.cursor_row - viewport_height + 1;
+        if let Some(&(row, _)) = self.visible_lines(viewport_height).last() {
+            self.cursor_row = row;
         }
+        self.clamp_cursor_col();
     }
 
     // ── Character classification helpers ──────────────────────────────
 
-    /// Classify a character into one of three categories used for word motions.
-    /// 0 = whitespace, 1 = word (alphanumeric / underscore), 2 = punctuation.
-    #[allow(dead_code)]
-    fn char_class(c: char) -> u8 {
-        if c.is_whitespace() {
-            0
-        } else if c.is_alphanumeric() || c == '_' {
-            1
-        } else {
-            2
-        }
+    /// Classify a character for word motions using the buffer's 'iskeyword'.
+    /// With `big` set, every non-blank character belongs to one WORD class.
+    pub(super) fn char_class(&self, c: char, big: bool) -> CharClass {
+        self.buffer.char_class(c, big)
     }
 
     // ── Word motions ──────────────────────────────────────────────────
 
     /// Move cursor to the start of the next word (vim `w`).
-    #[allow(dead_code)]
     pub fn move_word_forward(&mut self) {
+        self.word_forward(false);
+    }
+
+    /// Move cursor to the start of the next WORD (vim `W`).
+    pub fn move_big_word_forward(&mut self) {
+        self.word_forward(true);
+    }
+
+    fn word_forward(&mut self, big: bool) {
         let max_row = self.max_row();
         let mut row = self.cursor_row;
         let mut col = self.cursor_col;
@@ -134,10 +129,10 @@ impl Editor {
         }
 
         // Step 1: skip over the current word (contiguous chars of the same class).
-        let start_class = Self::char_class(chars[col]);
-        if start_class != 0 {
+        let start_class = self.char_class(chars[col], big);
+        if start_class != CharClass::Blank {
             // On a word or punctuation — skip the rest of this run.
-            while col < chars.len() && Self::char_class(chars[col]) == start_class {
+            while col < chars.len() && self.char_class(chars[col], big) == start_class {
                 col += 1;
             }
         }
@@ -172,8 +167,16 @@ impl Editor {
     }
 
     /// Move cursor to the start of the previous word (vim `b`).
-    #[allow(dead_code)]
     pub fn move_word_backward(&mut self) {
+        self.word_backward(false);
+    }
+
+    /// Move cursor to the start of the previous WORD (vim `B`).
+    pub fn move_big_word_backward(&mut self) {
+        self.word_backward(true);
+    }
+
+    fn word_backward(&mut self, big: bool) {
         let mut row = self.cursor_row;
         let mut col = self.cursor_col;
 
@@ -216,8 +219,8 @@ impl Editor {
                         col -= 1;
                     }
                     // Now back up to the start of this word.
-                    let cls = Self::char_class(pchars[col]);
-                    while col > 0 && Self::char_class(pchars[col - 1]) == cls {
+                    let cls = self.char_class(pchars[col], big);
+                    while col > 0 && self.char_class(pchars[col - 1], big) == cls {
                         col -= 1;
                     }
                 }
@@ -228,8 +231,8 @@ impl Editor {
         }
 
         // Now we're on a word or punctuation char — back up to the start of this run.
-        let cls = Self::char_class(chars[col]);
-        while col > 0 && Self::char_class(chars[col - 1]) == cls {
+        let cls = self.char_class(chars[col], big);
+        while col > 0 && self.char_class(chars[col - 1], big) == cls {
             col -= 1;
         }
 
@@ -238,8 +241,16 @@ impl Editor {
     }
 
     /// Move cursor to the end of the current/next word (vim `e`).
-    #[allow(dead_code)]
     pub fn move_word_end(&mut self) {
+        self.word_end(false);
+    }
+
+    /// Move cursor to the end of the current/next WORD (vim `E`).
+    pub fn move_big_word_end(&mut self) {
+        self.word_end(true);
+    }
+
+    fn word_end(&mut self, big: bool) {
         let max_row = self.max_row();
         let mut row = self.cursor_row;
         let mut col = self.cursor_col;
@@ -268,8 +279,8 @@ impl Editor {
 
             if col < chars.len() {
                 // Find the end of this word.
-                let cls = Self::char_class(chars[col]);
-                while col + 1 < chars.len() && Self::char_class(chars[col + 1]) == cls {
+                let cls = self.char_class(chars[col], big);
+                while col + 1 < chars.len() && self.char_class(chars[col + 1], big) == cls {
                     col += 1;
                 }
                 self.cursor_row = row;
@@ -299,8 +310,8 @@ impl Editor {
 
             if col < nchars.len() {
                 // Find the end of this word.
-                let cls = Self::char_class(nchars[col]);
-                while col + 1 < nchars.len() && Self::char_class(nchars[col + 1]) == cls {
+                let cls = self.char_class(nchars[col], big);
+                while col + 1 < nchars.len() && self.char_class(nchars[col + 1], big) == cls {
                     col += 1;
                 }
             } else {
@@ -312,6 +323,55 @@ impl Editor {
         self.cursor_col = col;
     }
 
+    /// Move cursor to the end of the previous word (vim `ge`).
+    pub fn move_word_end_backward(&mut self) {
+        self.word_end_backward(false);
+    }
+
+    /// Move cursor to the end of the previous WORD (vim `gE`).
+    pub fn move_big_word_end_backward(&mut self) {
+        self.word_end_backward(true);
+    }
+
+    fn word_end_backward(&mut self, big: bool) {
+        let class_at = |ed: &Self, row: usize, col: usize| {
+            ed.buffer
+                .line(row)
+                .and_then(|l| l.chars().nth(col))
+                .map_or(CharClass::Blank, |c| ed.char_class(c, big))
+        };
+
+        let (mut row, mut col) = (self.cursor_row, self.cursor_col);
+        let start_class = class_at(self, row, col);
+        // Once we've stepped off the word under the cursor, the next non-blank
+        // character we meet is the end of the previous word.
+        let mut left_word = start_class == CharClass::Blank;
+        loop {
+            if col > 0 {
+                col -= 1;
+            } else if row > 0 {
+                row -= 1;
+                col = self.buffer.line_len(row).saturating_sub(1);
+                left_word = true;
+                // Like vim, an empty line counts as a word of its own.
+                if self.buffer.line_len(row) == 0 {
+                    break;
+                }
+            } else {
+                return;
+            }
+
+            let class = class_at(self, row, col);
+            if class == CharClass::Blank {
+                left_word = true;
+            } else if left_word || class != start_class {
+                break;
+            }
+        }
+        self.cursor_row = row;
+        self.cursor_col = col;
+    }
+
     // ── Line position motions ─────────────────────────────────────────
 
     /// Move cursor to column 0 (vim `0`).
@@ -349,10 +409,64 @@ impl Editor {
             self.cursor_col = 0;
         }
     }
+
+    // ── Character find motions ────────────────────────────────────────
+
+    /// Move to the [count]th occurrence of a character on the current line
+    /// (vim `f`, `F`, `t`, `T`), remembering it for `;` and `,`. Returns
+    /// false and leaves the cursor alone if there aren't that many.
+    pub fn find_char(&mut self, find: FindChar, count: usize) -> bool {
+        self.last_find = Some(find);
+        self.search_line_for_char(find, count, false)
+    }
+
+    /// Repeat the last character find (`;`), or reverse it (`,`).
+    pub fn repeat_find(&mut self, reverse: bool, count: usize) -> bool {
+        let Some(mut find) = self.last_find else {
+            return false;
+        };
+        if reverse {
+            find.kind = find.kind.reversed();
+        }
+        self.search_line_for_char(find, count, true)
+    }
+
+    fn search_line_for_char(&mut self, find: FindChar, count: usize, repeat: bool) -> bool {
+        let Some(line) = self.buffer.line(self.cursor_row) else {
+            return false;
+        };
+        let chars: Vec<char> = line.chars().collect();
+        let col = self.cursor_col;
+        // A repeated `t`/`T` that starts right next to its target would find
+        // the same spot again, so step over the adjacent match.
+        let skip = 1 + usize::from(repeat && find.kind.is_till());
+
+        let target = if find.kind.is_forward() {
+            (col + skip..chars.len())
+                .filter(|&i| chars[i] == find.ch)
+                .nth(count.saturating_sub(1))
+                .map(|i| if find.kind.is_till() { i - 1 } else { i })
+        } else {
+            (0..col.saturating_sub(skip - 1))
+                .rev()
+                .filter(|&i| chars[i] == find.ch)
+                .nth(count.saturating_sub(1))
+                .map(|i| if find.kind.is_till() { i + 1 } else { i })
+        };
+
+        match target {
+            Some(target) => {
+                self.cursor_col = target;
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::motion::{FindChar, FindKind};
     use super::super::test_editor;
 
     #[test]
@@ -496,6 +610,64 @@ mod tests {
         assert_eq!(ed.cursor_row, 0);
     }
 
+    #[test]
+    fn move_big_word_forward_skips_punctuation() {
+        let mut ed = test_editor("foo.bar baz\n");
+        ed.move_big_word_forward();
+        assert_eq!(ed.cursor_col, 8);
+    }
+
+    #[test]
+    fn move_big_word_backward_and_end() {
+        let mut ed = test_editor("a.b c-d\n");
+        ed.cursor_col = 6;
+        ed.move_big_word_backward();
+        assert_eq!(ed.cursor_col, 4);
+        ed.cursor_col = 0;
+        ed.move_big_word_end();
+        assert_eq!(ed.cursor_col, 2);
+    }
+
+    #[test]
+    fn move_word_end_backward_basic() {
+        let mut ed = test_editor("foo bar.baz\n");
+        ed.cursor_col = 9;
+        ed.move_word_end_backward();
+        assert_eq!(ed.cursor_col, 7);
+        ed.move_word_end_backward();
+        assert_eq!(ed.cursor_col, 6);
+        ed.move_word_end_backward();
+        assert_eq!(ed.cursor_col, 2);
+    }
+
+    #[test]
+    fn move_big_word_end_backward_crosses_lines() {
+        let mut ed = test_editor("foo.bar\n  baz\n");
+        ed.cursor_row = 1;
+        ed.cursor_col = 3;
+        ed.move_big_word_end_backward();
+        assert_eq!((ed.cursor_row, ed.cursor_col), (0, 6));
+    }
+
+    #[test]
+    fn move_word_end_backward_stops_on_empty_line() {
+        let mut ed = test_editor("foo\n\nbar\n");
+        ed.cursor_row = 2;
+        ed.move_word_end_backward();
+        assert_eq!((ed.cursor_row, ed.cursor_col), (1, 0));
+    }
+
+    #[test]
+    fn word_motions_follow_iskeyword() {
+        let mut ed = test_editor("foo-bar baz\n");
+        ed.move_word_forward();
+        assert_eq!(ed.cursor_col, 3);
+        ed.buffer.set_iskeyword("@,48-57,_,-").unwrap();
+        ed.cursor_col = 0;
+        ed.move_word_forward();
+        assert_eq!(ed.cursor_col, 8);
+    }
+
     // ── Line position motion tests ────────────────────────────────────
 
     #[test]
@@ -514,6 +686,89 @@ mod tests {
         assert_eq!(ed.cursor_col, 4);
     }
 
+    #[test]
+    fn goto_line_clamps() {
+        let mut ed = test_editor("a\nb\nc\n");
+        ed.goto_line(2);
+        assert_eq!(ed.cursor_row, 1);
+        ed.goto_line(99);
+        assert_eq!(ed.cursor_row, 2);
+        ed.goto_line(0);
+        assert_eq!(ed.cursor_row, 0);
+    }
+
+    // ── Character find tests ──────────────────────────────────────────
+
+    fn find(kind: FindKind, ch: char) -> FindChar {
+        FindChar { kind, ch }
+    }
+
+    #[test]
+    fn find_char_forward() {
+        let mut ed = test_editor("foo(bar, baz)\n");
+        assert!(ed.find_char(find(FindKind::Forward, ','), 1));
+        assert_eq!(ed.cursor_col, 7);
+    }
+
+    #[test]
+    fn find_char_till_forward_stops_before() {
+        let mut ed = test_editor("foo(bar, baz)\n");
+        assert!(ed.find_char(find(FindKind::TillForward, ')'), 1));
+        assert_eq!(ed.cursor_col, 11);
+    }
+
+    #[test]
+    fn find_char_backward_and_till_backward() {
+        let mut ed = test_editor("foo(bar, baz)\n");
+        ed.cursor_col = 12;
+        assert!(ed.find_char(find(FindKind::Backward, '('), 1));
+        assert_eq!(ed.cursor_col, 3);
+        ed.cursor_col = 12;
+        assert!(ed.find_char(find(FindKind::TillBackward, '('), 1));
+        assert_eq!(ed.cursor_col, 4);
+    }
+
+    #[test]
+    fn find_char_with_count() {
+        let mut ed = test_editor("a.b.c.d\n");
+        assert!(ed.find_char(find(FindKind::Forward, '.'), 3));
+        assert_eq!(ed.cursor_col, 5);
+    }
+
+    #[test]
+    fn find_char_missing_leaves_cursor() {
+        let mut ed = test_editor("abc\n");
+        ed.cursor_col = 1;
+        assert!(!ed.find_char(find(FindKind::Forward, 'z'), 1));
+        assert_eq!(ed.cursor_col, 1);
+        assert!(!ed.find_char(find(FindKind::Forward, 'a'), 1));
+    }
+
+    #[test]
+    fn repeat_find_forward_and_reverse() {
+        let mut ed = test_editor("a.b.c.d\n");
+        ed.find_char(find(FindKind::Forward, '.'), 1);
+        assert!(ed.repeat_find(false, 1));
+        assert_eq!(ed.cursor_col, 3);
+        assert!(ed.repeat_find(true, 1));
+        assert_eq!(ed.cursor_col, 1);
+    }
+
+    #[test]
+    fn repeat_till_skips_adjacent_match() {
+        let mut ed = test_editor("a.b.c.d\n");
+        ed.find_char(find(FindKind::TillForward, '.'), 1);
+        assert_eq!(ed.cursor_col, 0);
+        assert!(ed.repeat_find(false, 1));
+        assert_eq!(ed.cursor_col, 2);
+    }
+
+    #[test]
+    fn repeat_find_without_previous_fails() {
+        let mut ed = test_editor("abc\n");
+        assert!(!ed.repeat_find(false, 1));
+    }
+
     #[test]
     fn test_goto_first_non_blank() {
         let mut ed = test_editor("  hello\n");
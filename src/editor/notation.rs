@@ -0,0 +1,156 @@
+//! Vim-style key notation (`<Esc>`, `<C-w>`, `<lt>`), so recorded keys can
+//! be kept in a register as editable text and read back.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Named keys, as written between `<` and `>`.
+const NAMED_KEYS: &[(&str, KeyCode)] = &[
+    ("Esc", KeyCode::Esc),
+    ("CR", KeyCode::Enter),
+    ("BS", KeyCode::Backspace),
+    ("Tab", KeyCode::Tab),
+    ("Del", KeyCode::Delete),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Insert", KeyCode::Insert),
+    ("Space", KeyCode::Char(' ')),
+    ("lt", KeyCode::Char('<')),
+];
+
+/// Write `keys` as text. Plain characters stand for themselves.
+pub fn encode(keys: &[KeyEvent]) -> String {
+    keys.iter().map(|&key| encode_key(key)).collect()
+}
+
+fn encode_key(key: KeyEvent) -> String {
+    let mut prefix = String::new();
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        prefix.push_str("C-");
+    }
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        prefix.push_str("M-");
+    }
+    let name = match key.code {
+        KeyCode::Char('<') => "lt".to_string(),
+        KeyCode::Char(c) if prefix.is_empty() => return c.to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{n}"),
+        code => match NAMED_KEYS.iter().find(|&&(_, named)| named == code) {
+            Some((name, _)) => name.to_string(),
+            None => return String::new(),
+        },
+    };
+    format!("<{prefix}{name}>")
+}
+
+/// Read keys back from text. Anything in `<...>` that isn't a known key is
+/// taken literally, like vim does.
+pub fn parse(text: &str) -> Vec<KeyEvent> {
+    let mut keys = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c == '<'
+            && let Some(end) = rest.find('>')
+            && let Some(key) = parse_special(&rest[1..end])
+        {
+            keys.push(key);
+            rest = &rest[end + 1..];
+            continue;
+        }
+        let code = match c {
+            '\n' => KeyCode::Enter,
+            '\x1b' => KeyCode::Esc,
+            c => KeyCode::Char(c),
+        };
+        keys.push(KeyEvent::new(code, KeyModifiers::NONE));
+        rest = &rest[c.len_utf8()..];
+    }
+    keys
+}
+
+/// Parse the inside of `<...>`, e.g. `C-w`, `M-x`, `F5`, `Esc`.
+fn parse_special(name: &str) -> Option<KeyEvent> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut name = name;
+    loop {
+        let lower = name.to_ascii_lowercase();
+        if lower.starts_with("c-") && name.len() > 2 {
+            modifiers |= KeyModifiers::CONTROL;
+        } else if (lower.starts_with("m-") || lower.starts_with("a-")) && name.len() > 2 {
+            modifiers |= KeyModifiers::ALT;
+        } else {
+            break;
+        }
+        name = &name[2..];
+    }
+
+    let mut chars = name.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) if !modifiers.is_empty() => KeyCode::Char(c.to_ascii_lowercase()),
+        _ => {
+            if let Some(n) = name.strip_prefix(['F', 'f'])
+                && let Ok(n) = n.parse::<u8>()
+            {
+                KeyCode::F(n)
+            } else {
+                NAMED_KEYS
+                    .iter()
+                    .find(|(named, _)| named.eq_ignore_ascii_case(name))
+                    .map(|&(_, code)| code)?
+            }
+        }
+    };
+    Some(KeyEvent::new(code, modifiers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn encodes_plain_and_special_keys() {
+        let keys = [
+            key(KeyCode::Char('d')),
+            key(KeyCode::Char('<')),
+            key(KeyCode::Esc),
+            KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL),
+            key(KeyCode::Enter),
+        ];
+        assert_eq!(encode(&keys), "d<lt><Esc><C-w><CR>");
+    }
+
+    #[test]
+    fn round_trips() {
+        let text = "ciwfoo<Esc>j0<C-d><F3><Space>x";
+        assert_eq!(encode(&parse(text)), "ciwfoo<Esc>j0<C-d><F3> x");
+    }
+
+    #[test]
+    fn unknown_names_are_literal() {
+        let keys = parse("<foo>");
+        assert_eq!(keys.len(), 5);
+        assert_eq!(keys[0], key(KeyCode::Char('<')));
+    }
+
+    #[test]
+    fn names_are_case_insensitive() {
+        assert_eq!(
+            parse("<esc><cr>"),
+            vec![key(KeyCode::Esc), key(KeyCode::Enter)]
+        );
+        assert_eq!(
+            parse("<c-W>"),
+            vec![KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL)]
+        );
+    }
+}
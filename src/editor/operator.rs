@@ -0,0 +1,453 @@
+use super::Editor;
+use super::motion::{Motion, MotionKind};
+use super::text_object::{TextObject, TextRange};
+use crate::buffer::CharClass;
+use crate::mode::Mode;
+
+/// An operator waiting for (or applied to) a motion, e.g. the `d` in `dw`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Delete,
+    Change,
+    Yank,
+    /// `gu`
+    Lowercase,
+    /// `gU`
+    Uppercase,
+    /// `g~`
+    ToggleCase,
+    /// `>`
+    ShiftRight,
+    /// `<`
+    ShiftLeft,
+    /// `=`
+    Reindent,
+}
+
+impl Operator {
+    /// The case operators change text in place rather than removing it,
+    /// and leave the registers alone.
+    pub fn is_case(self) -> bool {
+        matches!(
+            self,
+            Operator::Lowercase | Operator::Uppercase | Operator::ToggleCase
+        )
+    }
+
+    /// The indent operators always act on whole lines, whatever they're
+    /// given.
+    pub fn is_linewise(self) -> bool {
+        matches!(
+            self,
+            Operator::ShiftRight | Operator::ShiftLeft | Operator::Reindent
+        )
+    }
+}
+
+impl Editor {
+    /// Apply `op` to the text covered by `motion` from the cursor (`d3w`,
+    /// `ct)`). Does nothing and returns false if the motion fails.
+    pub fn apply_operator(&mut self, op: Operator, motion: Motion, count: Option<usize>) -> bool {
+        let start = (self.cursor_row, self.cursor_col);
+        let mut kind = motion.kind(self);
+
+        // `cw` on a word behaves like `ce`: the trailing blanks are kept.
+        let on_blank = self
+            .buffer
+            .line(start.0)
+            .and_then(|l| l.chars().nth(start.1))
+            .is_none_or(char::is_whitespace);
+        let word_end = match motion {
+            Motion::WordForward => Some(Motion::WordEnd),
+            Motion::BigWordForward => Some(Motion::BigWordEnd),
+            _ => None,
+        };
+        let end = if let Some(word_end) = word_end
+            && op == Operator::Change
+            && !on_blank
+        {
+            kind = MotionKind::Inclusive;
+            // Already on the last character of a word, that word is the
+            // first one changed, not the next.
+            let big = motion == Motion::BigWordForward;
+            let count = count.unwrap_or(1);
+            let count = if self.at_word_end(start, big) {
+                count - 1
+            } else {
+                count
+            };
+            if count > 0 && !self.apply_motion(word_end, Some(count)) {
+                return false;
+            }
+            (self.cursor_row, self.cursor_col)
+        } else if word_end.is_some() {
+            let big = motion == Motion::BigWordForward;
+            self.word_forward_operator_end(count.unwrap_or(1), big)
+        } else {
+            if !self.apply_motion(motion, count) {
+                return false;
+            }
+            (self.cursor_row, self.cursor_col)
+        };
+
+        let (from, mut to) = if end < start {
+            (end, start)
+        } else {
+            (start, end)
+        };
+
+        // An exclusive motion that ends in column 0 of a later line stops at
+        // the end of the line before it instead, and becomes linewise when it
+        // also started at or before the first non-blank (`:help exclusive`).
+        if kind == MotionKind::Exclusive && to.1 == 0 && to.0 > from.0 {
+            if from.1 <= self.first_non_blank_col(from.0) {
+                kind = MotionKind::Linewise;
+                to = (to.0 - 1, 0);
+            } else {
+                to = (to.0 - 1, self.buffer.line_len(to.0 - 1));
+            }
+        }
+
+        if op.is_linewise() {
+            kind = MotionKind::Linewise;
+        }
+        match kind {
+            MotionKind::Linewise => self.operate_lines(op, from.0, to.0),
+            MotionKind::Inclusive => {
+                let to_exclusive = (to.0, (to.1 + 1).min(self.buffer.line_len(to.0)));
+                self.operate_chars(op, from, to_exclusive);
+            }
+            MotionKind::Exclusive => self.operate_chars(op, from, to),
+        }
+        true
+    }
+
+    /// Apply `op` to [count] of a text object around the cursor (`ci"`,
+    /// `da(`). Does nothing and returns false if there is no such object.
+    pub fn apply_operator_object(
+        &mut self,
+        op: Operator,
+        object: TextObject,
+        count: usize,
+    ) -> bool {
+        let Some(range) = self.text_object_range(object, count) else {
+            return false;
+        };
+        self.operate_range(op, range);
+        true
+    }
+
+    /// Apply `op` to a text object or visual selection.
+    pub(super) fn operate_range(&mut self, op: Operator, range: TextRange) {
+        if range.linewise {
+            self.operate_lines(op, range.start.0, range.end.0);
+        } else {
+            self.operate_chars(op, range.start, range.end);
+        }
+    }
+
+    /// Apply `op` to [count] whole lines starting at the cursor (`dd`, `cc`, `yy`).
+    pub fn apply_operator_lines(&mut self, op: Operator, count: usize) {
+        let last = (self.cursor_row + count.max(1) - 1).min(self.max_row());
+        self.operate_lines(op, self.cursor_row, last);
+    }
+
+    /// Operate on the characters from `from` up to (not including) `to`.
+    fn operate_chars(&mut self, op: Operator, from: (usize, usize), to: (usize, usize)) {
+        if op.is_linewise() {
+            let last = if to.1 == 0 && to.0 > from.0 {
+                to.0 - 1
+            } else {
+                to.0
+            };
+            self.operate_lines(op, from.0, last);
+            return;
+        }
+        let text = self.buffer.text_range(from, to);
+        let last = self
+            .buffer
+            .position(self.buffer.char_index(to.0, to.1).saturating_sub(1));
+        if op.is_case() {
+            self.change_case(op, from, to);
+            self.set_change_marks(from, last);
+            (self.cursor_row, self.cursor_col) = from;
+            self.clamp_cursor_col();
+            return;
+        }
+        self.store_register(text, false, op == Operator::Yank);
+        self.set_change_marks(from, if op == Operator::Yank { last } else { from });
+        if op != Operator::Yank {
+            self.buffer.delete_range(from, to);
+        }
+        self.cursor_row = from.0;
+        self.cursor_col = from.1;
+        match op {
+            Operator::Change => self.enter_insert_mode(),
+            _ => self.clamp_cursor_col(),
+        }
+    }
+
+    /// Operate on whole lines `first..=last`.
+    fn operate_lines(&mut self, op: Operator, first: usize, last: usize) {
+        if op.is_linewise() {
+            self.indent_lines(op, first, last);
+            return;
+        }
+        if op.is_case() {
+            let last_len = self.buffer.line_len(last);
+            self.change_case(op, (first, 0), (last, last_len));
+            self.set_change_marks((first, 0), (last, last_len.saturating_sub(1)));
+            self.cursor_row = first;
+            self.clamp_cursor_col();
+            return;
+        }
+        let mut text = self.buffer.text_range((first, 0), (last + 1, 0));
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        self.store_register(text, true, op == Operator::Yank);
+        let last_line_end = (last, self.buffer.line_len(last).saturating_sub(1));
+        let end = if op == Operator::Yank {
+            last_line_end
+        } else {
+            (first, 0)
+        };
+        self.set_change_marks((first, 0), end);
+        match op {
+            Operator::Delete => {
+                self.cursor_row = first;
+                for _ in first..=last {
+                    self.delete_line();
+                }
+                self.goto_first_non_blank();
+            }
+            Operator::Change => {
+                // Keep a single empty line to type into.
+                let end = (last, self.buffer.line_len(last));
+                self.buffer.delete_range((first, 0), end);
+                self.cursor_row = first;
+                self.cursor_col = 0;
+                self.mode = Mode::Insert;
+            }
+            _ => {
+                self.cursor_row = first;
+                self.clamp_cursor_col();
+            }
+        }
+    }
+
+    /// Whether `pos` is on the last character of a word.
+    fn at_word_end(&self, (row, col): (usize, usize), big: bool) -> bool {
+        let chars: Vec<char> = self.buffer.line(row).unwrap_or_default().chars().collect();
+        let Some(&c) = chars.get(col) else {
+            return false;
+        };
+        chars
+            .get(col + 1)
+            .is_none_or(|&next| self.char_class(next, big) != self.char_class(c, big))
+    }
+
+    /// Set the `[` and `]` marks around text just changed or yanked.
+    pub(super) fn set_change_marks(&mut self, first: (usize, usize), last: (usize, usize)) {
+        self.set_mark_at('[', first);
+        self.set_mark_at(']', last);
+    }
+
+    /// Where `w` ends when used as an operator target. Unlike the motion, the
+    /// last word moved over never carries the operation onto the next line:
+    /// `dw` on the last word of a line deletes to the end of that line.
+    fn word_forward_operator_end(&self, count: usize, big: bool) -> (usize, usize) {
+        let (mut row, mut col) = (self.cursor_row, self.cursor_col);
+        for i in 0..count {
+            let chars: Vec<char> = self.buffer.line(row).unwrap_or_default().chars().collect();
+            if col < chars.len() {
+                let class = self.char_class(chars[col], big);
+                if class != CharClass::Blank {
+                    while col < chars.len() && self.char_class(chars[col], big) == class {
+                        col += 1;
+                    }
+                }
+                while col < chars.len() && chars[col].is_whitespace() {
+                    col += 1;
+                }
+            }
+            let last = i + 1 == count;
+            if col >= chars.len() && !last && row < self.max_row() {
+                row += 1;
+                col = self.first_non_blank_col(row);
+            }
+        }
+        (row, col)
+    }
+
+    /// Column of the first non-whitespace character on `row` (0 if none).
+    pub(crate) fn first_non_blank_col(&self, row: usize) -> usize {
+        self.buffer
+            .line(row)
+            .and_then(|l| l.chars().position(|c| !c.is_whitespace()))
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::motion::{FindChar, FindKind, Motion};
+    use super::super::{test_editor, type_keys};
+    use super::Operator;
+    use crate::mode::Mode;
+
+    fn find(kind: FindKind, ch: char) -> Motion {
+        Motion::FindChar(FindChar { kind, ch })
+    }
+
+    #[test]
+    fn delete_till_char() {
+        let mut ed = test_editor("call(a, b)\n");
+        ed.cursor_col = 5;
+        ed.apply_operator(Operator::Delete, find(FindKind::TillForward, ')'), None);
+        assert_eq!(ed.buffer.line(0).unwrap(), "call()");
+        assert_eq!(ed.cursor_col, 5);
+    }
+
+    #[test]
+    fn delete_find_char_inclusive() {
+        let mut ed = test_editor("call(a, b)\n");
+        ed.apply_operator(Operator::Delete, find(FindKind::Forward, '('), None);
+        assert_eq!(ed.buffer.line(0).unwrap(), "a, b)");
+    }
+
+    #[test]
+    fn delete_find_backward_is_exclusive() {
+        let mut ed = test_editor("call(a, b)\n");
+        ed.cursor_col = 9;
+        ed.apply_operator(Operator::Delete, find(FindKind::Backward, '('), None);
+        assert_eq!(ed.buffer.line(0).unwrap(), "call)");
+    }
+
+    #[test]
+    fn change_find_char_enters_insert() {
+        let mut ed = test_editor("x = a, b\n");
+        ed.cursor_col = 4;
+        ed.apply_operator(Operator::Change, find(FindKind::Forward, ','), None);
+        assert_eq!(ed.buffer.line(0).unwrap(), "x =  b");
+        assert_eq!(ed.mode, Mode::Insert);
+        assert_eq!(ed.cursor_col, 4);
+    }
+
+    #[test]
+    fn failed_find_leaves_text_alone() {
+        let mut ed = test_editor("abc\n");
+        ed.apply_operator(Operator::Delete, find(FindKind::Forward, 'z'), None);
+        assert_eq!(ed.buffer.line(0).unwrap(), "abc");
+    }
+
+    #[test]
+    fn delete_with_count() {
+        let mut ed = test_editor("a.b.c.d\n");
+        ed.apply_operator(Operator::Delete, find(FindKind::Forward, '.'), Some(2));
+        assert_eq!(ed.buffer.line(0).unwrap(), "c.d");
+    }
+
+    #[test]
+    fn delete_word_across_lines_with_count() {
+        let mut ed = test_editor("foo\nbar baz\n");
+        ed.apply_operator(Operator::Delete, Motion::WordForward, Some(2));
+        assert_eq!(ed.buffer.line(0).unwrap(), "baz");
+    }
+
+    #[test]
+    fn change_word_keeps_trailing_space() {
+        let mut ed = test_editor("foo bar\n");
+        ed.apply_operator(Operator::Change, Motion::WordForward, None);
+        assert_eq!(ed.buffer.line(0).unwrap(), " bar");
+        assert_eq!(ed.mode, Mode::Insert);
+    }
+
+    #[test]
+    fn change_word_from_a_word_end_stays_in_the_word() {
+        let mut ed = test_editor("a bc\n");
+        type_keys(&mut ed, "cwX\x1b");
+        assert_eq!(ed.buffer.line(0).unwrap(), "X bc");
+        let mut ed = test_editor("foo bar baz\n");
+        ed.cursor_col = 2;
+        type_keys(&mut ed, "cwX\x1b");
+        assert_eq!(ed.buffer.line(0).unwrap(), "foX bar baz");
+        type_keys(&mut ed, "2cwY\x1b");
+        assert_eq!(ed.buffer.line(0).unwrap(), "foY baz");
+    }
+
+    #[test]
+    fn delete_big_word() {
+        let mut ed = test_editor("foo.bar baz\n");
+        ed.apply_operator(Operator::Delete, Motion::BigWordForward, None);
+        assert_eq!(ed.buffer.line(0).unwrap(), "baz");
+    }
+
+    #[test]
+    fn change_big_word_keeps_trailing_space() {
+        let mut ed = test_editor("foo.bar baz\n");
+        ed.apply_operator(Operator::Change, Motion::BigWordForward, None);
+        assert_eq!(ed.buffer.line(0).unwrap(), " baz");
+    }
+
+    #[test]
+    fn delete_paragraph_from_line_start_is_linewise() {
+        let mut ed = test_editor("a\nb\n\nc\n");
+        ed.apply_operator(Operator::Delete, Motion::ParagraphForward, None);
+        assert_eq!(ed.buffer.line(0).unwrap(), "");
+        assert_eq!(ed.buffer.line(1).unwrap(), "c");
+    }
+
+    #[test]
+    fn delete_paragraph_from_mid_line_keeps_line_start() {
+        let mut ed = test_editor("abc\nd\n\ne\n");
+        ed.cursor_col = 1;
+        ed.apply_operator(Operator::Delete, Motion::ParagraphForward, None);
+        assert_eq!(ed.buffer.line(0).unwrap(), "a");
+        assert_eq!(ed.buffer.line(1).unwrap(), "");
+    }
+
+    #[test]
+    fn delete_sentence() {
+        let mut ed = test_editor("One two. Three.\n");
+        ed.apply_operator(Operator::Delete, Motion::SentenceForward, None);
+        assert_eq!(ed.buffer.line(0).unwrap(), "Three.");
+    }
+
+    #[test]
+    fn delete_down_is_linewise() {
+        let mut ed = test_editor("a\nb\nc\n");
+        ed.apply_operator(Operator::Delete, Motion::Down, None);
+        assert_eq!(ed.buffer.line(0).unwrap(), "c");
+    }
+
+    #[test]
+    fn delete_lines_with_count() {
+        let mut ed = test_editor("a\nb\nc\nd\n");
+        ed.cursor_row = 1;
+        ed.apply_operator_lines(Operator::Delete, 2);
+        assert_eq!(ed.buffer.line(0).unwrap(), "a");
+        assert_eq!(ed.buffer.line(1).unwrap(), "d");
+        assert_eq!(ed.cursor_row, 1);
+    }
+
+    #[test]
+    fn yank_lines_leaves_text_alone() {
+        let mut ed = test_editor("a\nb\nc\n");
+        ed.cursor_row = 1;
+        ed.apply_operator(Operator::Yank, Motion::Up, None);
+        assert_eq!(ed.buffer.line(0).unwrap(), "a");
+        assert_eq!(ed.cursor_row, 0);
+        assert_eq!(ed.registers.get('"').unwrap().text, "a\nb\n");
+        assert!(!ed.buffer.is_modified());
+    }
+
+    #[test]
+    fn change_lines_leaves_empty_line() {
+        let mut ed = test_editor("a\nb\nc\n");
+        ed.apply_operator_lines(Operator::Change, 2);
+        assert_eq!(ed.buffer.line(0).unwrap(), "");
+        assert_eq!(ed.buffer.line(1).unwrap(), "c");
+        assert_eq!(ed.mode, Mode::Insert);
+    }
+}
@@ -0,0 +1,317 @@
+//! `:set` and `:setlocal`, and the settings from the config file.
+
+use super::Editor;
+use crate::config::{Config, Settings};
+use crate::error::DvimError;
+use crate::options::{self, GlobalOptions, Kind, OptionDef, Scope, Value, WindowOptions};
+
+impl Editor {
+    /// The value `def` has for the current buffer and window.
+    pub fn option(&self, def: &OptionDef) -> Value {
+        let value = match def.scope {
+            Scope::Global => self.global_options.get(def.name),
+            Scope::Window => self.window_options.get(def.name),
+            Scope::Buffer => self.buffer.option(def.name),
+        };
+        value.expect("every option in the registry has a value")
+    }
+
+    /// The value `def` starts with, which `:set opt&` goes back to.
+    fn default_option(&self, def: &OptionDef) -> Value {
+        let value = match def.scope {
+            Scope::Global => GlobalOptions::default().get(def.name),
+            Scope::Window => WindowOptions::default().get(def.name),
+            Scope::Buffer => self.buffer.default_option(def.name),
+        };
+        value.expect("every option in the registry has a default")
+    }
+
+    pub fn set_option(&mut self, def: &OptionDef, value: &Value) -> Result<(), DvimError> {
+        def.check(value)?;
+        match def.scope {
+            Scope::Global => self.global_options.set(def.name, value),
+            Scope::Window => self.window_options.set(def.name, value),
+            Scope::Buffer => {
+                self.buffer.set_option(def.name, value)?;
+                if def.name == "filetype" {
+                    // The new filetype's defaults are in; the config's
+                    // settings go over them as they did at startup.
+                    if let Some(e) = self.apply_buffer_config().into_iter().next() {
+                        return Err(e);
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Run `:set` (or `:setlocal`) with `args`. With one buffer and one
+    /// window there's nowhere for a local option's global value to go, so
+    /// the two are the same. As in vim, `:setlocal` on a global option
+    /// sets its one value.
+    pub(super) fn set_command(&mut self, args: &str) -> Result<(), DvimError> {
+        let args = split_args(args);
+        if args.is_empty() {
+            self.status_message = Some(self.options_listing(false));
+            return Ok(());
+        }
+        let mut shown = String::new();
+        for arg in args {
+            if arg == "all" {
+                self.status_message = Some(self.options_listing(true));
+                return Ok(());
+            }
+            if let Some(show) = self.set_arg(&arg)? {
+                shown.push_str(&format!("  {show}"));
+            }
+        }
+        if !shown.is_empty() {
+            self.status_message = Some(shown);
+        }
+        Ok(())
+    }
+
+    /// Carry out one `:set` argument: `opt`, `noopt`, `invopt`, `opt!`,
+    /// `opt?`, `opt&` or `opt=value`. Returns the text to show for queries.
+    fn set_arg(&mut self, arg: &str) -> Result<Option<String>, DvimError> {
+        let end = arg
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(arg.len());
+        let (name, rest) = arg.split_at(end);
+        let (def, prefix) = match options::find(name) {
+            Some(def) => (def, None),
+            None => {
+                let (prefix, unprefixed) = ["no", "inv"]
+                    .into_iter()
+                    .find_map(|p| name.strip_prefix(p).map(|n| (p, n)))
+                    .ok_or_else(|| DvimError::UnknownOption(name.to_string()))?;
+                let def = options::find(unprefixed)
+                    .ok_or_else(|| DvimError::UnknownOption(name.to_string()))?;
+                (def, Some(prefix))
+            }
+        };
+        let invalid = || DvimError::InvalidArgument(arg.to_string());
+        let is_bool = def.kind == Kind::Bool;
+        if prefix.is_some() && (!is_bool || !rest.is_empty()) {
+            return Err(invalid());
+        }
+
+        let current = self.option(def);
+        let toggled = Value::Bool(current != Value::Bool(true));
+        let value = match (prefix, rest) {
+            (Some("no"), _) => Value::Bool(false),
+            (Some(_), _) => toggled,
+            (None, "?") => return Ok(Some(def.show(&current))),
+            (None, "") if is_bool => Value::Bool(true),
+            (None, "") => return Ok(Some(def.show(&current))),
+            (None, "!") if is_bool => toggled,
+            (None, "&") => self.default_option(def),
+            (None, rest) => match rest.strip_prefix(['=', ':']) {
+                Some(text) => def.parse(text)?,
+                None => return Err(invalid()),
+            },
+        };
+        self.set_option(def, &value)?;
+        Ok(None)
+    }
+
+    /// `:set` lists the options that differ from their defaults, `:set all`
+    /// every option.
+    fn options_listing(&self, all: bool) -> String {
+        let mut listing = String::from("--- Options ---");
+        for def in options::OPTIONS {
+            let value = self.option(def);
+            if all || value != self.default_option(def) {
+                listing.push_str(&format!("\n  {}", def.show(&value)));
+            }
+        }
+        listing
+    }
+
+    /// Start using `config`: set its options, then those for the buffer's
+    /// filetype, then add its mappings and switch to its theme. Settings
+    /// that fail are skipped and reported in the status bar.
+    pub fn load_config(&mut self, mut config: Config) {
+        let mut errors = Vec::new();
+        let tables = std::iter::once(&mut config.options).chain(config.filetypes.values_mut());
+        for settings in tables {
+            settings.retain(|(name, _)| {
+                let known = options::find(name).is_some();
+                if !known {
+                    errors.push(DvimError::UnknownOption(name.clone()));
+                }
+                known
+            });
+        }
+        self.config = config;
+        let settings = self.config.options.clone();
+        errors.extend(self.apply_settings(&settings, |_| true));
+        errors.extend(self.apply_filetype_config());
+        let keymaps = self.config.keymaps.clone();
+        errors.extend(self.apply_keymap_config(&keymaps));
+        if let Some(name) = self.config.colorscheme.clone()
+            && let Err(e) = self.set_colorscheme(&name)
+        {
+            errors.push(e);
+        }
+        if !errors.is_empty() {
+            let messages: Vec<String> =
+                errors.iter().map(|e| format!("config.toml: {e}")).collect();
+            self.status_message = Some(messages.join("\n"));
+        }
+    }
+
+    /// Reapply the config's buffer-local settings, and those for the
+    /// buffer's filetype, after a change of filetype.
+    fn apply_buffer_config(&mut self) -> Vec<DvimError> {
+        let settings = self.config.options.clone();
+        let mut errors = self.apply_settings(&settings, |def| {
+            def.scope == Scope::Buffer && def.name != "filetype"
+        });
+        errors.extend(self.apply_filetype_config());
+        errors
+    }
+
+    /// Apply the config's settings for the buffer's filetype. A filetype
+    /// set there is ignored; it would send us round again.
+    fn apply_filetype_config(&mut self) -> Vec<DvimError> {
+        let filetype = self.buffer.filetype();
+        let Some(settings) = self.config.filetypes.get(filetype).cloned() else {
+            return Vec::new();
+        };
+        self.apply_settings(&settings, |def| def.name != "filetype")
+    }
+
+    /// Apply the `settings` whose option passes `filter`.
+    fn apply_settings(
+        &mut self,
+        settings: &Settings,
+        filter: impl Fn(&OptionDef) -> bool,
+    ) -> Vec<DvimError> {
+        let mut errors = Vec::new();
+        for (name, value) in settings {
+            let Some(def) = options::find(name).filter(|def| filter(def)) else {
+                continue;
+            };
+            if let Err(e) = self.set_option(def, value) {
+                errors.push(e);
+            }
+        }
+        errors
+    }
+}
+
+/// Split `:set` arguments on whitespace, except where escaped with a
+/// backslash.
+fn split_args(args: &str) -> Vec<String> {
+    let mut split = Vec::new();
+    let mut arg = String::new();
+    let mut chars = args.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars
+                .peek()
+                .is_some_and(|&n| n == '\\' || n.is_whitespace()) =>
+            {
+                arg.extend(chars.next());
+            }
+            c if c.is_whitespace() => {
+                if !arg.is_empty() {
+                    split.push(std::mem::take(&mut arg));
+                }
+            }
+            c => arg.push(c),
+        }
+    }
+    if !arg.is_empty() {
+        split.push(arg);
+    }
+    split
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Editor, editor_with_suffix, test_editor};
+    use super::split_args;
+    use crate::config::Config;
+    use std::path::Path;
+
+    fn run(ed: &mut Editor, cmd: &str) -> anyhow::Result<()> {
+        ed.command_buffer = cmd.to_string();
+        ed.execute_command()
+    }
+
+    #[test]
+    fn set_bool_options() {
+        let mut ed = test_editor("x\n");
+        run(&mut ed, "set noai ar").unwrap();
+        assert!(!ed.buffer.autoindent());
+        assert!(ed.global_options.autoread);
+        run(&mut ed, "set invautoread nu!").unwrap();
+        assert!(!ed.global_options.autoread);
+        assert!(!ed.window_options.number);
+        run(&mut ed, "setlocal number").unwrap();
+        assert!(ed.window_options.number);
+        run(&mut ed, "setlocal autoread").unwrap();
+        assert!(ed.global_options.autoread);
+    }
+
+    #[test]
+    fn set_queries_and_resets() {
+        let mut ed = test_editor("x\n");
+        run(&mut ed, "set sw=2 ts=4").unwrap();
+        run(&mut ed, "set sw ts? et?").unwrap();
+        assert_eq!(
+            ed.status_message.as_deref(),
+            Some("  shiftwidth=2  tabstop=4  expandtab")
+        );
+        run(&mut ed, "set sw&").unwrap();
+        assert_eq!(ed.buffer.shiftwidth(), 4);
+        run(&mut ed, "set").unwrap();
+        assert_eq!(
+            ed.status_message.as_deref(),
+            Some("--- Options ---\n  tabstop=4")
+        );
+    }
+
+    #[test]
+    fn set_errors() {
+        let mut ed = test_editor("x\n");
+        let err = |ed: &mut Editor, cmd| run(ed, cmd).unwrap_err().to_string();
+        assert_eq!(err(&mut ed, "set nosuch"), "unknown option: nosuch");
+        assert_eq!(err(&mut ed, "set nosw"), "invalid argument: nosw");
+        assert_eq!(err(&mut ed, "set ai=1"), "invalid argument: autoindent=1");
+        assert_eq!(
+            err(&mut ed, "set sw=0"),
+            "invalid value for 'shiftwidth': 0"
+        );
+        assert_eq!(err(&mut ed, "set ts+=1"), "invalid argument: ts+=1");
+    }
+
+    #[test]
+    fn split_args_keeps_escaped_spaces() {
+        assert_eq!(split_args(r"a=x\ y  b c\\"), ["a=x y", "b", r"c\"]);
+    }
+
+    #[test]
+    fn config_options_then_filetype() {
+        let mut ed = editor_with_suffix("x\n", ".go");
+        let config = Config::parse(
+            "[options]\nshiftwidth = 2\nnumber = false\nnosuch = 1\n\n[filetype.go]\nexpandtab = true\n\n[filetype.python]\nshiftwidth = 3\n",
+            Path::new("config.toml"),
+        )
+        .unwrap();
+        ed.load_config(config);
+        assert_eq!(ed.buffer.shiftwidth(), 2);
+        assert!(ed.buffer.expandtab());
+        assert!(!ed.window_options.number);
+        assert_eq!(
+            ed.status_message.as_deref(),
+            Some("config.toml: unknown option: nosuch")
+        );
+        run(&mut ed, "set ft=python").unwrap();
+        assert_eq!(ed.buffer.shiftwidth(), 3);
+        run(&mut ed, "set ft=text").unwrap();
+        assert_eq!(ed.buffer.shiftwidth(), 2);
+    }
+}
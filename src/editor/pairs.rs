@@ -0,0 +1,141 @@
+//! Auto-pairing in insert mode: typing an opener from the buffer's
+//! 'autopairs' adds its closer too, and typing the closer steps over it.
+
+use super::Editor;
+use crate::buffer::CharClass;
+
+impl Editor {
+    /// The characters either side of the cursor.
+    fn chars_around_cursor(&self) -> (Option<char>, Option<char>) {
+        let line = self.buffer.line(self.cursor_row).unwrap_or_default();
+        let before = self
+            .cursor_col
+            .checked_sub(1)
+            .and_then(|i| line.chars().nth(i));
+        (before, line.chars().nth(self.cursor_col))
+    }
+
+    /// Type `ch` as part of a pair: step over it if it's the closer under
+    /// the cursor, or insert its closer after it if it's an opener. Returns
+    /// false if `ch` should be inserted as usual.
+    pub fn insert_pair(&mut self, ch: char) -> bool {
+        let (before, after) = self.chars_around_cursor();
+        if after == Some(ch) && self.buffer.is_pair_closer(ch) {
+            self.cursor_col += 1;
+            self.autoindented = false;
+            return true;
+        }
+        let Some(close) = self.buffer.pair_closer(ch) else {
+            return false;
+        };
+        // Only pair in front of nothing, a blank or a closer, so `(` typed
+        // before a word doesn't get one.
+        let opens = after.is_none_or(|c| c.is_whitespace() || self.buffer.is_pair_closer(c));
+        // A quote after a word is an apostrophe or a string prefix.
+        let quote_after_word =
+            close == ch && before.is_some_and(|c| self.char_class(c, false) == CharClass::Keyword);
+        if !opens || quote_after_word {
+            return false;
+        }
+        self.insert_char(ch);
+        self.buffer
+            .insert_char(self.cursor_row, self.cursor_col, close);
+        true
+    }
+
+    /// Backspace between an empty pair deletes both halves. Returns false
+    /// if the cursor isn't between one.
+    pub fn delete_empty_pair(&mut self) -> bool {
+        let (Some(before), Some(after)) = self.chars_around_cursor() else {
+            return false;
+        };
+        if self.buffer.pair_closer(before) != Some(after) {
+            return false;
+        }
+        let (row, col) = (self.cursor_row, self.cursor_col);
+        self.buffer.delete_range((row, col - 1), (row, col + 1));
+        self.cursor_col -= 1;
+        true
+    }
+
+    /// Enter between an empty pair of brackets puts the closer on a line of
+    /// its own, lined up with the opener's line, and the cursor on an
+    /// indented line between them. Returns false if the cursor isn't
+    /// between brackets.
+    pub fn open_pair_block(&mut self) -> bool {
+        let (Some(before), Some(after)) = self.chars_around_cursor() else {
+            return false;
+        };
+        if before == after || self.buffer.pair_closer(before) != Some(after) {
+            return false;
+        }
+        let (row, col) = (self.cursor_row, self.cursor_col);
+        let width = self.buffer.indent_width(row);
+        self.buffer.insert_newline(row, col);
+        self.buffer.set_indent(row + 1, width);
+        self.buffer.insert_newline(row, col);
+        self.buffer
+            .set_indent(row + 1, width + self.buffer.shiftwidth());
+        self.cursor_row = row + 1;
+        self.cursor_col = self.buffer.line_len(row + 1);
+        self.autoindented = true;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{editor_with_suffix, test_editor, type_keys};
+
+    #[test]
+    fn openers_get_closers_and_closers_step_over() {
+        let mut ed = editor_with_suffix("\n", ".py");
+        type_keys(&mut ed, "if(x[");
+        assert_eq!(ed.buffer.line(0).unwrap(), "f(x[])");
+        type_keys(&mut ed, "1])'a");
+        assert_eq!(ed.buffer.line(0).unwrap(), "f(x[1])'a'");
+        assert_eq!(ed.cursor_col, 9);
+    }
+
+    #[test]
+    fn no_pair_before_a_word_or_quote_after_one() {
+        let mut ed = editor_with_suffix("x\n", ".py");
+        type_keys(&mut ed, "i(\x1b$adon't");
+        assert_eq!(ed.buffer.line(0).unwrap(), "(xdon't");
+    }
+
+    #[test]
+    fn rust_does_not_pair_single_quotes() {
+        let mut ed = editor_with_suffix("\n", ".rs");
+        type_keys(&mut ed, "i<'a");
+        assert_eq!(ed.buffer.line(0).unwrap(), "<'a");
+    }
+
+    #[test]
+    fn backspace_deletes_an_empty_pair() {
+        let mut ed = test_editor("\n");
+        type_keys(&mut ed, "i{(\x08");
+        assert_eq!(ed.buffer.line(0).unwrap(), "{}");
+        type_keys(&mut ed, "\x08");
+        assert_eq!(ed.buffer.line(0).unwrap(), "");
+    }
+
+    #[test]
+    fn enter_between_braces_opens_a_block() {
+        let mut ed = editor_with_suffix("    fn f() \n", ".rs");
+        type_keys(&mut ed, "$a{\nx");
+        assert_eq!(ed.buffer.line(0).unwrap(), "    fn f() {");
+        assert_eq!(ed.buffer.line(1).unwrap(), "        x");
+        assert_eq!(ed.buffer.line(2).unwrap(), "    }");
+        assert_eq!((ed.cursor_row, ed.cursor_col), (1, 9));
+    }
+
+    #[test]
+    fn set_noautopairs() {
+        let mut ed = test_editor("\n");
+        ed.command_buffer = "set autopairs=".to_string();
+        ed.execute_command().unwrap();
+        type_keys(&mut ed, "i(");
+        assert_eq!(ed.buffer.line(0).unwrap(), "(");
+    }
+}
@@ -0,0 +1,205 @@
+//! Registers hold yanked and deleted text for putting back with `p`/`P`.
+
+use std::collections::HashMap;
+
+use super::Editor;
+
+/// The register used when none is named.
+pub const UNNAMED: char = '"';
+/// The register holding the most recent yank.
+pub const YANK: char = '0';
+
+/// Text held in a register, and whether it was taken as whole lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Register {
+    pub text: String,
+    pub linewise: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Registers {
+    registers: HashMap<char, Register>,
+}
+
+impl Registers {
+    /// Whether `name` can follow `"`, `q` or `@`.
+    pub fn is_valid_name(name: char) -> bool {
+        name.is_ascii_alphanumeric() || name == UNNAMED
+    }
+
+    pub fn get(&self, name: char) -> Option<&Register> {
+        self.registers.get(&name.to_ascii_lowercase())
+    }
+
+    /// Store `register` under `name`. An uppercase name appends to the
+    /// lowercase register instead of replacing it.
+    pub fn set(&mut self, name: char, register: Register) {
+        if !name.is_ascii_uppercase() {
+            self.registers.insert(name, register);
+            return;
+        }
+        let name = name.to_ascii_lowercase();
+        match self.registers.get_mut(&name) {
+            Some(existing) => {
+                if register.linewise && !existing.text.ends_with('\n') {
+                    existing.text.push('\n');
+                }
+                existing.text.push_str(&register.text);
+                existing.linewise |= register.linewise;
+            }
+            None => {
+                self.registers.insert(name, register);
+            }
+        }
+    }
+}
+
+impl Editor {
+    /// Remember text removed or copied by an operator, in the register
+    /// named with `"x` if there is one. Unnamed yanks also go to register
+    /// `0`, so they survive a later delete. Empty charwise text (`y0` at
+    /// the start of a line) leaves the registers alone.
+    pub(super) fn store_register(&mut self, text: String, linewise: bool, yank: bool) {
+        if text.is_empty() && !linewise {
+            self.selected_register = None;
+            return;
+        }
+        let register = Register { text, linewise };
+        match self.selected_register.take() {
+            Some(name) if name != UNNAMED => self.registers.set(name, register.clone()),
+            _ if yank => self.registers.set(YANK, register.clone()),
+            _ => {}
+        }
+        self.registers.set(UNNAMED, register);
+    }
+
+    /// Put the selected register (or the unnamed one) [count] times after
+    /// the cursor (`p`) or before it (`P`). Linewise text goes on its own
+    /// lines.
+    pub fn put(&mut self, after: bool, count: usize) {
+        let name = self.selected_register.take().unwrap_or(UNNAMED);
+        let Some(register) = self.registers.get(name).cloned() else {
+            self.command_failed = true;
+            return;
+        };
+        let text = register.text.repeat(count.max(1));
+        if text.is_empty() {
+            self.command_failed = true;
+            return;
+        }
+        if register.linewise {
+            self.put_lines(&text, after);
+            return;
+        }
+
+        let line_len = self.buffer.line_len(self.cursor_row);
+        let col = if after && line_len > 0 {
+            self.cursor_col + 1
+        } else {
+            self.cursor_col
+        };
+        // Non-empty, so there is a last character for `']` and the cursor.
+        let last = text.chars().count() - 1;
+        self.buffer.insert_text(self.cursor_row, col, &text);
+        let start = self.buffer.char_index(self.cursor_row, col);
+        let end = self.buffer.position(start + last);
+        self.set_change_marks((self.cursor_row, col), end);
+        if text.contains('\n') {
+            self.cursor_col = col;
+        } else {
+            self.cursor_col = col + last;
+        }
+        self.clamp_cursor_col();
+    }
+
+    fn put_lines(&mut self, text: &str, after: bool) {
+        let row = if after {
+            self.cursor_row + 1
+        } else {
+            self.cursor_row
+        };
+        self.buffer.insert_text(row, 0, text);
+        let start = self.buffer.char_index(row, 0);
+        let end = self
+            .buffer
+            .position(start + text.chars().count().saturating_sub(2));
+        self.set_change_marks((row, 0), end);
+        self.cursor_row = row;
+        self.goto_first_non_blank();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::motion::Motion;
+    use super::super::operator::Operator;
+    use super::super::test_editor;
+    use super::{UNNAMED, YANK};
+
+    #[test]
+    fn delete_fills_unnamed_register() {
+        let mut ed = test_editor("foo bar\n");
+        ed.apply_operator(Operator::Delete, Motion::WordForward, None);
+        let reg = ed.registers.get(UNNAMED).unwrap();
+        assert_eq!(reg.text, "foo ");
+        assert!(!reg.linewise);
+        assert!(ed.registers.get(YANK).is_none());
+    }
+
+    #[test]
+    fn named_register_and_append() {
+        let mut ed = test_editor("foo bar\n");
+        ed.selected_register = Some('a');
+        ed.apply_operator(Operator::Yank, Motion::WordForward, None);
+        ed.cursor_col = 4;
+        ed.selected_register = Some('A');
+        ed.apply_operator(Operator::Yank, Motion::LineEnd, None);
+        assert_eq!(ed.registers.get('a').unwrap().text, "foo bar");
+        assert!(ed.registers.get(YANK).is_none());
+        ed.selected_register = Some('a');
+        ed.put(false, 1);
+        assert_eq!(ed.buffer.line(0).unwrap(), "foo foo barbar");
+    }
+
+    #[test]
+    fn put_charwise_after_and_before() {
+        let mut ed = test_editor("ab\n");
+        ed.apply_operator(Operator::Yank, Motion::Right, None);
+        ed.put(true, 2);
+        assert_eq!(ed.buffer.line(0).unwrap(), "aaab");
+        assert_eq!(ed.cursor_col, 2);
+        ed.put(false, 1);
+        assert_eq!(ed.buffer.line(0).unwrap(), "aaaab");
+    }
+
+    #[test]
+    fn put_lines_below_and_above() {
+        let mut ed = test_editor("one\ntwo\n");
+        ed.apply_operator_lines(Operator::Yank, 1);
+        ed.cursor_row = 1;
+        ed.put(true, 1);
+        assert_eq!(ed.buffer.line(2).unwrap(), "one");
+        assert_eq!(ed.cursor_row, 2);
+        ed.put(false, 1);
+        assert_eq!(ed.buffer.line(2).unwrap(), "one");
+        assert_eq!(ed.buffer.line(3).unwrap(), "one");
+    }
+
+    #[test]
+    fn empty_charwise_yanks_are_not_stored() {
+        let mut ed = test_editor("ab\n");
+        ed.apply_operator(Operator::Yank, Motion::Right, None);
+        ed.apply_operator(Operator::Yank, Motion::LineStart, None);
+        assert_eq!(ed.registers.get(UNNAMED).unwrap().text, "a");
+        ed.registers.set(
+            UNNAMED,
+            super::Register {
+                text: String::new(),
+                linewise: false,
+            },
+        );
+        ed.put(false, 1);
+        assert!(ed.command_failed);
+        assert_eq!(ed.buffer.line(0).unwrap(), "ab");
+    }
+}
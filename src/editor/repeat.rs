@@ -0,0 +1,219 @@
+//! Dot-repeat: the keys of each buffer-changing command are recorded as
+//! they're typed, so `.` can feed them back through `handle_key`.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::{Editor, handle_key};
+
+/// A recorded change, with its count split off so `.` can replace it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedChange {
+    pub keys: Vec<KeyEvent>,
+    pub count: Option<usize>,
+}
+
+/// Collects the keys of the command in progress.
+#[derive(Debug, Default)]
+pub struct DotRecorder {
+    keys: Vec<KeyEvent>,
+    /// The buffer's changedtick when the command started.
+    tick: u64,
+    /// Set while `.` feeds keys back in, so they aren't recorded again.
+    replaying: bool,
+    /// Set by `.` so the command that ran it isn't recorded as a change.
+    repeated: bool,
+    pub last: Option<RecordedChange>,
+}
+
+impl DotRecorder {
+    /// Whether `.` is feeding recorded keys back in.
+    pub fn is_replaying(&self) -> bool {
+        self.replaying
+    }
+}
+
+impl Editor {
+    /// Note `key` as part of the command being typed. Called by
+    /// `handle_key` before the key is handled.
+    pub(super) fn record_key(&mut self, key: KeyEvent) {
+        if self.dot.replaying {
+            return;
+        }
+        if self.is_idle() {
+            self.dot.keys.clear();
+            self.dot.tick = self.buffer.changedtick();
+        }
+        self.dot.keys.push(key);
+    }
+
+    /// Once a command is complete, keep its keys for `.` if it changed the
+    /// buffer. Called by `handle_key` after the key is handled.
+    pub(super) fn finish_recording(&mut self) {
+        if self.dot.replaying || !self.is_idle() {
+            return;
+        }
+        let keys = std::mem::take(&mut self.dot.keys);
+        let changed = self.buffer.changedtick() != self.dot.tick;
+        // Ex commands aren't repeated by `.`.
+        let ex_command = keys.first().is_some_and(|k| k.code == KeyCode::Char(':'));
+        if std::mem::take(&mut self.dot.repeated) || !changed || ex_command {
+            return;
+        }
+        self.dot.last = Some(split_count(keys));
+    }
+
+    /// Replay the last change (`.`). A count replaces the recorded one,
+    /// for this and later repeats.
+    pub fn repeat_last_change(&mut self, count: Option<usize>, viewport_height: usize) {
+        let Some(mut change) = self.dot.last.clone() else {
+            return;
+        };
+        if count.is_some() {
+            change.count = count;
+        }
+
+        self.dot.replaying = true;
+        let count_keys = change.count.map(|n| n.to_string()).unwrap_or_default();
+        for digit in count_keys.chars() {
+            let _ = handle_key(self, plain_key(digit), viewport_height);
+        }
+        for &key in &change.keys {
+            let _ = handle_key(self, key, viewport_height);
+        }
+        // A replayed insert that was never finished shouldn't leave us typing.
+        if self.mode.is_insert() {
+            self.exit_insert_mode();
+        }
+        self.dot.replaying = false;
+        self.dot.repeated = true;
+        self.dot.last = Some(change);
+    }
+}
+
+fn plain_key(c: char) -> KeyEvent {
+    KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+}
+
+/// Split the count off the front of a command (`3x`), and off an operator's
+/// motion (`2d3w` is `d` and `w` with a count of six).
+fn split_count(keys: Vec<KeyEvent>) -> RecordedChange {
+    let (lead, rest) = leading_count(&keys);
+    let mut keys = rest.to_vec();
+    let mut count = lead;
+    let op_len = match (keys.first().map(|k| k.code), keys.get(1).map(|k| k.code)) {
+        (Some(KeyCode::Char('d' | 'c' | 'y' | '>' | '<' | '=')), _) => 1,
+        (Some(KeyCode::Char('g')), Some(KeyCode::Char('u' | 'U' | '~'))) => 2,
+        _ => 0,
+    };
+    if op_len > 0 {
+        let (inner, after) = leading_count(&keys[op_len..]);
+        if inner.is_some() {
+            count = Some(lead.unwrap_or(1).saturating_mul(inner.unwrap_or(1)));
+            keys = keys[..op_len].iter().chain(after.iter()).copied().collect();
+        }
+    }
+    RecordedChange { keys, count }
+}
+
+/// A count typed at the start of `keys`, and the keys after it.
+fn leading_count(keys: &[KeyEvent]) -> (Option<usize>, &[KeyEvent]) {
+    let digits = keys
+        .iter()
+        .enumerate()
+        .take_while(|&(i, k)| match k.code {
+            KeyCode::Char(c) => c.is_ascii_digit() && (i > 0 || c != '0'),
+            _ => false,
+        })
+        .count();
+    if digits == 0 {
+        return (None, keys);
+    }
+    let count = keys[..digits]
+        .iter()
+        .filter_map(|k| match k.code {
+            KeyCode::Char(c) => c.to_digit(10),
+            _ => None,
+        })
+        .fold(0usize, |n, d| {
+            n.saturating_mul(10).saturating_add(d as usize)
+        });
+    (Some(count), &keys[digits..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{test_editor, type_keys};
+
+    #[test]
+    fn repeat_delete_word() {
+        let mut ed = test_editor("one two three four\n");
+        type_keys(&mut ed, "dw..");
+        assert_eq!(ed.buffer.line(0).unwrap(), "four");
+    }
+
+    #[test]
+    fn repeat_x_with_new_count() {
+        let mut ed = test_editor("abcdefgh\n");
+        type_keys(&mut ed, "2x");
+        type_keys(&mut ed, "3.");
+        assert_eq!(ed.buffer.line(0).unwrap(), "fgh");
+        // The new count sticks for later repeats.
+        type_keys(&mut ed, ".");
+        assert_eq!(ed.buffer.line(0).unwrap(), "");
+    }
+
+    #[test]
+    fn repeat_operator_count_is_replaced() {
+        let mut ed = test_editor("a b c d e f\n");
+        type_keys(&mut ed, "2d2w");
+        assert_eq!(ed.buffer.line(0).unwrap(), "e f");
+        type_keys(&mut ed, "1.");
+        assert_eq!(ed.buffer.line(0).unwrap(), "f");
+    }
+
+    #[test]
+    fn repeat_case_operator_count_is_replaced() {
+        let mut ed = test_editor("a b c d\n");
+        type_keys(&mut ed, "gU2w");
+        assert_eq!(ed.buffer.line(0).unwrap(), "A B c d");
+        type_keys(&mut ed, "ww3.");
+        assert_eq!(ed.buffer.line(0).unwrap(), "A B C D");
+    }
+
+    #[test]
+    fn repeat_insert_session() {
+        let mut ed = test_editor("x\ny\n");
+        type_keys(&mut ed, "ihi \x1bj0.");
+        assert_eq!(ed.buffer.line(0).unwrap(), "hi x");
+        assert_eq!(ed.buffer.line(1).unwrap(), "hi y");
+    }
+
+    #[test]
+    fn repeat_open_line() {
+        let mut ed = test_editor("a\n");
+        type_keys(&mut ed, "onew\x1b.");
+        assert_eq!(ed.buffer.line(1).unwrap(), "new");
+        assert_eq!(ed.buffer.line(2).unwrap(), "new");
+    }
+
+    #[test]
+    fn repeat_delete_lines() {
+        let mut ed = test_editor("a\nb\nc\nd\ne\n");
+        type_keys(&mut ed, "dd2.");
+        assert_eq!(ed.buffer.line(0).unwrap(), "d");
+    }
+
+    #[test]
+    fn motions_and_yanks_are_not_changes() {
+        let mut ed = test_editor("abc def\n");
+        type_keys(&mut ed, "xwyw.");
+        assert_eq!(ed.buffer.line(0).unwrap(), "bc ef");
+    }
+
+    #[test]
+    fn change_inner_word_repeats() {
+        let mut ed = test_editor("foo bar\n");
+        type_keys(&mut ed, "ciwX\x1bw.");
+        assert_eq!(ed.buffer.line(0).unwrap(), "X X");
+    }
+}
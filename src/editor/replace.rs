@@ -0,0 +1,148 @@
+use super::Editor;
+use crate::mode::Mode;
+
+/// What a key typed in replace mode did to the text, so Backspace can put
+/// it back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overtyped {
+    /// The key typed over this character.
+    Char(char),
+    /// The key added text instead (past the end of the line, or Enter).
+    Inserted,
+}
+
+impl Editor {
+    /// Start typing over the text from the cursor (`R`).
+    pub fn enter_replace_mode(&mut self) {
+        self.overtyped.clear();
+        self.mode = Mode::Replace;
+    }
+
+    /// Type `ch` over the character under the cursor, or append it when the
+    /// cursor is at the end of the line.
+    pub fn replace_mode_char(&mut self, ch: char) {
+        let (row, col) = (self.cursor_row, self.cursor_col);
+        let entry = match self.buffer.replace_char(row, col, ch) {
+            Some(old) => Overtyped::Char(old),
+            None => {
+                self.buffer.insert_char(row, col, ch);
+                Overtyped::Inserted
+            }
+        };
+        self.overtyped.push(entry);
+        self.cursor_col += 1;
+    }
+
+    /// Enter in replace mode breaks the line rather than replacing anything.
+    /// The new line isn't autoindented, so one Backspace joins it back.
+    pub fn replace_mode_newline(&mut self) {
+        self.buffer.insert_newline(self.cursor_row, self.cursor_col);
+        self.cursor_row += 1;
+        self.cursor_col = 0;
+        self.overtyped.push(Overtyped::Inserted);
+    }
+
+    /// Backspace in replace mode restores what the last typed key covered.
+    /// Before where replacing started it only moves the cursor left.
+    pub fn replace_mode_backspace(&mut self) {
+        match self.overtyped.pop() {
+            Some(Overtyped::Char(old)) => {
+                self.cursor_col -= 1;
+                self.buffer
+                    .replace_char(self.cursor_row, self.cursor_col, old);
+            }
+            Some(Overtyped::Inserted) => self.delete_char_back(),
+            None => self.cursor_col = self.cursor_col.saturating_sub(1),
+        }
+    }
+
+    /// Replace [count] characters from the cursor with `ch` (`r`), leaving
+    /// the cursor on the last one. A `'\n'` replaces them all with a single
+    /// line break. Fails if fewer than [count] characters are left on the
+    /// line.
+    pub fn replace_chars(&mut self, ch: char, count: usize) -> bool {
+        let (row, col) = (self.cursor_row, self.cursor_col);
+        let count = count.max(1);
+        if col + count > self.buffer.line_len(row) {
+            return false;
+        }
+        if ch == '\n' {
+            self.buffer.delete_range((row, col), (row, col + count));
+            self.buffer.insert_newline(row, col);
+            self.set_change_marks((row, col), (row + 1, 0));
+            self.cursor_row = row + 1;
+            self.cursor_col = 0;
+        } else {
+            for i in 0..count {
+                self.buffer.replace_char(row, col + i, ch);
+            }
+            self.cursor_col = col + count - 1;
+            self.set_change_marks((row, col), (row, self.cursor_col));
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{test_editor, type_keys};
+    use crate::mode::Mode;
+
+    #[test]
+    fn replace_mode_overtypes_and_appends() {
+        let mut ed = test_editor("abc\n");
+        ed.cursor_col = 1;
+        type_keys(&mut ed, "Rxyz");
+        assert_eq!(ed.mode, Mode::Replace);
+        assert_eq!(ed.mode.to_string(), "REPLACE");
+        type_keys(&mut ed, "\x1b");
+        assert_eq!(ed.buffer.line(0).unwrap(), "axyz");
+        assert_eq!((ed.mode, ed.cursor_col), (Mode::Normal, 3));
+    }
+
+    #[test]
+    fn replace_mode_backspace_restores() {
+        let mut ed = test_editor("abc\n");
+        ed.cursor_col = 1;
+        type_keys(&mut ed, "Rxyz\x08\x08\x08\x08");
+        assert_eq!(ed.buffer.line(0).unwrap(), "abc");
+        assert_eq!(ed.cursor_col, 0);
+    }
+
+    #[test]
+    fn replace_mode_enter_breaks_line() {
+        let mut ed = test_editor("abcd\n");
+        type_keys(&mut ed, "Rx\ny");
+        assert_eq!(ed.buffer.line(0).unwrap(), "x");
+        assert_eq!(ed.buffer.line(1).unwrap(), "ycd");
+        type_keys(&mut ed, "\x08\x08");
+        assert_eq!(ed.buffer.line(0).unwrap(), "xbcd");
+    }
+
+    #[test]
+    fn replace_char_with_count() {
+        let mut ed = test_editor("abcdef\n");
+        type_keys(&mut ed, "l3rx");
+        assert_eq!(ed.buffer.line(0).unwrap(), "axxxef");
+        assert_eq!(ed.cursor_col, 3);
+        type_keys(&mut ed, "5ry");
+        assert!(ed.command_failed);
+        assert_eq!(ed.buffer.line(0).unwrap(), "axxxef");
+    }
+
+    #[test]
+    fn replace_char_with_enter_splits() {
+        let mut ed = test_editor("ab cd\n");
+        type_keys(&mut ed, "llr\n");
+        assert_eq!(ed.buffer.line(0).unwrap(), "ab");
+        assert_eq!(ed.buffer.line(1).unwrap(), "cd");
+        assert_eq!((ed.cursor_row, ed.cursor_col), (1, 0));
+    }
+
+    #[test]
+    fn replace_is_repeatable() {
+        let mut ed = test_editor("abcd\n");
+        type_keys(&mut ed, "rxl.");
+        assert_eq!(ed.buffer.line(0).unwrap(), "xxcd");
+    }
+}
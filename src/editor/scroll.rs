@@ -0,0 +1,423 @@
+//! Scrolling the view: following the cursor with 'scrolloff', the
+//! `Ctrl-e`/`Ctrl-y`/`Ctrl-d`/`Ctrl-u`/`Ctrl-f`/`Ctrl-b` and `zz`/`zt`/`zb`
+//! commands, and, when 'wrap' is off, scrolling sideways with `zl`, `zh`,
+//! `zL`, `zH`, `zs` and `ze`.
+
+use super::Editor;
+use super::wrap::cell_width;
+
+impl Editor {
+    /// Scroll, if the cursor has gone out of view, to bring it back at
+    /// least 'scrolloff' lines from the top and bottom of the window.
+    pub fn adjust_scroll(&mut self, viewport_height: usize) {
+        self.adjust_left_col();
+        let margin = self.scroll_margin(viewport_height);
+        let top = self.cursor_row.saturating_sub(margin);
+        if top < self.scroll_offset {
+            self.scroll_offset = top;
+        }
+        // Walk up from the lines below the cursor while the lines above
+        // still fit.
+        let below = (self.cursor_row + margin).min(self.max_row());
+        let mut used = if margin == 0 {
+            self.cursor_display_pos().0 + 1
+        } else {
+            (self.cursor_row..=below)
+                .map(|row| self.line_rows(row))
+                .sum()
+        };
+        let mut top = self.cursor_row;
+        while top > self.scroll_offset {
+            let rows = self.line_rows(top - 1);
+            if used + rows > viewport_height {
+                break;
+            }
+            used += rows;
+            top -= 1;
+        }
+        self.scroll_offset = top;
+    }
+
+    /// 'scrolloff', kept small enough to leave room for the cursor.
+    fn scroll_margin(&self, viewport_height: usize) -> usize {
+        self.global_options
+            .scrolloff
+            .min(viewport_height.saturating_sub(1) / 2)
+    }
+
+    /// The top line that puts `row`'s last screen row at the bottom of the
+    /// window, or as near as the start of the buffer allows.
+    fn top_for_bottom(&self, row: usize, viewport_height: usize) -> usize {
+        let mut top = row;
+        let mut used = self.line_rows(row);
+        while top > 0 {
+            let rows = self.line_rows(top - 1);
+            if used + rows > viewport_height {
+                break;
+            }
+            used += rows;
+            top -= 1;
+        }
+        top
+    }
+
+    /// Move the cursor onto the screen after the view has scrolled,
+    /// 'scrolloff' lines from the edges where the buffer goes on.
+    fn keep_cursor_in_view(&mut self, viewport_height: usize) {
+        let margin = self.scroll_margin(viewport_height);
+        let bottom = match self.visible_lines(viewport_height).last() {
+            Some(&(row, _)) if row < self.max_row() => row.saturating_sub(margin),
+            Some(&(row, _)) => row,
+            None => self.scroll_offset,
+        };
+        let top = if self.scroll_offset == 0 {
+            0
+        } else {
+            self.scroll_offset + margin
+        };
+        self.cursor_row = self
+            .cursor_row
+            .max(top.min(bottom))
+            .min(bottom.max(self.scroll_offset));
+        self.clamp_cursor_col();
+    }
+
+    /// `Ctrl-e` and `Ctrl-y`: scroll the view `count` lines down the
+    /// buffer (or up, if `down` is false), leaving the cursor where it is
+    /// unless it would go off screen. Returns false if the view can't
+    /// scroll that way.
+    pub fn scroll_lines(&mut self, down: bool, count: usize, viewport_height: usize) -> bool {
+        let top = if down {
+            (self.scroll_offset + count).min(self.max_row())
+        } else {
+            self.scroll_offset.saturating_sub(count)
+        };
+        if top == self.scroll_offset {
+            return false;
+        }
+        self.scroll_offset = top;
+        self.keep_cursor_in_view(viewport_height);
+        true
+    }
+
+    /// How far `Ctrl-d` and `Ctrl-u` scroll: 'scroll' lines, or half the
+    /// window if that's 0.
+    fn scroll_amount(&self, viewport_height: usize) -> usize {
+        match self.window_options.scroll {
+            0 => (viewport_height / 2).max(1),
+            lines => lines,
+        }
+    }
+
+    /// `Ctrl-d` and `Ctrl-u`: scroll the view and move the cursor the same
+    /// number of lines down (or up). A count sets 'scroll' first. Returns
+    /// false if the cursor is already at that end of the buffer.
+    pub fn scroll_half_page(
+        &mut self,
+        down: bool,
+        count: Option<usize>,
+        viewport_height: usize,
+    ) -> bool {
+        if let Some(lines) = count {
+            self.window_options.scroll = lines;
+        }
+        let amount = self.scroll_amount(viewport_height);
+        if down {
+            if self.cursor_row >= self.max_row() {
+                return false;
+            }
+            // The view stops once the last line is at the bottom.
+            let last_top = self.top_for_bottom(self.max_row(), viewport_height);
+            self.scroll_offset =
+                (self.scroll_offset + amount).min(last_top.max(self.scroll_offset));
+            self.cursor_row = (self.cursor_row + amount).min(self.max_row());
+        } else {
+            if self.cursor_row == 0 {
+                return false;
+            }
+            self.scroll_offset = self.scroll_offset.saturating_sub(amount);
+            self.cursor_row = self.cursor_row.saturating_sub(amount);
+        }
+        self.clamp_cursor_col();
+        true
+    }
+
+    /// `Ctrl-f` and `Ctrl-b`: scroll `count` pages down (or up), keeping
+    /// two lines of the old page in view. Returns false if the view can't
+    /// scroll that way.
+    pub fn scroll_pages(&mut self, down: bool, count: usize, viewport_height: usize) -> bool {
+        let before = self.scroll_offset;
+        for _ in 0..count {
+            if down {
+                let bottom = self
+                    .visible_lines(viewport_height)
+                    .last()
+                    .map_or(self.scroll_offset, |&(row, _)| row);
+                self.scroll_offset = bottom
+                    .saturating_sub(1)
+                    .max(self.scroll_offset + 1)
+                    .min(self.max_row());
+            } else {
+                let bottom = (self.scroll_offset + 1).min(self.max_row());
+                self.scroll_offset = self
+                    .top_for_bottom(bottom, viewport_height)
+                    .min(self.scroll_offset.saturating_sub(1));
+            }
+        }
+        if self.scroll_offset == before {
+            return false;
+        }
+        self.keep_cursor_in_view(viewport_height);
+        true
+    }
+
+    /// `zt`, `zz` and `zb`: scroll so the cursor line is at the top,
+    /// middle or bottom of the window, 'scrolloff' allowing.
+    pub fn scroll_cursor_to(&mut self, place: ScreenPlace, viewport_height: usize) {
+        let margin = self.scroll_margin(viewport_height);
+        self.scroll_offset = match place {
+            ScreenPlace::Top => self.cursor_row.saturating_sub(margin),
+            ScreenPlace::Middle => {
+                // Split the rows the cursor line leaves free between above
+                // and below it.
+                let above = viewport_height.saturating_sub(self.line_rows(self.cursor_row)) / 2;
+                let mut top = self.cursor_row;
+                let mut used = 0;
+                while top > 0 {
+                    let rows = self.line_rows(top - 1);
+                    if used + rows > above {
+                        break;
+                    }
+                    used += rows;
+                    top -= 1;
+                }
+                top
+            }
+            ScreenPlace::Bottom => {
+                let row = (self.cursor_row + margin).min(self.max_row());
+                self.top_for_bottom(row, viewport_height)
+            }
+        };
+    }
+
+    /// Scroll sideways, if the cursor has gone out of view, to bring it
+    /// back at least 'sidescrolloff' columns from the side of the window.
+    pub(super) fn adjust_left_col(&mut self) {
+        let width = self.text_width;
+        if self.window_options.wrap || width == 0 {
+            self.left_col = 0;
+            return;
+        }
+        let margin = self.side_margin();
+        let col = self.cursor_col;
+        let min_left = (col + margin + 1).saturating_sub(width);
+        let max_left = col.saturating_sub(margin);
+        if !(min_left..=max_left).contains(&self.left_col) {
+            let step = self.global_options.sidescroll;
+            self.left_col = if step == 0 {
+                col.saturating_sub(width / 2)
+            } else if self.left_col > max_left {
+                max_left.min(self.left_col.saturating_sub(step))
+            } else {
+                min_left.max(self.left_col + step)
+            };
+        }
+        // Wide characters take two cells, so fewer columns may fit.
+        let cursor_width = self
+            .buffer
+            .line(self.cursor_row)
+            .and_then(|line| line.chars().nth(col))
+            .map_or(1, |c| cell_width(c).max(1));
+        while self.left_col < col && self.cursor_display_pos().1 + cursor_width + margin > width {
+            self.left_col += 1;
+        }
+    }
+
+    /// 'sidescrolloff', kept small enough to leave room for the cursor.
+    fn side_margin(&self) -> usize {
+        self.global_options
+            .sidescrolloff
+            .min(self.text_width.saturating_sub(1) / 2)
+    }
+
+    /// `zl` and `zh`: scroll the view `columns` to the right (or left, if
+    /// negative), moving the cursor along if it would go out of view.
+    pub fn scroll_sideways(&mut self, columns: isize) {
+        let width = self.text_width;
+        if self.window_options.wrap || width == 0 {
+            return;
+        }
+        self.left_col = self.left_col.saturating_add_signed(columns);
+        let margin = self.side_margin();
+        let first = if self.left_col == 0 {
+            0
+        } else {
+            self.left_col + margin
+        };
+        let last = self.left_col + width - 1 - margin;
+        self.cursor_col = self.cursor_col.clamp(first, last);
+        self.clamp_cursor_col();
+    }
+
+    /// `zL` and `zH`: scroll half the window's width.
+    pub fn scroll_half_width(&mut self, right: bool) {
+        let half = (self.text_width / 2) as isize;
+        self.scroll_sideways(if right { half } else { -half });
+    }
+
+    /// `zs`: scroll so the cursor is at the left of the window.
+    pub fn scroll_cursor_to_left(&mut self) {
+        if !self.window_options.wrap {
+            self.left_col = self.cursor_col.saturating_sub(self.side_margin());
+        }
+    }
+
+    /// `ze`: scroll so the cursor is at the right of the window.
+    pub fn scroll_cursor_to_right(&mut self) {
+        if !self.window_options.wrap {
+            let margin = self.side_margin();
+            self.left_col = (self.cursor_col + margin + 1).saturating_sub(self.text_width);
+        }
+    }
+}
+
+/// Where `zt`, `zz` and `zb` put the cursor line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenPlace {
+    Top,
+    Middle,
+    Bottom,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Editor, ctrl, test_editor};
+    use super::ScreenPlace;
+
+    /// An editor on lines "0" to "19".
+    fn long_editor() -> Editor {
+        let content: String = (0..20).map(|n| format!("{n}\n")).collect();
+        test_editor(&content)
+    }
+
+    #[test]
+    fn scrolloff_keeps_lines_around_the_cursor() {
+        let mut ed = long_editor();
+        ed.global_options.scrolloff = 2;
+        ed.cursor_row = 8;
+        ed.adjust_scroll(10);
+        assert_eq!(ed.scroll_offset, 1);
+        ed.cursor_row = 2;
+        ed.adjust_scroll(10);
+        assert_eq!(ed.scroll_offset, 0);
+        ed.cursor_row = 19;
+        ed.adjust_scroll(10);
+        assert_eq!(ed.scroll_offset, 10);
+    }
+
+    #[test]
+    fn ctrl_e_and_ctrl_y_move_the_view_not_the_cursor() {
+        let mut ed = long_editor();
+        ed.cursor_row = 5;
+        assert!(ed.scroll_lines(true, 3, 10));
+        assert_eq!((ed.scroll_offset, ed.cursor_row), (3, 5));
+        assert!(ed.scroll_lines(true, 3, 10));
+        assert_eq!((ed.scroll_offset, ed.cursor_row), (6, 6));
+        assert!(ed.scroll_lines(false, 10, 10));
+        assert_eq!((ed.scroll_offset, ed.cursor_row), (0, 6));
+        assert!(!ed.scroll_lines(false, 1, 10));
+    }
+
+    #[test]
+    fn ctrl_d_scrolls_by_the_scroll_option() {
+        let mut ed = long_editor();
+        ed.cursor_row = 2;
+        assert!(ed.scroll_half_page(true, None, 10));
+        assert_eq!((ed.scroll_offset, ed.cursor_row), (5, 7));
+        assert!(ed.scroll_half_page(true, Some(3), 10));
+        assert_eq!((ed.scroll_offset, ed.cursor_row), (8, 10));
+        assert_eq!(ed.window_options.scroll, 3);
+        ed.scroll_half_page(true, Some(20), 10);
+        assert_eq!((ed.scroll_offset, ed.cursor_row), (10, 19));
+        assert!(!ed.scroll_half_page(true, None, 10));
+        assert!(ed.scroll_half_page(false, Some(4), 10));
+        assert_eq!((ed.scroll_offset, ed.cursor_row), (6, 15));
+    }
+
+    #[test]
+    fn ctrl_f_and_ctrl_b_page() {
+        let content: String = (0..60).map(|n| format!("{n}\n")).collect();
+        let mut ed = test_editor(&content);
+        ctrl(&mut ed, 'f');
+        assert_eq!(ed.pending, None);
+        assert_eq!((ed.scroll_offset, ed.cursor_row), (18, 18));
+        ctrl(&mut ed, 'b');
+        assert_eq!((ed.scroll_offset, ed.cursor_row), (0, 18));
+        ctrl(&mut ed, 'b');
+        assert!(ed.command_failed);
+    }
+
+    #[test]
+    fn zt_zz_and_zb_place_the_cursor_line() {
+        let mut ed = long_editor();
+        ed.cursor_row = 10;
+        ed.scroll_cursor_to(ScreenPlace::Top, 10);
+        assert_eq!(ed.scroll_offset, 10);
+        ed.scroll_cursor_to(ScreenPlace::Middle, 10);
+        assert_eq!(ed.scroll_offset, 6);
+        ed.scroll_cursor_to(ScreenPlace::Bottom, 10);
+        assert_eq!(ed.scroll_offset, 1);
+        ed.global_options.scrolloff = 1;
+        ed.scroll_cursor_to(ScreenPlace::Top, 10);
+        assert_eq!(ed.scroll_offset, 9);
+    }
+
+    fn nowrap_editor(content: &str, width: usize) -> Editor {
+        let mut ed = test_editor(content);
+        ed.window_options.wrap = false;
+        ed.text_width = width;
+        ed
+    }
+
+    #[test]
+    fn left_col_follows_the_cursor() {
+        let mut ed = nowrap_editor("abcdefghijklmnopqrst\n", 10);
+        ed.cursor_col = 12;
+        ed.adjust_scroll(5);
+        assert_eq!(ed.left_col, 7);
+        ed.global_options.sidescroll = 1;
+        ed.cursor_col = 18;
+        ed.adjust_scroll(5);
+        assert_eq!(ed.left_col, 9);
+        ed.global_options.sidescrolloff = 2;
+        ed.cursor_col = 10;
+        ed.adjust_scroll(5);
+        assert_eq!(ed.left_col, 8);
+        assert_eq!(ed.cursor_display_pos(), (0, 2));
+    }
+
+    #[test]
+    fn zl_and_zh_drag_the_cursor_along() {
+        let mut ed = nowrap_editor("abcdefghijklmnopqrst\n", 10);
+        ed.scroll_sideways(3);
+        assert_eq!((ed.left_col, ed.cursor_col), (3, 3));
+        ed.cursor_col = 12;
+        ed.scroll_sideways(-3);
+        assert_eq!((ed.left_col, ed.cursor_col), (0, 9));
+        ed.scroll_half_width(true);
+        assert_eq!((ed.left_col, ed.cursor_col), (5, 9));
+    }
+
+    #[test]
+    fn zs_and_ze_put_the_cursor_at_a_side() {
+        let mut ed = nowrap_editor("abcdefghijklmnopqrst\n", 10);
+        ed.cursor_col = 12;
+        ed.scroll_cursor_to_left();
+        assert_eq!(ed.left_col, 12);
+        ed.scroll_cursor_to_right();
+        assert_eq!(ed.left_col, 3);
+        ed.window_options.wrap = true;
+        ed.adjust_scroll(5);
+        assert_eq!(ed.left_col, 0);
+    }
+}
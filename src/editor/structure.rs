@@ -0,0 +1,421 @@
+//! Motions over larger units of text: paragraphs, sentences and sections.
+
+use super::Editor;
+
+/// Characters that may follow a sentence's final `.`, `!` or `?`.
+const SENTENCE_CLOSERS: &[char] = &[')', ']', '"', '\''];
+
+/// Keywords that introduce a Rust item worth treating as a section.
+const RUST_ITEM_KEYWORDS: &[&str] = &[
+    "fn",
+    "impl",
+    "trait",
+    "mod",
+    "struct",
+    "enum",
+    "union",
+    "macro_rules!",
+];
+
+/// Words that may precede an item keyword (`pub(crate) async fn`).
+const RUST_ITEM_MODIFIERS: &[&str] = &["pub", "async", "const", "unsafe", "extern", "default"];
+
+impl Editor {
+    // ── Paragraph motions ─────────────────────────────────────────────
+
+    /// Move to the next empty line after the current paragraph (vim `}`).
+    /// Stops on the last character of the buffer if there is none.
+    pub fn move_paragraph_forward(&mut self) {
+        let max_row = self.max_row();
+        let mut row = self.cursor_row;
+        while row < max_row && self.is_empty_line(row) {
+            row += 1;
+        }
+        while row < max_row && !self.is_empty_line(row) {
+            row += 1;
+        }
+        self.cursor_row = row;
+        if self.is_empty_line(row) {
+            self.cursor_col = 0;
+        } else {
+            self.goto_line_end();
+        }
+    }
+
+    /// Move to the empty line before the current paragraph (vim `{`).
+    pub fn move_paragraph_backward(&mut self) {
+        let mut row = self.cursor_row;
+        while row > 0 && self.is_empty_line(row) {
+            row -= 1;
+        }
+        while row > 0 && !self.is_empty_line(row) {
+            row -= 1;
+        }
+        self.cursor_row = row;
+        self.cursor_col = 0;
+    }
+
+    fn is_empty_line(&self, row: usize) -> bool {
+        self.buffer.line_len(row) == 0
+    }
+
+    // ── Sentence motions ──────────────────────────────────────────────
+
+    /// Move to the start of the next sentence (vim `)`).
+    pub fn move_sentence_forward(&mut self) {
+        let end = self.text_end();
+        let mut idx = self.buffer.char_index(self.cursor_row, self.cursor_col) + 1;
+        while idx < end && !self.is_sentence_start(idx) {
+            idx += 1;
+        }
+        if idx >= end {
+            self.cursor_row = self.max_row();
+            self.goto_line_end();
+            return;
+        }
+        (self.cursor_row, self.cursor_col) = self.buffer.position(idx);
+    }
+
+    /// Move to the start of the current sentence, or the previous one if
+    /// already there (vim `(`).
+    pub fn move_sentence_backward(&mut self) {
+        let mut idx = self.buffer.char_index(self.cursor_row, self.cursor_col);
+        while idx > 0 {
+            idx -= 1;
+            if self.is_sentence_start(idx) {
+                break;
+            }
+        }
+        (self.cursor_row, self.cursor_col) = self.buffer.position(idx);
+    }
+
+    /// The char index just past the last real line (ignoring the empty line
+    /// ropey reports after a trailing newline).
+    pub(super) fn text_end(&self) -> usize {
+        let max_row = self.max_row();
+        self.buffer
+            .char_index(max_row, self.buffer.line_len(max_row))
+    }
+
+    /// Whether a sentence (or an empty line between paragraphs) starts at
+    /// char index `idx`. A sentence starts at the first non-blank after a
+    /// `.`, `!` or `?` (plus any closing brackets and quotes) that is followed
+    /// by white space, and at the first non-blank of a paragraph.
+    pub(super) fn is_sentence_start(&self, idx: usize) -> bool {
+        let Some(c) = self.buffer.char_at(idx) else {
+            return false;
+        };
+        let (row, col) = self.buffer.position(idx);
+        if c == '\n' {
+            return col == 0 && self.is_empty_line(row);
+        }
+        if c.is_whitespace() {
+            return false;
+        }
+
+        // Walk back over the white space before this character.
+        let mut j = idx;
+        let mut saw_blank = false;
+        while j > 0 {
+            let prev = self.buffer.char_at(j - 1).unwrap_or(' ');
+            if !prev.is_whitespace() {
+                break;
+            }
+            if prev == '\n' && (j == 1 || self.buffer.char_at(j - 2) == Some('\n')) {
+                // An empty line: this is the first sentence of a paragraph.
+                return true;
+            }
+            saw_blank = true;
+            j -= 1;
+        }
+        if j == 0 {
+            return true;
+        }
+        if !saw_blank {
+            return false;
+        }
+        while j > 0 && SENTENCE_CLOSERS.contains(&self.buffer.char_at(j - 1).unwrap_or(' ')) {
+            j -= 1;
+        }
+        j > 0 && matches!(self.buffer.char_at(j - 1), Some('.' | '!' | '?'))
+    }
+
+    // ── Section motions ───────────────────────────────────────────────
+
+    /// Move to the start of the next section (vim `]]`).
+    pub fn move_section_forward(&mut self) {
+        let starts = self.section_lines(true);
+        let row = starts.into_iter().find(|&r| r > self.cursor_row);
+        self.goto_section_line(row.unwrap_or(self.max_row()));
+    }
+
+    /// Move to the start of the current or previous section (vim `[[`).
+    pub fn move_section_backward(&mut self) {
+        let starts = self.section_lines(true);
+        let row = starts.into_iter().rev().find(|&r| r < self.cursor_row);
+        self.goto_section_line(row.unwrap_or(0));
+    }
+
+    /// Move to the end of the next section (vim `][`).
+    pub fn move_section_end_forward(&mut self) {
+        let ends = self.section_lines(false);
+        let row = ends.into_iter().find(|&r| r > self.cursor_row);
+        self.goto_section_line(row.unwrap_or(self.max_row()));
+    }
+
+    /// Move to the end of the previous section (vim `[]`).
+    pub fn move_section_end_backward(&mut self) {
+        let ends = self.section_lines(false);
+        let row = ends.into_iter().rev().find(|&r| r < self.cursor_row);
+        self.goto_section_line(row.unwrap_or(0));
+    }
+
+    fn goto_section_line(&mut self, row: usize) {
+        self.cursor_row = row;
+        self.cursor_col = 0;
+    }
+
+    /// Rows where sections start (or end, with `starts` false). Rust files
+    /// use item boundaries; everything else uses vim's classic rule of a `{`
+    /// or `}` in the first column.
+    fn section_lines(&self, starts: bool) -> Vec<usize> {
+        if self.buffer.filetype() == "rust" {
+            return self.rust_section_lines(starts);
+        }
+        let brace = if starts { '{' } else { '}' };
+        (0..=self.max_row())
+            .filter(|&row| self.buffer.line(row).is_some_and(|l| l.starts_with(brace)))
+            .collect()
+    }
+
+    /// Rust sections are `fn`, `impl`, `trait`, `mod` and type items that are
+    /// either at the top level or directly inside an `impl`/`trait`/`mod`
+    /// block, found by tracking brace depth. A section starts on the line
+    /// with the item keyword (even when the `{` comes later) and ends on the
+    /// line with its closing `}`.
+    fn rust_section_lines(&self, starts: bool) -> Vec<usize> {
+        // Each open brace records whether it began a section, and if so on
+        // which row.
+        let mut stack: Vec<Option<usize>> = Vec::new();
+        let mut pending_item: Option<usize> = None;
+        let mut rows = Vec::new();
+        let mut in_block_comment = false;
+
+        for row in 0..=self.max_row() {
+            let line = self.buffer.line(row).unwrap_or_default();
+            let code = strip_rust_line(&line, &mut in_block_comment);
+            // Only items whose enclosing blocks are all items themselves:
+            // methods in an impl count, anything inside an `if` body doesn't.
+            let at_item_level = stack.iter().all(Option::is_some);
+            if at_item_level && pending_item.is_none() && starts_rust_item(&code) {
+                pending_item = Some(row);
+            }
+            for c in code.chars() {
+                match c {
+                    '{' => {
+                        let section = pending_item.take();
+                        if starts && let Some(start) = section {
+                            rows.push(start);
+                        }
+                        stack.push(section);
+                    }
+                    '}' => {
+                        if let Some(Some(_)) = stack.pop()
+                            && !starts
+                        {
+                            rows.push(row);
+                        }
+                    }
+                    // A `;` ends a body-less item like `fn f();` or `struct S;`.
+                    ';' => pending_item = None,
+                    _ => {}
+                }
+            }
+        }
+        rows.dedup();
+        rows
+    }
+}
+
+/// Whether a line of Rust begins an item such as `pub(crate) async fn`.
+fn starts_rust_item(code: &str) -> bool {
+    for word in code.split_whitespace() {
+        let bare = word.split('(').next().unwrap_or(word);
+        if RUST_ITEM_KEYWORDS.contains(&bare) || bare.starts_with("macro_rules!") {
+            return true;
+        }
+        if !RUST_ITEM_MODIFIERS.contains(&bare) && !word.starts_with('"') {
+            return false;
+        }
+    }
+    false
+}
+
+/// Blank out comments and string/char literals in a line of Rust so their
+/// braces don't count, carrying `/* */` comments over to the next line.
+fn strip_rust_line(line: &str, in_block_comment: &mut bool) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut out = String::with_capacity(line.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if *in_block_comment {
+            if c == '*' && next == Some('/') {
+                *in_block_comment = false;
+                i += 1;
+            }
+        } else if c == '/' && next == Some('/') {
+            break;
+        } else if c == '/' && next == Some('*') {
+            *in_block_comment = true;
+            i += 1;
+        } else if c == '"' {
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            out.push_str("\"\"");
+        } else if c == '\'' && chars.get(i + 2) == Some(&'\'') {
+            // 'x' — a char literal rather than a lifetime.
+            i += 2;
+        } else if c == '\'' && next == Some('\\') {
+            // '\n', '\'' and friends.
+            i += 2;
+            while i < chars.len() && chars[i] != '\'' {
+                i += 1;
+            }
+        } else {
+            out.push(c);
+        }
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{editor_with_suffix, test_editor};
+
+    #[test]
+    fn paragraph_forward_stops_on_blank_line() {
+        let mut ed = test_editor("a\nb\n\nc\nd\n\ne\n");
+        ed.move_paragraph_forward();
+        assert_eq!((ed.cursor_row, ed.cursor_col), (2, 0));
+        ed.move_paragraph_forward();
+        assert_eq!(ed.cursor_row, 5);
+    }
+
+    #[test]
+    fn paragraph_forward_at_end_goes_to_last_char() {
+        let mut ed = test_editor("a\n\nlast line\n");
+        ed.cursor_row = 2;
+        ed.move_paragraph_forward();
+        assert_eq!((ed.cursor_row, ed.cursor_col), (2, 8));
+    }
+
+    #[test]
+    fn paragraph_backward() {
+        let mut ed = test_editor("a\n\nb\nc\n");
+        ed.cursor_row = 3;
+        ed.move_paragraph_backward();
+        assert_eq!(ed.cursor_row, 1);
+        ed.move_paragraph_backward();
+        assert_eq!(ed.cursor_row, 0);
+    }
+
+    #[test]
+    fn sentence_forward() {
+        let mut ed = test_editor("One two. Three four! Five\n");
+        ed.move_sentence_forward();
+        assert_eq!(ed.cursor_col, 9);
+        ed.move_sentence_forward();
+        assert_eq!(ed.cursor_col, 21);
+    }
+
+    #[test]
+    fn sentence_needs_space_after_period() {
+        let mut ed = test_editor("See e.g.this. Next\n");
+        ed.move_sentence_forward();
+        assert_eq!(ed.cursor_col, 14);
+    }
+
+    #[test]
+    fn sentence_forward_across_lines_and_closers() {
+        let mut ed = test_editor("He said (\"hi.\")\nThen left.\n");
+        ed.move_sentence_forward();
+        assert_eq!((ed.cursor_row, ed.cursor_col), (1, 0));
+    }
+
+    #[test]
+    fn sentence_backward() {
+        let mut ed = test_editor("One two. Three four.\n");
+        ed.cursor_col = 15;
+        ed.move_sentence_backward();
+        assert_eq!(ed.cursor_col, 9);
+        ed.move_sentence_backward();
+        assert_eq!(ed.cursor_col, 0);
+    }
+
+    #[test]
+    fn sentence_stops_at_paragraph_boundary() {
+        let mut ed = test_editor("First para\n\nSecond\n");
+        ed.move_sentence_forward();
+        assert_eq!((ed.cursor_row, ed.cursor_col), (1, 0));
+        ed.move_sentence_forward();
+        assert_eq!((ed.cursor_row, ed.cursor_col), (2, 0));
+    }
+
+    #[test]
+    fn classic_sections_use_column_zero_braces() {
+        let mut ed = test_editor("int f()\n{\n  x;\n}\nint g()\n{\n}\n");
+        ed.move_section_forward();
+        assert_eq!(ed.cursor_row, 1);
+        ed.move_section_forward();
+        assert_eq!(ed.cursor_row, 5);
+        ed.move_section_end_backward();
+        assert_eq!(ed.cursor_row, 3);
+    }
+
+    #[test]
+    fn rust_sections_find_items() {
+        let src = "use x;\n\
+                   \n\
+                   fn one() {\n\
+                   \x20   let c = |a| { a };\n\
+                   }\n\
+                   \n\
+                   impl Foo {\n\
+                   \x20   pub fn two(\n\
+                   \x20       &self,\n\
+                   \x20   ) {\n\
+                   \x20       if x { \"}\" }\n\
+                   \x20   }\n\
+                   }\n";
+        let mut ed = editor_with_suffix(src, ".rs");
+        ed.move_section_forward();
+        assert_eq!(ed.cursor_row, 2);
+        ed.move_section_forward();
+        assert_eq!(ed.cursor_row, 6);
+        ed.move_section_forward();
+        assert_eq!(ed.cursor_row, 7);
+        ed.move_section_backward();
+        assert_eq!(ed.cursor_row, 6);
+        ed.move_section_end_forward();
+        assert_eq!(ed.cursor_row, 11);
+        ed.move_section_end_forward();
+        assert_eq!(ed.cursor_row, 12);
+        ed.move_section_end_backward();
+        assert_eq!(ed.cursor_row, 11);
+    }
+
+    #[test]
+    fn rust_sections_ignore_body_less_items() {
+        let mut ed = editor_with_suffix("trait T {\n    fn a();\n    fn b() {}\n}\n", ".rs");
+        ed.move_section_forward();
+        assert_eq!(ed.cursor_row, 2);
+    }
+}
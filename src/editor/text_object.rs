@@ -0,0 +1,503 @@
+//! Text objects: the `iw`, `a(`, `it` that follow an operator or extend a
+//! visual selection.
+
+use super::Editor;
+use crate::buffer::CharClass;
+
+/// What a text object covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Word,
+    BigWord,
+    Sentence,
+    Paragraph,
+    /// Text between a pair of this quote character on the line.
+    Quote(char),
+    /// Text between an open and close bracket.
+    Bracket(char, char),
+    /// Text between an XML/HTML start tag and its end tag.
+    Tag,
+}
+
+/// A text object with its `i` (inner) or `a` (around) flavour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextObject {
+    pub kind: ObjectKind,
+    pub inner: bool,
+}
+
+impl TextObject {
+    /// The object named by the key typed after `i` or `a`.
+    pub fn from_key(c: char, inner: bool) -> Option<Self> {
+        let kind = match c {
+            'w' => ObjectKind::Word,
+            'W' => ObjectKind::BigWord,
+            's' => ObjectKind::Sentence,
+            'p' => ObjectKind::Paragraph,
+            '"' | '\'' | '`' => ObjectKind::Quote(c),
+            '(' | ')' | 'b' => ObjectKind::Bracket('(', ')'),
+            '[' | ']' => ObjectKind::Bracket('[', ']'),
+            '{' | '}' | 'B' => ObjectKind::Bracket('{', '}'),
+            '<' | '>' => ObjectKind::Bracket('<', '>'),
+            't' => ObjectKind::Tag,
+            _ => return None,
+        };
+        Some(Self { kind, inner })
+    }
+}
+
+/// A span of text selected by a text object or visual selection. For a
+/// charwise range `end` is exclusive; for a linewise one only the rows
+/// matter and `end.0` is the last row included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextRange {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+    pub linewise: bool,
+}
+
+impl TextRange {
+    fn chars(start: (usize, usize), end: (usize, usize)) -> Self {
+        Self {
+            start,
+            end,
+            linewise: false,
+        }
+    }
+}
+
+impl Editor {
+    /// The text covered by [count] of `object` around the cursor, or None
+    /// if there is no such object (e.g. `i(` outside any parentheses).
+    pub fn text_object_range(&self, object: TextObject, count: usize) -> Option<TextRange> {
+        let count = count.max(1);
+        let inner = object.inner;
+        match object.kind {
+            ObjectKind::Word => self.word_object(inner, false, count),
+            ObjectKind::BigWord => self.word_object(inner, true, count),
+            ObjectKind::Sentence => self.sentence_object(inner, count),
+            ObjectKind::Paragraph => self.paragraph_object(inner, count),
+            ObjectKind::Quote(quote) => self.quote_object(quote, inner),
+            ObjectKind::Bracket(open, close) => self.bracket_object((open, close), inner, count),
+            ObjectKind::Tag => self.tag_object(inner, count),
+        }
+    }
+
+    /// `iw` selects [count] runs of word characters, punctuation or blanks;
+    /// `aw` also takes the white space after each word, or before the first
+    /// one if there is none after.
+    fn word_object(&self, inner: bool, big: bool, count: usize) -> Option<TextRange> {
+        let row = self.cursor_row;
+        let chars: Vec<char> = self.buffer.line(row)?.chars().collect();
+        if chars.is_empty() {
+            return None;
+        }
+        let class = |i: usize| self.char_class(chars[i], big);
+        let run_end = |from: usize| {
+            let mut end = from;
+            while end < chars.len() && class(end) == class(from) {
+                end += 1;
+            }
+            end
+        };
+
+        let col = self.cursor_col.min(chars.len() - 1);
+        let mut start = col;
+        while start > 0 && class(start - 1) == class(col) {
+            start -= 1;
+        }
+        let on_blank = class(col) == CharClass::Blank;
+
+        let mut end = start;
+        for _ in 0..count {
+            if end >= chars.len() {
+                break;
+            }
+            end = run_end(end);
+            if !inner && end < chars.len() && (on_blank || class(end) == CharClass::Blank) {
+                end = run_end(end);
+            }
+        }
+
+        let trailing_blank = class(end - 1) == CharClass::Blank;
+        if !inner && !trailing_blank && !on_blank {
+            let indent = self.first_non_blank_col(row);
+            while start > indent && class(start - 1) == CharClass::Blank {
+                start -= 1;
+            }
+        }
+        Some(TextRange::chars((row, start), (row, end)))
+    }
+
+    /// `is` selects [count] sentences without the white space after the
+    /// last one; `as` keeps it.
+    fn sentence_object(&self, inner: bool, count: usize) -> Option<TextRange> {
+        let text_end = self.text_end();
+        let idx = self
+            .buffer
+            .char_index(self.cursor_row, self.cursor_col)
+            .min(text_end);
+        let mut start = idx;
+        while start > 0 && !self.is_sentence_start(start) {
+            start -= 1;
+        }
+        let mut end = start;
+        for _ in 0..count {
+            end += 1;
+            while end < text_end && !self.is_sentence_start(end) {
+                end += 1;
+            }
+        }
+        end = end.min(text_end);
+        if inner {
+            while end > start
+                && self
+                    .buffer
+                    .char_at(end - 1)
+                    .is_some_and(char::is_whitespace)
+            {
+                end -= 1;
+            }
+        }
+        Some(TextRange::chars(
+            self.buffer.position(start),
+            self.buffer.position(end),
+        ))
+    }
+
+    /// `ip` selects [count] runs of blank or non-blank lines; `ap` selects
+    /// [count] paragraphs with the blank lines after them, or before them
+    /// if there are none after.
+    fn paragraph_object(&self, inner: bool, count: usize) -> Option<TextRange> {
+        let max_row = self.max_row();
+        let blank = |row: usize| self.buffer.line(row).is_none_or(|l| l.trim().is_empty());
+        let run_end = |from: usize| {
+            let mut end = from;
+            while end < max_row && blank(end + 1) == blank(from) {
+                end += 1;
+            }
+            end
+        };
+
+        let row = self.cursor_row;
+        let mut start = row;
+        while start > 0 && blank(start - 1) == blank(row) {
+            start -= 1;
+        }
+        let runs = if inner { count } else { count * 2 };
+        let mut end = run_end(start);
+        for _ in 1..runs {
+            if end >= max_row {
+                break;
+            }
+            end = run_end(end + 1);
+        }
+
+        if !inner && !blank(end) && !blank(row) {
+            while start > 0 && blank(start - 1) {
+                start -= 1;
+            }
+        }
+        Some(TextRange {
+            start: (start, 0),
+            end: (end, 0),
+            linewise: true,
+        })
+    }
+
+    /// `i"` selects the text between the quotes around (or after) the
+    /// cursor on its line; `a"` includes the quotes and trailing white space.
+    fn quote_object(&self, quote: char, inner: bool) -> Option<TextRange> {
+        let row = self.cursor_row;
+        let chars: Vec<char> = self.buffer.line(row)?.chars().collect();
+        let quotes: Vec<usize> = (0..chars.len())
+            .filter(|&i| chars[i] == quote && (i == 0 || chars[i - 1] != '\\'))
+            .collect();
+        let col = self.cursor_col;
+        let (open, close) = quotes
+            .chunks_exact(2)
+            .map(|pair| (pair[0], pair[1]))
+            .find(|&(_, close)| col <= close)?;
+
+        if inner {
+            return Some(TextRange::chars((row, open + 1), (row, close)));
+        }
+        let (mut start, mut end) = (open, close + 1);
+        if end < chars.len() && chars[end].is_whitespace() {
+            while end < chars.len() && chars[end].is_whitespace() {
+                end += 1;
+            }
+        } else {
+            while start > 0 && chars[start - 1].is_whitespace() {
+                start -= 1;
+            }
+        }
+        Some(TextRange::chars((row, start), (row, end)))
+    }
+
+    /// `i(` selects the text inside the [count]th enclosing pair, leaving
+    /// out a line break right after the opening bracket and the indent
+    /// before a closing bracket on its own line; `a(` includes the brackets.
+    fn bracket_object(&self, pair: (char, char), inner: bool, count: usize) -> Option<TextRange> {
+        let idx = self.buffer.char_index(self.cursor_row, self.cursor_col);
+        let (open, close) = self.buffer.enclosing_pair(idx, pair, count)?;
+        if !inner {
+            return Some(TextRange::chars(
+                self.buffer.position(open),
+                self.buffer.position(close + 1),
+            ));
+        }
+
+        let mut start = self.buffer.position(open + 1);
+        if self.buffer.char_at(open + 1) == Some('\n') {
+            start = (start.0 + 1, 0);
+        }
+        let mut end = self.buffer.position(close);
+        let before_close = self.buffer.text_range((end.0, 0), end);
+        if end.0 > self.buffer.position(open).0 && before_close.trim().is_empty() {
+            end = (end.0, 0);
+        }
+        if end < start {
+            end = start;
+        }
+        Some(TextRange::chars(start, end))
+    }
+
+    /// `it` selects the contents of the [count]th enclosing element; `at`
+    /// includes its start and end tags.
+    fn tag_object(&self, inner: bool, count: usize) -> Option<TextRange> {
+        let chars: Vec<char> = self.buffer.contents().chars().collect();
+        let idx = self.buffer.char_index(self.cursor_row, self.cursor_col);
+
+        let mut enclosing: Vec<TagPair> = tag_pairs(&chars)
+            .into_iter()
+            .filter(|pair| pair.open.0 <= idx && idx < pair.close.1)
+            .collect();
+        // Innermost first.
+        enclosing.sort_by_key(|pair| std::cmp::Reverse(pair.open.0));
+        let pair = enclosing.get(count - 1)?;
+
+        let (start, end) = if inner {
+            (pair.open.1, pair.close.0)
+        } else {
+            (pair.open.0, pair.close.1)
+        };
+        Some(TextRange::chars(
+            self.buffer.position(start),
+            self.buffer.position(end),
+        ))
+    }
+}
+
+/// A start tag and its end tag, each as a char range `(start, end)` with
+/// `end` exclusive.
+struct TagPair {
+    open: (usize, usize),
+    close: (usize, usize),
+}
+
+/// Pair up the start and end tags in `chars`. Self-closing tags, comments
+/// and declarations are skipped; unbalanced tags are dropped.
+fn tag_pairs(chars: &[char]) -> Vec<TagPair> {
+    let mut pairs = Vec::new();
+    let mut stack: Vec<(String, (usize, usize))> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] != '<' {
+            i += 1;
+            continue;
+        }
+        let Some(len) = chars[i..].iter().position(|&c| c == '>') else {
+            break;
+        };
+        let tag = (i, i + len + 1);
+        let body: String = chars[i + 1..i + len].iter().collect();
+        i = tag.1;
+
+        if let Some(name) = body.strip_prefix('/') {
+            let name = name.trim();
+            if let Some(depth) = stack.iter().rposition(|(open, _)| open == name) {
+                let (_, open) = stack[depth];
+                stack.truncate(depth);
+                pairs.push(TagPair { open, close: tag });
+            }
+        } else if body.starts_with(|c: char| c.is_alphabetic()) && !body.ends_with('/') {
+            let name: String = body
+                .chars()
+                .take_while(|c| !c.is_whitespace() && *c != '/')
+                .collect();
+            stack.push((name, tag));
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::operator::Operator;
+    use super::super::test_editor;
+    use super::{TextObject, TextRange};
+    use crate::mode::Mode;
+
+    fn object(key: char, inner: bool) -> TextObject {
+        TextObject::from_key(key, inner).unwrap()
+    }
+
+    #[test]
+    fn inner_and_around_word() {
+        let mut ed = test_editor("foo bar baz\n");
+        ed.cursor_col = 5;
+        let range = ed.text_object_range(object('w', true), 1).unwrap();
+        assert_eq!((range.start, range.end), ((0, 4), (0, 7)));
+        let range = ed.text_object_range(object('w', false), 1).unwrap();
+        assert_eq!((range.start, range.end), ((0, 4), (0, 8)));
+    }
+
+    #[test]
+    fn around_last_word_takes_leading_space() {
+        let mut ed = test_editor("foo bar\n");
+        ed.cursor_col = 5;
+        ed.apply_operator_object(Operator::Delete, object('w', false), 1);
+        assert_eq!(ed.buffer.line(0).unwrap(), "foo");
+    }
+
+    #[test]
+    fn inner_word_with_count_counts_blanks() {
+        let mut ed = test_editor("foo bar baz\n");
+        ed.apply_operator_object(Operator::Delete, object('w', true), 3);
+        assert_eq!(ed.buffer.line(0).unwrap(), " baz");
+    }
+
+    #[test]
+    fn around_big_word() {
+        let mut ed = test_editor("a foo.bar b\n");
+        ed.cursor_col = 4;
+        ed.apply_operator_object(Operator::Delete, object('W', false), 1);
+        assert_eq!(ed.buffer.line(0).unwrap(), "a b");
+    }
+
+    #[test]
+    fn change_inner_quotes() {
+        let mut ed = test_editor("x = \"hello\" + 'a'\n");
+        ed.apply_operator_object(Operator::Change, object('"', true), 1);
+        assert_eq!(ed.buffer.line(0).unwrap(), "x = \"\" + 'a'");
+        assert_eq!(ed.mode, Mode::Insert);
+        assert_eq!(ed.cursor_col, 5);
+    }
+
+    #[test]
+    fn around_quotes_takes_trailing_space() {
+        let mut ed = test_editor("say \"hi\" now\n");
+        ed.cursor_col = 6;
+        ed.apply_operator_object(Operator::Delete, object('"', false), 1);
+        assert_eq!(ed.buffer.line(0).unwrap(), "say now");
+    }
+
+    #[test]
+    fn quotes_skip_escapes() {
+        let mut ed = test_editor("\"a\\\"b\" c\n");
+        ed.cursor_col = 1;
+        ed.apply_operator_object(Operator::Delete, object('"', true), 1);
+        assert_eq!(ed.buffer.line(0).unwrap(), "\"\" c");
+    }
+
+    #[test]
+    fn delete_around_parens() {
+        let mut ed = test_editor("f(a, (b))\n");
+        ed.cursor_col = 6;
+        ed.apply_operator_object(Operator::Delete, object('(', false), 1);
+        assert_eq!(ed.buffer.line(0).unwrap(), "f(a, )");
+    }
+
+    #[test]
+    fn bracket_count_selects_outer_level() {
+        let mut ed = test_editor("f(a, (b))\n");
+        ed.cursor_col = 6;
+        ed.apply_operator_object(Operator::Delete, object('b', true), 2);
+        assert_eq!(ed.buffer.line(0).unwrap(), "f()");
+    }
+
+    #[test]
+    fn inner_braces_across_lines_keeps_lines() {
+        let mut ed = test_editor("fn f() {\n    x;\n    y;\n}\n");
+        ed.cursor_row = 1;
+        ed.apply_operator_object(Operator::Delete, object('{', true), 1);
+        assert_eq!(ed.buffer.line(0).unwrap(), "fn f() {");
+        assert_eq!(ed.buffer.line(1).unwrap(), "}");
+    }
+
+    #[test]
+    fn angle_brackets() {
+        let mut ed = test_editor("Vec<Option<u8>>\n");
+        ed.cursor_col = 12;
+        ed.apply_operator_object(Operator::Delete, object('<', true), 2);
+        assert_eq!(ed.buffer.line(0).unwrap(), "Vec<>");
+    }
+
+    #[test]
+    fn inner_and_around_tag() {
+        let mut ed = test_editor("<div><b>bold</b> text</div>\n");
+        ed.cursor_col = 9;
+        let range = ed.text_object_range(object('t', true), 1).unwrap();
+        assert_eq!((range.start, range.end), ((0, 8), (0, 12)));
+        let range = ed.text_object_range(object('t', false), 2).unwrap();
+        assert_eq!((range.start, range.end), ((0, 0), (0, 27)));
+    }
+
+    #[test]
+    fn tag_skips_self_closing() {
+        let mut ed = test_editor("<p>a<br/>b</p>\n");
+        ed.cursor_col = 10;
+        ed.apply_operator_object(Operator::Delete, object('t', true), 1);
+        assert_eq!(ed.buffer.line(0).unwrap(), "<p></p>");
+    }
+
+    #[test]
+    fn inner_paragraph_is_linewise() {
+        let ed = test_editor("a\nb\n\nc\n");
+        let range = ed.text_object_range(object('p', true), 1).unwrap();
+        assert_eq!(
+            range,
+            TextRange {
+                start: (0, 0),
+                end: (1, 0),
+                linewise: true
+            }
+        );
+    }
+
+    #[test]
+    fn yank_around_paragraph_includes_blank_lines() {
+        let mut ed = test_editor("a\nb\n\n\nc\n");
+        ed.apply_operator_object(Operator::Yank, object('p', false), 1);
+        let reg = ed.registers.get('"').unwrap();
+        assert_eq!(reg.text, "a\nb\n\n\n");
+        assert!(reg.linewise);
+    }
+
+    #[test]
+    fn around_last_paragraph_takes_blank_lines_before() {
+        let mut ed = test_editor("a\n\nb\nc\n");
+        ed.cursor_row = 3;
+        ed.apply_operator_object(Operator::Delete, object('p', false), 1);
+        assert_eq!(ed.buffer.line(0).unwrap(), "a");
+        assert_eq!(ed.max_row(), 0);
+    }
+
+    #[test]
+    fn inner_and_around_sentence() {
+        let mut ed = test_editor("One two. Three four. Five.\n");
+        ed.cursor_col = 12;
+        let range = ed.text_object_range(object('s', true), 1).unwrap();
+        assert_eq!((range.start, range.end), ((0, 9), (0, 20)));
+        let range = ed.text_object_range(object('s', false), 1).unwrap();
+        assert_eq!((range.start, range.end), ((0, 9), (0, 21)));
+    }
+
+    #[test]
+    fn missing_object_does_nothing() {
+        let mut ed = test_editor("abc\n");
+        ed.apply_operator_object(Operator::Delete, object('(', true), 1);
+        assert_eq!(ed.buffer.line(0).unwrap(), "abc");
+    }
+}
@@ -0,0 +1,195 @@
+use super::Editor;
+use super::operator::Operator;
+use super::text_object::{TextObject, TextRange};
+use crate::mode::Mode;
+
+impl Editor {
+    /// Start a visual selection at the cursor (`v`, or `V` for whole lines).
+    pub fn enter_visual_mode(&mut self, linewise: bool) {
+        self.visual_start = (self.cursor_row, self.cursor_col);
+        self.mode = if linewise {
+            Mode::VisualLine
+        } else {
+            Mode::Visual
+        };
+    }
+
+    pub fn exit_visual_mode(&mut self) {
+        self.set_visual_marks();
+        self.mode = Mode::Normal;
+        self.clamp_cursor_col();
+    }
+
+    /// Remember the selection in the `<` and `>` marks.
+    fn set_visual_marks(&mut self) {
+        let cursor = (self.cursor_row, self.cursor_col);
+        self.set_mark_at('<', cursor.min(self.visual_start));
+        self.set_mark_at('>', cursor.max(self.visual_start));
+    }
+
+    /// Move the cursor to the other end of the selection (`o`).
+    pub fn visual_swap_ends(&mut self) {
+        let cursor = (self.cursor_row, self.cursor_col);
+        (self.cursor_row, self.cursor_col) = self.visual_start;
+        self.visual_start = cursor;
+    }
+
+    /// The selected text, or None outside visual mode. A charwise selection
+    /// includes the character under its far end, and the line break when
+    /// that end is on an empty line.
+    pub fn visual_range(&self) -> Option<TextRange> {
+        if !self.mode.is_visual() {
+            return None;
+        }
+        let cursor = (self.cursor_row, self.cursor_col);
+        let (start, end) = if cursor < self.visual_start {
+            (cursor, self.visual_start)
+        } else {
+            (self.visual_start, cursor)
+        };
+        let linewise = self.mode == Mode::VisualLine;
+        if linewise {
+            return Some(TextRange {
+                start: (start.0, 0),
+                end: (end.0, 0),
+                linewise,
+            });
+        }
+        let line_len = self.buffer.line_len(end.0);
+        let end = if end.1 < line_len {
+            (end.0, end.1 + 1)
+        } else if end.0 < self.max_row() {
+            (end.0 + 1, 0)
+        } else {
+            (end.0, line_len)
+        };
+        Some(TextRange {
+            start,
+            end,
+            linewise,
+        })
+    }
+
+    /// Apply `op` to the selection and leave visual mode (`d`, `c`, `y`).
+    pub fn apply_operator_visual(&mut self, op: Operator) {
+        let Some(range) = self.visual_range() else {
+            return;
+        };
+        self.set_visual_marks();
+        self.mode = Mode::Normal;
+        self.operate_range(op, range);
+    }
+
+    /// Extend the selection to cover [count] of `object` (`vi(`, `vap`).
+    /// Repeating an object that is already selected selects the next level
+    /// out, so `vi(i(` grows to the enclosing parentheses.
+    /// Returns false if there is no such object.
+    pub fn select_text_object(&mut self, object: TextObject, count: usize) -> bool {
+        let current = self.visual_range();
+        let Some(mut range) = self.text_object_range(object, count) else {
+            return false;
+        };
+        if current == Some(range)
+            && let Some(outer) = self.text_object_range(object, count.max(1) + 1)
+        {
+            range = outer;
+        }
+
+        if range.linewise {
+            self.mode = Mode::VisualLine;
+            self.visual_start = (range.start.0.min(self.visual_start.0), 0);
+            self.cursor_row = range.end.0.max(self.cursor_row);
+            self.cursor_col = 0;
+            return true;
+        }
+        if range.end <= range.start {
+            return true;
+        }
+        let last = self.buffer.position(
+            self.buffer
+                .char_index(range.end.0, range.end.1)
+                .saturating_sub(1),
+        );
+        let fresh = self.visual_start == (self.cursor_row, self.cursor_col);
+        if fresh {
+            self.visual_start = range.start;
+            (self.cursor_row, self.cursor_col) = last;
+        } else {
+            self.visual_start = self.visual_start.min(range.start);
+            (self.cursor_row, self.cursor_col) = last.max((self.cursor_row, self.cursor_col));
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::motion::Motion;
+    use super::super::operator::Operator;
+    use super::super::test_editor;
+    use super::super::text_object::TextObject;
+    use crate::mode::Mode;
+
+    #[test]
+    fn visual_delete_includes_cursor_char() {
+        let mut ed = test_editor("abcdef\n");
+        ed.cursor_col = 1;
+        ed.enter_visual_mode(false);
+        ed.apply_motion(Motion::Right, Some(2));
+        ed.apply_operator_visual(Operator::Delete);
+        assert_eq!(ed.buffer.line(0).unwrap(), "aef");
+        assert_eq!(ed.mode, Mode::Normal);
+        assert_eq!(ed.cursor_col, 1);
+    }
+
+    #[test]
+    fn visual_selection_backwards() {
+        let mut ed = test_editor("abcdef\n");
+        ed.cursor_col = 3;
+        ed.enter_visual_mode(false);
+        ed.apply_motion(Motion::Left, Some(2));
+        ed.apply_operator_visual(Operator::Yank);
+        assert_eq!(ed.registers.get('"').unwrap().text, "bcd");
+    }
+
+    #[test]
+    fn visual_line_delete() {
+        let mut ed = test_editor("a\nb\nc\n");
+        ed.enter_visual_mode(true);
+        ed.apply_motion(Motion::Down, None);
+        ed.apply_operator_visual(Operator::Delete);
+        assert_eq!(ed.buffer.line(0).unwrap(), "c");
+    }
+
+    #[test]
+    fn visual_text_object_selects_and_grows() {
+        let mut ed = test_editor("f(a, (bc))\n");
+        ed.cursor_col = 7;
+        ed.enter_visual_mode(false);
+        let inner_paren = TextObject::from_key('(', true).unwrap();
+        ed.select_text_object(inner_paren, 1);
+        assert_eq!(ed.visual_start, (0, 6));
+        assert_eq!(ed.cursor_col, 7);
+        ed.select_text_object(inner_paren, 1);
+        assert_eq!(ed.visual_start, (0, 2));
+        assert_eq!(ed.cursor_col, 8);
+    }
+
+    #[test]
+    fn visual_paragraph_switches_to_linewise() {
+        let mut ed = test_editor("a\nb\n\nc\n");
+        ed.enter_visual_mode(false);
+        ed.select_text_object(TextObject::from_key('p', true).unwrap(), 1);
+        assert_eq!(ed.mode, Mode::VisualLine);
+        assert_eq!(ed.cursor_row, 1);
+    }
+
+    #[test]
+    fn swap_ends() {
+        let mut ed = test_editor("abc\n");
+        ed.enter_visual_mode(false);
+        ed.apply_motion(Motion::Right, Some(2));
+        ed.visual_swap_ends();
+        assert_eq!((ed.cursor_col, ed.visual_start), (0, (0, 2)));
+    }
+}
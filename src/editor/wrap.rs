@@ -0,0 +1,382 @@
+//! Soft wrapping: how buffer lines are laid out over screen rows, and the
+//! commands that move by screen row (`gj`, `gk`, `g0`, `g$`). Rows are
+//! measured in screen cells, so wide characters take two.
+
+use unicode_width::UnicodeWidthChar;
+
+use super::Editor;
+
+/// One screen row of a buffer line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayRow {
+    /// The first column of the line shown on the row.
+    pub start: usize,
+    /// The column just past the last one shown.
+    pub end: usize,
+    /// How many cells of 'breakindent' indent come before the text.
+    pub indent: usize,
+    /// Whether 'showbreak' is drawn after the indent.
+    pub showbreak: bool,
+}
+
+impl Editor {
+    /// How buffer line `row` is laid out over screen rows. Without 'wrap',
+    /// that's a single row of the columns from 'leftcol' that fit.
+    pub fn display_rows(&self, row: usize) -> Vec<DisplayRow> {
+        let chars: Vec<char> = self.buffer.line(row).unwrap_or_default().chars().collect();
+        let width = self.text_width;
+        if !self.window_options.wrap && width > 0 {
+            let start = self.left_col.min(chars.len());
+            return vec![DisplayRow {
+                start,
+                end: fitting(&chars, start, width),
+                indent: 0,
+                showbreak: false,
+            }];
+        }
+        if width == 0 || cells(&chars) <= width {
+            return vec![DisplayRow {
+                start: 0,
+                end: chars.len(),
+                indent: 0,
+                showbreak: false,
+            }];
+        }
+        let showbreak_width = self.global_options.showbreak.chars().count();
+        let mut indent = if self.window_options.breakindent {
+            chars.iter().take_while(|c| is_blank(**c)).count()
+        } else {
+            0
+        };
+        // Every row keeps at least one cell for text.
+        if indent + showbreak_width >= width {
+            indent = 0;
+        }
+        let showbreak = showbreak_width > 0 && indent + showbreak_width < width;
+        let continued_width = width - indent - if showbreak { showbreak_width } else { 0 };
+
+        let mut rows = Vec::new();
+        let mut start = 0;
+        loop {
+            let first = rows.is_empty();
+            let room = if first { width } else { continued_width };
+            let mut end = fitting(&chars, start, room);
+            if end < chars.len() && self.window_options.linebreak {
+                // Break after the last blank that fits, if there is one.
+                if let Some(after_blank) = (start + 1..=end).rev().find(|&i| is_blank(chars[i - 1]))
+                {
+                    end = after_blank;
+                }
+            }
+            rows.push(DisplayRow {
+                start,
+                end,
+                indent: if first { 0 } else { indent },
+                showbreak: !first && showbreak,
+            });
+            if end >= chars.len() {
+                return rows;
+            }
+            start = end;
+        }
+    }
+
+    /// How many screen rows buffer line `row` takes.
+    pub(super) fn line_rows(&self, row: usize) -> usize {
+        self.display_rows(row).len()
+    }
+
+    /// The cells before the text on `row`: its indent and 'showbreak'.
+    pub fn display_prefix_width(&self, row: &DisplayRow) -> usize {
+        let showbreak = if row.showbreak {
+            self.global_options.showbreak.chars().count()
+        } else {
+            0
+        };
+        row.indent + showbreak
+    }
+
+    /// The screen column of column `col` of line `line`, shown on `row`.
+    pub(super) fn display_x(&self, line: usize, row: &DisplayRow, col: usize) -> usize {
+        let chars = self.buffer.line(line).unwrap_or_default();
+        let before = chars.chars().take(col).skip(row.start);
+        self.display_prefix_width(row) + before.map(cell_width).sum::<usize>()
+    }
+
+    /// The column of line `line` shown at screen column `x` on `row`, or
+    /// the one after the row's text if `x` is past it.
+    pub(super) fn display_col(&self, line: usize, row: &DisplayRow, x: usize) -> usize {
+        let chars = self.buffer.line(line).unwrap_or_default();
+        let mut left = x.saturating_sub(self.display_prefix_width(row));
+        let mut col = row.start;
+        for c in chars.chars().skip(row.start).take(row.end - row.start) {
+            let width = cell_width(c);
+            if left < width {
+                break;
+            }
+            left -= width;
+            col += 1;
+        }
+        col + if col == row.end { left } else { 0 }
+    }
+
+    /// The cursor's screen row within its line, and its screen column.
+    pub fn cursor_display_pos(&self) -> (usize, usize) {
+        let rows = self.display_rows(self.cursor_row);
+        let index = display_row_index(&rows, self.cursor_col);
+        let row = &rows[index];
+        let x = self.display_x(self.cursor_row, row, self.cursor_col.max(row.start));
+        if self.window_options.wrap && self.text_width > 0 {
+            // Appending at the end of a full row stays on that row.
+            return (index, x.min(self.text_width - 1));
+        }
+        (index, x)
+    }
+
+    /// The buffer lines that fit wholly on screen, from the top of the
+    /// window, with the number of screen rows each takes. The top line is
+    /// always there, even when it doesn't fit.
+    pub(super) fn visible_lines(&self, viewport_height: usize) -> Vec<(usize, usize)> {
+        let mut lines = Vec::new();
+        let mut used = 0;
+        for row in self.scroll_offset..=self.max_row() {
+            let rows = self.line_rows(row);
+            if used + rows > viewport_height && !lines.is_empty() {
+                break;
+            }
+            lines.push((row, rows));
+            used += rows;
+        }
+        lines
+    }
+
+    /// `gj` and `gk`: move one screen row down or up, keeping the cursor's
+    /// screen column where the row is long enough. Without 'wrap' every
+    /// line is one row, so that's the column in the line, wherever
+    /// 'leftcol' has scrolled to.
+    pub fn move_display_row(&mut self, down: bool) {
+        let wrap = self.window_options.wrap;
+        let rows = self.display_rows(self.cursor_row);
+        let index = display_row_index(&rows, self.cursor_col);
+        let x = if wrap {
+            self.display_x(self.cursor_row, &rows[index], self.cursor_col)
+        } else {
+            self.cursor_col
+        };
+        let (row, target) = if down && index + 1 < rows.len() {
+            (self.cursor_row, rows[index + 1])
+        } else if down && self.cursor_row < self.max_row() {
+            let row = self.cursor_row + 1;
+            (row, self.display_rows(row)[0])
+        } else if !down && index > 0 {
+            (self.cursor_row, rows[index - 1])
+        } else if !down && self.cursor_row > 0 {
+            let row = self.cursor_row - 1;
+            (row, *self.display_rows(row).last().unwrap())
+        } else {
+            return;
+        };
+        self.cursor_row = row;
+        if wrap {
+            let last_on_row = target.end.saturating_sub(1).max(target.start);
+            self.cursor_col = self.display_col(row, &target, x).min(last_on_row);
+        } else {
+            self.cursor_col = x;
+        }
+        self.clamp_cursor_col();
+    }
+
+    /// `g0`: to the first character on the cursor's screen row.
+    pub fn goto_display_row_start(&mut self) {
+        let rows = self.display_rows(self.cursor_row);
+        self.cursor_col = rows[display_row_index(&rows, self.cursor_col)].start;
+        self.clamp_cursor_col();
+    }
+
+    /// `g$`: to the last character on the cursor's screen row.
+    pub fn goto_display_row_end(&mut self) {
+        let rows = self.display_rows(self.cursor_row);
+        let row = rows[display_row_index(&rows, self.cursor_col)];
+        self.cursor_col = row.end.saturating_sub(1).max(row.start);
+        self.clamp_cursor_col();
+    }
+}
+
+/// Which of a line's `rows` shows column `col`. A column past the end of
+/// the line is on the last row.
+fn display_row_index(rows: &[DisplayRow], col: usize) -> usize {
+    rows.iter()
+        .position(|row| col < row.end)
+        .unwrap_or(rows.len() - 1)
+}
+
+/// The screen cells `c` takes up. Control characters aren't drawn.
+pub fn cell_width(c: char) -> usize {
+    c.width().unwrap_or(0)
+}
+
+fn cells(chars: &[char]) -> usize {
+    chars.iter().copied().map(cell_width).sum()
+}
+
+/// The end of the run of `chars` from `start` that fits in `room` cells.
+/// At least one character goes on a row, however wide.
+fn fitting(chars: &[char], start: usize, room: usize) -> usize {
+    let mut used = 0;
+    let mut end = start;
+    while end < chars.len() {
+        used += cell_width(chars[end]);
+        if used > room && end > start {
+            break;
+        }
+        end += 1;
+    }
+    end
+}
+
+fn is_blank(c: char) -> bool {
+    c == ' ' || c == '\t'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Editor, test_editor};
+    use super::DisplayRow;
+
+    fn spans(ed: &Editor, row: usize) -> Vec<(usize, usize)> {
+        ed.display_rows(row)
+            .iter()
+            .map(|r| (r.start, r.end))
+            .collect()
+    }
+
+    #[test]
+    fn long_lines_wrap_at_the_window_width() {
+        let mut ed = test_editor("abcdefghij\nabc\n");
+        assert_eq!(spans(&ed, 0), [(0, 10)]);
+        ed.text_width = 4;
+        assert_eq!(spans(&ed, 0), [(0, 4), (4, 8), (8, 10)]);
+        assert_eq!(spans(&ed, 1), [(0, 3)]);
+        ed.window_options.wrap = false;
+        assert_eq!(spans(&ed, 0), [(0, 4)]);
+        ed.left_col = 8;
+        assert_eq!(spans(&ed, 0), [(8, 10)]);
+        assert_eq!(spans(&ed, 1), [(3, 3)]);
+    }
+
+    #[test]
+    fn linebreak_breaks_after_blanks() {
+        let mut ed = test_editor("one two three\n");
+        ed.text_width = 9;
+        ed.window_options.linebreak = true;
+        assert_eq!(spans(&ed, 0), [(0, 8), (8, 13)]);
+        ed.window_options.linebreak = false;
+        assert_eq!(spans(&ed, 0), [(0, 9), (9, 13)]);
+    }
+
+    #[test]
+    fn showbreak_and_breakindent_narrow_continued_rows() {
+        let mut ed = test_editor("  abcdefghij\n");
+        ed.text_width = 6;
+        ed.global_options.showbreak = "> ".to_string();
+        ed.window_options.breakindent = true;
+        let rows = ed.display_rows(0);
+        assert_eq!(
+            rows[1],
+            DisplayRow {
+                start: 6,
+                end: 8,
+                indent: 2,
+                showbreak: true
+            }
+        );
+        assert_eq!(ed.display_prefix_width(&rows[1]), 4);
+        assert_eq!(rows.len(), 4);
+    }
+
+    #[test]
+    fn wide_characters_wrap_by_cells() {
+        let mut ed = test_editor("日本語テキスト\nab日c\n");
+        ed.text_width = 5;
+        assert_eq!(spans(&ed, 0), [(0, 2), (2, 4), (4, 6), (6, 7)]);
+        ed.text_width = 4;
+        assert_eq!(spans(&ed, 1), [(0, 3), (3, 4)]);
+        ed.text_width = 5;
+        ed.cursor_col = 3;
+        assert_eq!(ed.cursor_display_pos(), (1, 2));
+        ed.move_display_row(true);
+        assert_eq!(ed.cursor_col, 5);
+    }
+
+    #[test]
+    fn nowrap_scrolls_wide_characters_into_view() {
+        let mut ed = test_editor("日本語テキスト\n");
+        ed.text_width = 5;
+        ed.window_options.wrap = false;
+        ed.global_options.sidescroll = 1;
+        ed.cursor_col = 3;
+        ed.adjust_left_col();
+        assert_eq!(ed.left_col, 2);
+        assert_eq!(ed.cursor_display_pos(), (0, 2));
+        assert_eq!(spans(&ed, 0), [(2, 4)]);
+    }
+
+    #[test]
+    fn gj_and_gk_move_by_screen_row() {
+        let mut ed = test_editor("abcdefghij\nxy\n");
+        ed.text_width = 4;
+        ed.cursor_col = 1;
+        ed.move_display_row(true);
+        assert_eq!((ed.cursor_row, ed.cursor_col), (0, 5));
+        ed.move_display_row(true);
+        ed.move_display_row(true);
+        assert_eq!((ed.cursor_row, ed.cursor_col), (1, 1));
+        ed.move_display_row(false);
+        assert_eq!((ed.cursor_row, ed.cursor_col), (0, 9));
+    }
+
+    #[test]
+    fn gj_without_wrap_keeps_the_line_column() {
+        let mut ed = test_editor("abcdefghij\nabcdefghij\n");
+        ed.text_width = 4;
+        ed.window_options.wrap = false;
+        ed.left_col = 6;
+        ed.cursor_col = 0;
+        ed.move_display_row(true);
+        assert_eq!((ed.cursor_row, ed.cursor_col), (1, 0));
+        ed.cursor_col = 8;
+        ed.move_display_row(false);
+        assert_eq!((ed.cursor_row, ed.cursor_col), (0, 8));
+    }
+
+    #[test]
+    fn g0_and_g_dollar_stay_on_the_screen_row() {
+        let mut ed = test_editor("abcdefghij\n");
+        ed.text_width = 4;
+        ed.cursor_col = 5;
+        ed.goto_display_row_end();
+        assert_eq!(ed.cursor_col, 7);
+        ed.goto_display_row_start();
+        assert_eq!(ed.cursor_col, 4);
+        ed.window_options.wrap = false;
+        ed.goto_display_row_end();
+        assert_eq!(ed.cursor_col, 3);
+    }
+
+    #[test]
+    fn scrolling_counts_screen_rows() {
+        let mut ed = test_editor("abcdefghij\nb\nc\nd\n");
+        ed.text_width = 4;
+        ed.cursor_row = 1;
+        ed.adjust_scroll(4);
+        assert_eq!(ed.scroll_offset, 0);
+        ed.cursor_row = 3;
+        ed.adjust_scroll(4);
+        assert_eq!(ed.scroll_offset, 1);
+        ed.scroll_offset = 0;
+        ed.goto_viewport_bottom(4);
+        assert_eq!(ed.cursor_row, 1);
+        ed.goto_viewport_middle(4);
+        assert_eq!(ed.cursor_row, 0);
+    }
+}
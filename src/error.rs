@@ -12,4 +12,28 @@ pub enum DvimError {
         path: String,
         source: std::io::Error,
     },
+    #[error("'{path}' changed on disk since it was read (add ! to override)")]
+    FileChanged { path: String },
+    #[error("no write since last change (add ! to override)")]
+    UnsavedChanges,
+    #[error("invalid value for '{name}': {value}")]
+    InvalidOption { name: String, value: String },
+    #[error("unknown option: {0}")]
+    UnknownOption(String),
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error("no such mapping: {0}")]
+    NoSuchMapping(String),
+    #[error("recursive mapping")]
+    RecursiveMapping,
+    #[error("stopped after {0} keys from a macro")]
+    TooManyKeys(usize),
+    #[error("unknown action: {0}")]
+    UnknownAction(String),
+    #[error("cannot find color scheme '{0}'")]
+    UnknownColorscheme(String),
+    #[error("highlight group not found: {0}")]
+    UnknownGroup(String),
+    #[error("error in '{path}': {message}")]
+    Config { path: String, message: String },
 }
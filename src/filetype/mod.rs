@@ -0,0 +1,191 @@
+use std::path::Path;
+
+/// vim's default 'iskeyword': letters, digits, underscore and Latin-1 letters.
+pub const DEFAULT_ISKEYWORD: &str = "@,48-57,_,192-255";
+
+/// Guess a vim-style filetype name ("rust", "css", "sh", ...) from a path.
+/// Files we don't recognise are plain "text".
+pub fn detect(path: &Path) -> &'static str {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    match name {
+        "Makefile" | "makefile" | "GNUmakefile" => return "make",
+        ".bashrc" | ".bash_profile" | ".profile" | ".zshrc" => return "sh",
+        _ => {}
+    }
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    match ext {
+        "rs" => "rust",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hh" | "hpp" => "cpp",
+        "go" => "go",
+        "java" => "java",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" => "typescript",
+        "py" => "python",
+        "css" => "css",
+        "scss" => "scss",
+        "less" => "less",
+        "html" | "htm" => "html",
+        "xml" => "xml",
+        "lisp" | "cl" | "el" => "lisp",
+        "scm" | "ss" => "scheme",
+        "clj" | "cljs" | "edn" => "clojure",
+        "sh" | "bash" | "zsh" => "sh",
+        "toml" => "toml",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "md" | "markdown" => "markdown",
+        _ => "text",
+    }
+}
+
+/// The 'iskeyword' a filetype starts with. Lisps and stylesheets use `-`
+/// inside identifiers, and shell scripts want `$VAR` to be one word.
+pub fn default_iskeyword(filetype: &str) -> &'static str {
+    match filetype {
+        "css" | "scss" | "less" | "lisp" | "scheme" | "clojure" => "@,48-57,_,192-255,-",
+        "sh" => "@,48-57,_,192-255,$",
+        _ => DEFAULT_ISKEYWORD,
+    }
+}
+
+/// How a filetype is indented: the 'shiftwidth', 'expandtab' and
+/// 'indentexpr' it starts with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndentStyle {
+    pub shiftwidth: usize,
+    pub expandtab: bool,
+    /// The indenter for new lines and `=`, empty to just copy the line above.
+    pub indentexpr: &'static str,
+}
+
+pub fn indent_style(filetype: &str) -> IndentStyle {
+    let indentexpr = match filetype {
+        "rust" | "c" | "cpp" | "java" | "go" | "javascript" | "typescript" | "css" | "scss"
+        | "less" | "json" => "brackets",
+        "python" | "yaml" => "smart",
+        _ => "",
+    };
+    let (shiftwidth, expandtab) = match filetype {
+        "go" | "make" => (8, false),
+        "javascript" | "typescript" | "json" | "yaml" | "html" | "xml" | "css" | "scss"
+        | "less" | "lisp" | "scheme" | "clojure" => (2, true),
+        _ => (4, true),
+    };
+    IndentStyle {
+        shiftwidth,
+        expandtab,
+        indentexpr,
+    }
+}
+
+/// The 'autopairs' a filetype starts with: brackets, and the quotes its
+/// strings use. Rust's `'` is left out, being mostly lifetimes.
+pub fn auto_pairs(filetype: &str) -> String {
+    let mut spec = String::from("(:),[:],{:}");
+    for q in syntax(filetype).quotes {
+        spec.push_str(&format!(",{q}:{q}"));
+    }
+    spec
+}
+
+/// How a filetype writes comments and string literals, so bracket matching
+/// can skip brackets that aren't code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Syntax {
+    pub line_comment: Option<&'static str>,
+    pub block_comment: Option<(&'static str, &'static str)>,
+    /// Characters that open (and close) a string literal.
+    pub quotes: &'static [char],
+    /// Rust-style `'x'` char literals, where a lone `'` is a lifetime.
+    pub char_literals: bool,
+}
+
+const NO_SYNTAX: Syntax = Syntax {
+    line_comment: None,
+    block_comment: None,
+    quotes: &[],
+    char_literals: false,
+};
+
+pub fn syntax(filetype: &str) -> Syntax {
+    let c_block = Some(("/*", "*/"));
+    match filetype {
+        "rust" => Syntax {
+            line_comment: Some("//"),
+            block_comment: c_block,
+            quotes: &['"'],
+            char_literals: true,
+        },
+        "c" | "cpp" | "java" => Syntax {
+            line_comment: Some("//"),
+            block_comment: c_block,
+            quotes: &['"', '\''],
+            char_literals: false,
+        },
+        "go" | "javascript" | "typescript" => Syntax {
+            line_comment: Some("//"),
+            block_comment: c_block,
+            quotes: &['"', '\'', '`'],
+            char_literals: false,
+        },
+        "css" | "scss" | "less" => Syntax {
+            line_comment: None,
+            block_comment: c_block,
+            quotes: &['"', '\''],
+            char_literals: false,
+        },
+        "python" | "sh" | "make" | "toml" | "yaml" => Syntax {
+            line_comment: Some("#"),
+            block_comment: None,
+            quotes: &['"', '\''],
+            char_literals: false,
+        },
+        "lisp" | "scheme" | "clojure" => Syntax {
+            line_comment: Some(";"),
+            block_comment: None,
+            quotes: &['"'],
+            char_literals: false,
+        },
+        "html" | "xml" => Syntax {
+            block_comment: Some(("<!--", "-->")),
+            ..NO_SYNTAX
+        },
+        "json" => Syntax {
+            quotes: &['"'],
+            ..NO_SYNTAX
+        },
+        _ => NO_SYNTAX,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_by_extension() {
+        assert_eq!(detect(Path::new("src/main.rs")), "rust");
+        assert_eq!(detect(Path::new("style.css")), "css");
+        assert_eq!(detect(Path::new("build.sh")), "sh");
+    }
+
+    #[test]
+    fn detect_by_name() {
+        assert_eq!(detect(Path::new("/home/me/.bashrc")), "sh");
+        assert_eq!(detect(Path::new("Makefile")), "make");
+    }
+
+    #[test]
+    fn detect_unknown_is_text() {
+        assert_eq!(detect(Path::new("notes")), "text");
+        assert_eq!(detect(Path::new("data.xyz")), "text");
+    }
+
+    #[test]
+    fn iskeyword_per_filetype() {
+        assert!(default_iskeyword("css").ends_with(",-"));
+        assert!(default_iskeyword("sh").ends_with(",$"));
+        assert_eq!(default_iskeyword("rust"), DEFAULT_ISKEYWORD);
+    }
+}
@@ -1,60 +1,173 @@
 mod buffer;
+mod config;
 mod editor;
 mod error;
+mod filetype;
+mod mapping;
 mod mode;
+mod options;
+mod theme;
 mod ui;
+mod watcher;
 
 use anyhow::Result;
 use clap::Parser;
-use crossterm::event::{self, Event};
+use crossterm::cursor;
+use crossterm::event::{
+    self, DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture, Event,
+};
 use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
 use ratatui::Terminal;
 use ratatui::backend::CrosstermBackend;
 use std::path::PathBuf;
+use std::time::Duration;
+
+/// How long to wait for terminal input before checking for file changes.
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(250);
 
 #[derive(Parser)]
 #[command(name = "dvim", about = "A vim-like text editor")]
 struct Cli {
     /// File to open
     file: PathBuf,
+    /// Don't read the config file
+    #[arg(long)]
+    clean: bool,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
     let buffer = buffer::Buffer::from_file(cli.file)?;
     let mut editor = editor::Editor::new(buffer);
+    if !cli.clean {
+        match config::Config::load() {
+            Ok(config) => editor.load_config(config),
+            Err(e) => editor.status_message = Some(e.to_string()),
+        }
+    }
+    // Change detection still works on focus and before writes without this,
+    // so a watcher that fails to start isn't fatal.
+    let watcher = watcher::FileWatcher::new(editor.buffer.filename()).ok();
 
     // Set up terminal
-    terminal::enable_raw_mode()?;
-    let mut stdout = std::io::stdout();
-    crossterm::execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
+    install_panic_hook();
+    setup_terminal()?;
+    let backend = CrosstermBackend::new(std::io::stdout());
     let mut terminal = Terminal::new(backend)?;
 
     // Main event loop
-    let result = run_loop(&mut terminal, &mut editor);
+    let result = run_loop(&mut terminal, &mut editor, watcher.as_ref());
 
     // Teardown — always runs, even if the loop errored
-    terminal::disable_raw_mode()?;
-    crossterm::execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    restore_terminal()?;
 
     result
 }
 
+fn setup_terminal() -> std::io::Result<()> {
+    terminal::enable_raw_mode()?;
+    crossterm::execute!(std::io::stdout(), EnterAlternateScreen, EnableFocusChange)
+}
+
+/// Put the terminal back the way we found it.
+fn restore_terminal() -> std::io::Result<()> {
+    terminal::disable_raw_mode()?;
+    crossterm::execute!(
+        std::io::stdout(),
+        DisableFocusChange,
+        DisableMouseCapture,
+        LeaveAlternateScreen,
+        cursor::Show
+    )
+}
+
+/// Restore the terminal before a panic's message is printed, so the
+/// message can be read and the shell is usable afterwards.
+fn install_panic_hook() {
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        hook(info);
+    }));
+}
+
+/// Stop the process the way the shell's `Ctrl-z` would, with the terminal
+/// restored until it's continued.
+#[cfg(unix)]
+fn suspend(terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>) -> Result<()> {
+    restore_terminal()?;
+    // SAFETY: raise has no preconditions; it returns once SIGCONT arrives.
+    unsafe {
+        libc::raise(libc::SIGTSTP);
+    }
+    setup_terminal()?;
+    // Whatever ran in the meantime left the screen in an unknown state.
+    terminal.clear()?;
+    Ok(())
+}
+
+/// There's no job control to hand the terminal back to elsewhere.
+#[cfg(not(unix))]
+fn suspend(_terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>) -> Result<()> {
+    Ok(())
+}
+
 fn run_loop(
     terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
     editor: &mut editor::Editor,
+    watcher: Option<&watcher::FileWatcher>,
 ) -> Result<()> {
+    // Whether mouse events are being reported, to follow changes to 'mouse'.
+    let mut mouse_captured = false;
     while editor.running {
-        let viewport_height = terminal.size()?.height.saturating_sub(1) as usize;
+        let mouse = !editor.global_options.mouse.is_empty();
+        if mouse && !mouse_captured {
+            crossterm::execute!(terminal.backend_mut(), EnableMouseCapture)?;
+        } else if !mouse && mouse_captured {
+            crossterm::execute!(terminal.backend_mut(), DisableMouseCapture)?;
+        }
+        mouse_captured = mouse;
+
+        let size = terminal.size()?;
+        let viewport_height = size.height.saturating_sub(1) as usize;
+        editor.text_width = ui::text_width(editor, size.width, viewport_height);
         editor.adjust_scroll(viewport_height);
 
         terminal.draw(|frame| {
             ui::draw(frame, editor);
         })?;
 
-        if let Event::Key(key) = event::read()? {
-            editor::handle_key(editor, key, viewport_height)?;
+        // Keys that could start a mapping, and built-in prefixes like `g`,
+        // only wait 'timeoutlen' for the rest of the command.
+        let key_timeout = editor.key_timeout_left();
+        let timeout = key_timeout.map_or(WATCH_POLL_INTERVAL, |t| t.min(WATCH_POLL_INTERVAL));
+        if event::poll(timeout)? {
+            match event::read()? {
+                Event::Key(key) => editor::handle_key(editor, key, viewport_height)?,
+                Event::Mouse(mouse) => {
+                    let text_left = (size.width as usize).saturating_sub(editor.text_width);
+                    editor.handle_mouse(mouse, text_left, viewport_height);
+                }
+                Event::FocusGained => editor.check_file_changed(),
+                Event::FocusLost => editor.focus_lost(),
+                // The next pass lays out and draws at the new size, keeping
+                // the cursor in view.
+                Event::Resize(..) => {}
+                _ => {}
+            }
+        } else if editor.key_timeout_left() == Some(Duration::ZERO) {
+            editor.flush_pending_keys(viewport_height);
+        }
+
+        if editor.suspend {
+            editor.suspend = false;
+            suspend(terminal)?;
+            // Coming back sets the terminal up without mouse reporting.
+            mouse_captured = false;
+        }
+
+        if watcher.is_some_and(|w| w.has_changed()) {
+            editor.check_file_changed();
         }
     }
     Ok(())
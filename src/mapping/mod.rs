@@ -0,0 +1,315 @@
+//! Key mappings: one trie of mappings per mode, keyed by the keys of each
+//! mapping's left-hand side, and the built-in actions a mapping can run by
+//! name.
+
+use std::collections::HashMap;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// The modes mappings are defined for. Replace mode uses the insert-mode
+/// mappings and visual-line mode the visual ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapMode {
+    Normal,
+    Visual,
+    Insert,
+    Command,
+}
+
+impl MapMode {
+    pub const ALL: [MapMode; 4] = [
+        MapMode::Normal,
+        MapMode::Visual,
+        MapMode::Insert,
+        MapMode::Command,
+    ];
+
+    /// The mode called `name` in config.toml's `[keymap.<name>]` tables.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "normal" => Some(MapMode::Normal),
+            "visual" => Some(MapMode::Visual),
+            "insert" => Some(MapMode::Insert),
+            "command" => Some(MapMode::Command),
+            _ => None,
+        }
+    }
+
+    /// The letter `:map` listings show the mode as, which is also the
+    /// prefix of its commands (`nmap`, `imap`).
+    pub fn letter(self) -> char {
+        match self {
+            MapMode::Normal => 'n',
+            MapMode::Visual => 'v',
+            MapMode::Insert => 'i',
+            MapMode::Command => 'c',
+        }
+    }
+}
+
+/// A built-in command a mapping can run by name, as `<Action>(name)`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Action {
+    pub name: &'static str,
+    /// The normal-mode keys that carry it out, in key notation.
+    pub keys: &'static str,
+}
+
+const fn action(name: &'static str, keys: &'static str) -> Action {
+    Action { name, keys }
+}
+
+/// Every action, in alphabetical order.
+pub const ACTIONS: &[Action] = &[
+    action("append", "a"),
+    action("bottom", "G"),
+    action("command-line", ":"),
+    action("dedent-line", "<lt><lt>"),
+    action("delete-char", "x"),
+    action("delete-line", "dd"),
+    action("delete-to-end", "D"),
+    action("first-non-blank", "^"),
+    action("half-page-down", "<C-d>"),
+    action("half-page-up", "<C-u>"),
+    action("indent-line", ">>"),
+    action("insert", "i"),
+    action("join-lines", "J"),
+    action("jump-newer", "<Tab>"),
+    action("jump-older", "<C-o>"),
+    action("line-end", "$"),
+    action("line-start", "0"),
+    action("match-bracket", "%"),
+    action("open-above", "O"),
+    action("open-below", "o"),
+    action("put-after", "p"),
+    action("put-before", "P"),
+    action("quit", ":q<CR>"),
+    action("reindent-line", "=="),
+    action("reload", ":e<CR>"),
+    action("repeat-change", "."),
+    action("replace-mode", "R"),
+    action("toggle-case", "~"),
+    action("top", "gg"),
+    action("visual", "v"),
+    action("visual-line", "V"),
+    action("word-backward", "b"),
+    action("word-end", "e"),
+    action("word-forward", "w"),
+    action("write", ":w<CR>"),
+    action("write-quit", ":wq<CR>"),
+    action("yank-line", "yy"),
+];
+
+pub fn find_action(name: &str) -> Option<&'static Action> {
+    ACTIONS.iter().find(|action| action.name == name)
+}
+
+/// What a mapping does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rhs {
+    /// Run these keys as if typed.
+    Keys(Vec<KeyEvent>),
+    Action(&'static Action),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mapping {
+    pub rhs: Rhs,
+    /// Whether the keys of `rhs` are run as they are, without applying
+    /// mappings to them (`noremap`).
+    pub noremap: bool,
+}
+
+/// A key as the trie stores it. Shift is left out for characters, since
+/// the character already says whether it was shifted.
+type Key = (KeyCode, KeyModifiers);
+
+fn trie_key(key: &KeyEvent) -> Key {
+    let mut modifiers = key.modifiers;
+    if let KeyCode::Char(_) = key.code {
+        modifiers.remove(KeyModifiers::SHIFT);
+    }
+    (key.code, modifiers)
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    mapping: Option<Mapping>,
+    children: HashMap<Key, Node>,
+}
+
+/// What the keys typed so far match.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Lookup<'a> {
+    /// The mapping whose left-hand side is exactly these keys.
+    pub mapping: Option<&'a Mapping>,
+    /// Whether longer mappings start with these keys.
+    pub longer: bool,
+}
+
+/// The mappings for one mode.
+#[derive(Debug, Default)]
+pub struct Keymap {
+    root: Node,
+}
+
+impl Keymap {
+    /// Map `lhs` to `mapping`, replacing any mapping it had.
+    pub fn insert(&mut self, lhs: &[KeyEvent], mapping: Mapping) {
+        let node = lhs.iter().fold(&mut self.root, |node, key| {
+            node.children.entry(trie_key(key)).or_default()
+        });
+        node.mapping = Some(mapping);
+    }
+
+    /// Remove the mapping for `lhs`. Returns false if there wasn't one.
+    pub fn remove(&mut self, lhs: &[KeyEvent]) -> bool {
+        fn remove_from(node: &mut Node, lhs: &[KeyEvent]) -> bool {
+            let Some((first, rest)) = lhs.split_first() else {
+                return node.mapping.take().is_some();
+            };
+            let key = trie_key(first);
+            let Some(child) = node.children.get_mut(&key) else {
+                return false;
+            };
+            let removed = remove_from(child, rest);
+            if child.mapping.is_none() && child.children.is_empty() {
+                node.children.remove(&key);
+            }
+            removed
+        }
+        !lhs.is_empty() && remove_from(&mut self.root, lhs)
+    }
+
+    pub fn lookup(&self, keys: &[KeyEvent]) -> Lookup<'_> {
+        let mut node = &self.root;
+        for key in keys {
+            match node.children.get(&trie_key(key)) {
+                Some(child) => node = child,
+                None => {
+                    return Lookup {
+                        mapping: None,
+                        longer: false,
+                    };
+                }
+            }
+        }
+        Lookup {
+            mapping: node.mapping.as_ref(),
+            longer: !node.children.is_empty(),
+        }
+    }
+
+    pub fn get(&self, lhs: &[KeyEvent]) -> Option<&Mapping> {
+        self.lookup(lhs).mapping
+    }
+
+    /// Every mapping, with its left-hand side, in no particular order.
+    pub fn mappings(&self) -> Vec<(Vec<KeyEvent>, &Mapping)> {
+        fn collect<'a>(
+            node: &'a Node,
+            lhs: &mut Vec<KeyEvent>,
+            out: &mut Vec<(Vec<KeyEvent>, &'a Mapping)>,
+        ) {
+            if let Some(mapping) = &node.mapping {
+                out.push((lhs.clone(), mapping));
+            }
+            for (&(code, modifiers), child) in &node.children {
+                lhs.push(KeyEvent::new(code, modifiers));
+                collect(child, lhs, out);
+                lhs.pop();
+            }
+        }
+        let mut out = Vec::new();
+        collect(&self.root, &mut Vec::new(), &mut out);
+        out
+    }
+}
+
+/// The mappings for every mode.
+#[derive(Debug, Default)]
+pub struct Keymaps {
+    normal: Keymap,
+    visual: Keymap,
+    insert: Keymap,
+    command: Keymap,
+}
+
+impl Keymaps {
+    pub fn get(&self, mode: MapMode) -> &Keymap {
+        match mode {
+            MapMode::Normal => &self.normal,
+            MapMode::Visual => &self.visual,
+            MapMode::Insert => &self.insert,
+            MapMode::Command => &self.command,
+        }
+    }
+
+    pub fn get_mut(&mut self, mode: MapMode) -> &mut Keymap {
+        match mode {
+            MapMode::Normal => &mut self.normal,
+            MapMode::Visual => &mut self.visual,
+            MapMode::Insert => &mut self.insert,
+            MapMode::Command => &mut self.command,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(text: &str) -> Vec<KeyEvent> {
+        text.chars()
+            .map(|c| KeyEvent::from(KeyCode::Char(c)))
+            .collect()
+    }
+
+    fn mapping(text: &str) -> Mapping {
+        Mapping {
+            rhs: Rhs::Keys(keys(text)),
+            noremap: true,
+        }
+    }
+
+    #[test]
+    fn lookup_finds_mappings_and_prefixes() {
+        let mut keymap = Keymap::default();
+        keymap.insert(&keys("a"), mapping("x"));
+        keymap.insert(&keys("ab"), mapping("y"));
+        let a = keymap.lookup(&keys("a"));
+        assert_eq!(a.mapping, Some(&mapping("x")));
+        assert!(a.longer);
+        let ab = keymap.lookup(&keys("ab"));
+        assert_eq!(ab.mapping, Some(&mapping("y")));
+        assert!(!ab.longer);
+        assert_eq!(keymap.lookup(&keys("b")).mapping, None);
+    }
+
+    #[test]
+    fn shift_is_ignored_for_characters() {
+        let mut keymap = Keymap::default();
+        keymap.insert(&keys("Q"), mapping("x"));
+        let typed = KeyEvent::new(KeyCode::Char('Q'), KeyModifiers::SHIFT);
+        assert!(keymap.get(&[typed]).is_some());
+    }
+
+    #[test]
+    fn remove_prunes_the_trie() {
+        let mut keymap = Keymap::default();
+        keymap.insert(&keys("ab"), mapping("y"));
+        assert!(!keymap.remove(&keys("a")));
+        assert!(keymap.remove(&keys("ab")));
+        assert!(!keymap.lookup(&keys("a")).longer);
+        assert!(keymap.mappings().is_empty());
+    }
+
+    #[test]
+    fn actions_are_sorted_and_unique() {
+        let names: Vec<&str> = ACTIONS.iter().map(|a| a.name).collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(names, sorted);
+    }
+}
@@ -6,7 +6,25 @@ use std::fmt;
 pub enum Mode {
     Normal,
     Insert,
+    /// Overtyping existing text (`R`).
+    Replace,
     Command,
+    /// Characterwise visual selection (`v`).
+    Visual,
+    /// Linewise visual selection (`V`).
+    VisualLine,
+}
+
+impl Mode {
+    /// Whether typed characters go into the text, letting the cursor sit
+    /// just past the end of the line.
+    pub fn is_insert(self) -> bool {
+        matches!(self, Mode::Insert | Mode::Replace)
+    }
+
+    pub fn is_visual(self) -> bool {
+        matches!(self, Mode::Visual | Mode::VisualLine)
+    }
 }
 
 impl fmt::Display for Mode {
@@ -14,7 +32,10 @@ impl fmt::Display for Mode {
         match self {
             Mode::Normal => write!(f, "NORMAL"),
             Mode::Insert => write!(f, "INSERT"),
+            Mode::Replace => write!(f, "REPLACE"),
             Mode::Command => write!(f, "COMMAND"),
+            Mode::Visual => write!(f, "VISUAL"),
+            Mode::VisualLine => write!(f, "V-LINE"),
         }
     }
 }
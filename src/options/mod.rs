@@ -0,0 +1,471 @@
+//! The options registry: every option `:set` and config.toml know about,
+//! with its type and where its value lives. Global options are kept in
+//! [`GlobalOptions`], window-local ones in [`WindowOptions`], and
+//! buffer-local ones on the buffer itself.
+
+use std::fmt;
+
+use crate::error::DvimError;
+
+/// The largest value the column and line count options take, like
+/// 'tabstop' and 'scrolloff'. Anything bigger only overflows.
+pub const MAX_NUMBER: usize = 9999;
+
+/// Where an option's value is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Global,
+    Window,
+    Buffer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Bool,
+    Number,
+    String,
+}
+
+/// The value of an option.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Bool(bool),
+    Number(usize),
+    String(String),
+}
+
+impl Value {
+    pub fn kind(&self) -> Kind {
+        match self {
+            Value::Bool(_) => Kind::Bool,
+            Value::Number(_) => Kind::Number,
+            Value::String(_) => Kind::String,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Number(n) => write!(f, "{n}"),
+            Value::String(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct OptionDef {
+    pub name: &'static str,
+    /// The abbreviation `:set` also takes (`sw` for 'shiftwidth').
+    pub short: Option<&'static str>,
+    pub scope: Scope,
+    pub kind: Kind,
+}
+
+const fn def(name: &'static str, short: &'static str, scope: Scope, kind: Kind) -> OptionDef {
+    OptionDef {
+        name,
+        short: if short.is_empty() { None } else { Some(short) },
+        scope,
+        kind,
+    }
+}
+
+/// Every option, in alphabetical order.
+pub const OPTIONS: &[OptionDef] = &[
+    def("autoindent", "ai", Scope::Buffer, Kind::Bool),
+    def("autopairs", "", Scope::Buffer, Kind::String),
+    def("autoread", "ar", Scope::Global, Kind::Bool),
+    def("autosave", "", Scope::Global, Kind::Bool),
+    def("breakindent", "bri", Scope::Window, Kind::Bool),
+    def("colorcolumn", "cc", Scope::Window, Kind::String),
+    def("cursorcolumn", "cuc", Scope::Window, Kind::Bool),
+    def("cursorline", "cul", Scope::Window, Kind::Bool),
+    def("expandtab", "et", Scope::Buffer, Kind::Bool),
+    def("filetype", "ft", Scope::Buffer, Kind::String),
+    def("foldcolumn", "fdc", Scope::Window, Kind::Number),
+    def("indentexpr", "inde", Scope::Buffer, Kind::String),
+    def("iskeyword", "isk", Scope::Buffer, Kind::String),
+    def("linebreak", "lbr", Scope::Window, Kind::Bool),
+    def("mapleader", "", Scope::Global, Kind::String),
+    def("mouse", "", Scope::Global, Kind::String),
+    def("number", "nu", Scope::Window, Kind::Bool),
+    def("numberwidth", "nuw", Scope::Window, Kind::Number),
+    def("relativenumber", "rnu", Scope::Window, Kind::Bool),
+    def("scroll", "scr", Scope::Window, Kind::Number),
+    def("scrolloff", "so", Scope::Global, Kind::Number),
+    def("shiftwidth", "sw", Scope::Buffer, Kind::Number),
+    def("showbreak", "sbr", Scope::Global, Kind::String),
+    def("showcmd", "sc", Scope::Global, Kind::Bool),
+    def("sidescroll", "ss", Scope::Global, Kind::Number),
+    def("sidescrolloff", "siso", Scope::Global, Kind::Number),
+    def("signcolumn", "scl", Scope::Window, Kind::String),
+    def("smartindent", "si", Scope::Buffer, Kind::Bool),
+    def("tabstop", "ts", Scope::Buffer, Kind::Number),
+    def("timeoutlen", "tm", Scope::Global, Kind::Number),
+    def("ttimeoutlen", "ttm", Scope::Global, Kind::Number),
+    def("wrap", "", Scope::Window, Kind::Bool),
+];
+
+/// The option called `name`, by its full name or abbreviation.
+pub fn find(name: &str) -> Option<&'static OptionDef> {
+    OPTIONS
+        .iter()
+        .find(|def| def.name == name || def.short == Some(name))
+}
+
+impl OptionDef {
+    /// Parse `text` as a value for this option (the part after `=` in
+    /// `:set`).
+    pub fn parse(&self, text: &str) -> Result<Value, DvimError> {
+        let invalid = || DvimError::InvalidOption {
+            name: self.name.to_string(),
+            value: text.to_string(),
+        };
+        match self.kind {
+            Kind::Bool => Err(DvimError::InvalidArgument(format!("{}={text}", self.name))),
+            Kind::Number => text.parse().map(Value::Number).map_err(|_| invalid()),
+            Kind::String => Ok(Value::String(text.to_string())),
+        }
+    }
+
+    /// Check `value` is of this option's kind.
+    pub fn check(&self, value: &Value) -> Result<(), DvimError> {
+        if value.kind() == self.kind {
+            return Ok(());
+        }
+        Err(DvimError::InvalidOption {
+            name: self.name.to_string(),
+            value: value.to_string(),
+        })
+    }
+
+    /// How `:set` shows the option with `value`: `shiftwidth=4`,
+    /// `noautoindent`.
+    pub fn show(&self, value: &Value) -> String {
+        match value {
+            Value::Bool(true) => self.name.to_string(),
+            Value::Bool(false) => format!("no{}", self.name),
+            value => format!("{}={value}", self.name),
+        }
+    }
+}
+
+fn unknown(name: &str) -> DvimError {
+    DvimError::UnknownOption(name.to_string())
+}
+
+/// The values of the global options.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlobalOptions {
+    /// Reload the buffer automatically when it changes on disk and has no
+    /// unsaved edits.
+    pub autoread: bool,
+    /// Write the buffer when the terminal loses focus.
+    pub autosave: bool,
+    /// Show the keys of a partly typed command in the status bar.
+    pub showcmd: bool,
+    /// How many lines to keep between the cursor and the top and bottom
+    /// of the window.
+    pub scrolloff: usize,
+    /// Shown at the start of each screen row a wrapped line continues on.
+    pub showbreak: String,
+    /// How many columns to scroll sideways when the cursor goes off the
+    /// side of the window, or 0 to bring it to the middle.
+    pub sidescroll: usize,
+    /// How many columns to keep between the cursor and the sides of the
+    /// window when scrolling sideways.
+    pub sidescrolloff: usize,
+    /// The modes the mouse works in: any of n(ormal), v(isual), i(nsert)
+    /// and c(ommand), or a for all of them.
+    pub mouse: String,
+    /// What `<leader>` stands for in mappings defined after it's set.
+    pub mapleader: String,
+    /// How many milliseconds to wait, after keys that could be the start
+    /// of a longer mapping or a `g`, `z`, `[`, `]`, `Ctrl-w` or `"`
+    /// command, for the rest of it.
+    pub timeoutlen: usize,
+    /// How long to wait instead when the keys start with `<Esc>`, as
+    /// terminals send Alt chords, so leaving insert mode stays quick.
+    pub ttimeoutlen: usize,
+}
+
+impl Default for GlobalOptions {
+    fn default() -> Self {
+        Self {
+            autoread: false,
+            autosave: false,
+            showcmd: true,
+            scrolloff: 0,
+            showbreak: String::new(),
+            sidescroll: 0,
+            sidescrolloff: 0,
+            mapleader: "\\".to_string(),
+            mouse: String::new(),
+            timeoutlen: 1000,
+            ttimeoutlen: 100,
+        }
+    }
+}
+
+impl GlobalOptions {
+    pub fn get(&self, name: &str) -> Option<Value> {
+        match name {
+            "autoread" => Some(Value::Bool(self.autoread)),
+            "autosave" => Some(Value::Bool(self.autosave)),
+            "mapleader" => Some(Value::String(self.mapleader.clone())),
+            "mouse" => Some(Value::String(self.mouse.clone())),
+            "scrolloff" => Some(Value::Number(self.scrolloff)),
+            "showbreak" => Some(Value::String(self.showbreak.clone())),
+            "showcmd" => Some(Value::Bool(self.showcmd)),
+            "sidescroll" => Some(Value::Number(self.sidescroll)),
+            "sidescrolloff" => Some(Value::Number(self.sidescrolloff)),
+            "timeoutlen" => Some(Value::Number(self.timeoutlen)),
+            "ttimeoutlen" => Some(Value::Number(self.ttimeoutlen)),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, name: &str, value: &Value) -> Result<(), DvimError> {
+        match (name, value) {
+            ("autoread", Value::Bool(b)) => self.autoread = *b,
+            ("autosave", Value::Bool(b)) => self.autosave = *b,
+            ("mapleader", Value::String(s)) => self.mapleader = s.clone(),
+            ("mouse", Value::String(s)) if s.chars().all(|c| "nvica".contains(c)) => {
+                self.mouse = s.clone()
+            }
+            ("scrolloff", Value::Number(n)) if *n <= MAX_NUMBER => self.scrolloff = *n,
+            ("showbreak", Value::String(s)) => self.showbreak = s.clone(),
+            ("showcmd", Value::Bool(b)) => self.showcmd = *b,
+            ("sidescroll", Value::Number(n)) if *n <= MAX_NUMBER => self.sidescroll = *n,
+            ("sidescrolloff", Value::Number(n)) if *n <= MAX_NUMBER => self.sidescrolloff = *n,
+            ("timeoutlen", Value::Number(n)) if *n <= MAX_NUMBER => self.timeoutlen = *n,
+            ("ttimeoutlen", Value::Number(n)) if *n <= MAX_NUMBER => self.ttimeoutlen = *n,
+            (
+                "mouse" | "scrolloff" | "sidescroll" | "sidescrolloff" | "timeoutlen"
+                | "ttimeoutlen",
+                _,
+            ) => {
+                return Err(DvimError::InvalidOption {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                });
+            }
+            _ => return Err(unknown(name)),
+        }
+        Ok(())
+    }
+}
+
+/// The values of the options local to the window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowOptions {
+    /// Show line numbers in the gutter.
+    pub number: bool,
+    /// Show line numbers relative to the cursor line. With 'number' too,
+    /// the cursor line shows its absolute number.
+    pub relativenumber: bool,
+    /// The narrowest the line number column gets, including the space
+    /// after the number.
+    pub numberwidth: usize,
+    /// Highlight the cursor's row.
+    pub cursorline: bool,
+    /// Highlight the cursor's column.
+    pub cursorcolumn: bool,
+    /// The 1-based screen columns to draw rulers at, comma separated.
+    pub colorcolumn: String,
+    /// When to give the gutter a sign column: "yes", "no" or "auto".
+    pub signcolumn: String,
+    /// How wide a fold column to give the gutter.
+    pub foldcolumn: usize,
+    /// How many lines `Ctrl-d` and `Ctrl-u` scroll, or 0 for half the
+    /// window.
+    pub scroll: usize,
+    /// Show lines too long for the window over several screen rows.
+    pub wrap: bool,
+    /// Wrap long lines at a blank rather than the last character that
+    /// fits.
+    pub linebreak: bool,
+    /// Indent the rows a wrapped line continues on as much as the line.
+    pub breakindent: bool,
+}
+
+impl Default for WindowOptions {
+    fn default() -> Self {
+        Self {
+            number: true,
+            relativenumber: false,
+            numberwidth: 4,
+            cursorline: false,
+            cursorcolumn: false,
+            colorcolumn: String::new(),
+            signcolumn: "auto".to_string(),
+            foldcolumn: 0,
+            scroll: 0,
+            wrap: true,
+            linebreak: false,
+            breakindent: false,
+        }
+    }
+}
+
+impl WindowOptions {
+    pub fn get(&self, name: &str) -> Option<Value> {
+        match name {
+            "breakindent" => Some(Value::Bool(self.breakindent)),
+            "colorcolumn" => Some(Value::String(self.colorcolumn.clone())),
+            "cursorcolumn" => Some(Value::Bool(self.cursorcolumn)),
+            "cursorline" => Some(Value::Bool(self.cursorline)),
+            "foldcolumn" => Some(Value::Number(self.foldcolumn)),
+            "linebreak" => Some(Value::Bool(self.linebreak)),
+            "number" => Some(Value::Bool(self.number)),
+            "numberwidth" => Some(Value::Number(self.numberwidth)),
+            "relativenumber" => Some(Value::Bool(self.relativenumber)),
+            "scroll" => Some(Value::Number(self.scroll)),
+            "signcolumn" => Some(Value::String(self.signcolumn.clone())),
+            "wrap" => Some(Value::Bool(self.wrap)),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, name: &str, value: &Value) -> Result<(), DvimError> {
+        let invalid = || DvimError::InvalidOption {
+            name: name.to_string(),
+            value: value.to_string(),
+        };
+        match (name, value) {
+            ("breakindent", Value::Bool(b)) => self.breakindent = *b,
+            ("colorcolumn", Value::String(s)) => {
+                parse_columns(s).ok_or_else(invalid)?;
+                self.colorcolumn = s.clone();
+            }
+            ("cursorcolumn", Value::Bool(b)) => self.cursorcolumn = *b,
+            ("cursorline", Value::Bool(b)) => self.cursorline = *b,
+            ("foldcolumn", Value::Number(n)) if *n <= 12 => self.foldcolumn = *n,
+            ("linebreak", Value::Bool(b)) => self.linebreak = *b,
+            ("number", Value::Bool(b)) => self.number = *b,
+            ("numberwidth", Value::Number(n)) if (1..=20).contains(n) => self.numberwidth = *n,
+            ("relativenumber", Value::Bool(b)) => self.relativenumber = *b,
+            ("scroll", Value::Number(n)) if *n <= MAX_NUMBER => self.scroll = *n,
+            ("signcolumn", Value::String(s)) if matches!(s.as_str(), "yes" | "no" | "auto") => {
+                self.signcolumn = s.clone()
+            }
+            ("wrap", Value::Bool(b)) => self.wrap = *b,
+            ("foldcolumn" | "numberwidth" | "scroll" | "signcolumn", _) => return Err(invalid()),
+            _ => return Err(unknown(name)),
+        }
+        Ok(())
+    }
+
+    /// The 0-based columns 'colorcolumn' draws rulers at.
+    pub fn color_columns(&self) -> Vec<usize> {
+        parse_columns(&self.colorcolumn).unwrap_or_default()
+    }
+}
+
+/// Parse a comma-separated list of 1-based columns into 0-based ones.
+fn parse_columns(text: &str) -> Option<Vec<usize>> {
+    text.split(',')
+        .filter(|part| !part.is_empty())
+        .map(|part| match part.parse::<usize>() {
+            Ok(col) if col > 0 => Some(col - 1),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_by_name_or_abbreviation() {
+        assert_eq!(find("shiftwidth").map(|d| d.name), Some("shiftwidth"));
+        assert_eq!(find("sw").map(|d| d.name), Some("shiftwidth"));
+        assert!(find("nosuch").is_none());
+    }
+
+    #[test]
+    fn options_are_sorted_and_unique() {
+        let names: Vec<&str> = OPTIONS.iter().map(|d| d.name).collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(names, sorted);
+    }
+
+    #[test]
+    fn global_and_window_options_have_values() {
+        for def in OPTIONS {
+            let value = match def.scope {
+                Scope::Global => GlobalOptions::default().get(def.name),
+                Scope::Window => WindowOptions::default().get(def.name),
+                Scope::Buffer => continue,
+            };
+            assert_eq!(value.map(|v| v.kind()), Some(def.kind), "{}", def.name);
+        }
+    }
+
+    #[test]
+    fn parse_by_kind() {
+        let sw = find("sw").unwrap();
+        assert_eq!(sw.parse("2").unwrap(), Value::Number(2));
+        assert!(sw.parse("two").is_err());
+        assert!(find("ai").unwrap().parse("1").is_err());
+        assert_eq!(sw.show(&Value::Number(2)), "shiftwidth=2");
+        assert_eq!(
+            find("ai").unwrap().show(&Value::Bool(false)),
+            "noautoindent"
+        );
+    }
+
+    #[test]
+    fn mouse_takes_mode_flags() {
+        let mut global = GlobalOptions::default();
+        global
+            .set("mouse", &Value::String("nv".to_string()))
+            .unwrap();
+        assert_eq!(global.mouse, "nv");
+        assert!(
+            global
+                .set("mouse", &Value::String("x".to_string()))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn window_options_check_values() {
+        let mut window = WindowOptions::default();
+        let string = |s: &str| Value::String(s.to_string());
+        window.set("colorcolumn", &string("80,100")).unwrap();
+        assert_eq!(window.color_columns(), [79, 99]);
+        assert!(window.set("colorcolumn", &string("0")).is_err());
+        assert!(window.set("colorcolumn", &string("80,x")).is_err());
+        assert_eq!(window.colorcolumn, "80,100");
+        assert!(window.set("numberwidth", &Value::Number(0)).is_err());
+        assert!(window.set("signcolumn", &string("maybe")).is_err());
+        assert!(window.set("foldcolumn", &Value::Number(13)).is_err());
+        assert!(window.set("scroll", &Value::Number(usize::MAX)).is_err());
+    }
+
+    #[test]
+    fn line_and_column_counts_are_bounded() {
+        let mut global = GlobalOptions::default();
+        for name in ["scrolloff", "sidescroll", "sidescrolloff"] {
+            assert!(global.set(name, &Value::Number(MAX_NUMBER)).is_ok());
+            assert!(global.set(name, &Value::Number(MAX_NUMBER + 1)).is_err());
+        }
+        assert_eq!(global.scrolloff, MAX_NUMBER);
+    }
+
+    #[test]
+    fn timeouts_are_bounded() {
+        let mut global = GlobalOptions::default();
+        for name in ["timeoutlen", "ttimeoutlen"] {
+            assert!(global.set(name, &Value::Number(MAX_NUMBER)).is_ok());
+            assert!(global.set(name, &Value::Number(usize::MAX)).is_err());
+        }
+        assert_eq!(global.timeoutlen, MAX_NUMBER);
+    }
+}
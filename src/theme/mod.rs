@@ -0,0 +1,407 @@
+//! Highlight groups and the themes (color schemes) that set them. The UI
+//! looks up each thing it draws by group name. A theme file is
+//! `$XDG_CONFIG_HOME/dvim/colors/<name>.toml`, and gives the groups it
+//! changes from the default theme:
+//!
+//! ```toml
+//! LineNr = { fg = "#5c6370" }
+//! Visual = { bg = "#3e4451" }
+//! ModeInsert = { fg = "black", bg = "green", bold = true }
+//! "@keyword" = { fg = "#c678dd", italic = true }
+//! StatusLineNC = "StatusLine"   # looks like StatusLine
+//! ```
+//!
+//! Colors are names, `#rrggbb` or 256-color indexes. On terminals without
+//! truecolor, `#rrggbb` colors are drawn as the nearest of the 256.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use ratatui::style::{Color, Modifier, Style};
+
+use crate::config::Config;
+use crate::error::DvimError;
+
+/// The name of the built-in theme.
+pub const DEFAULT: &str = "default";
+
+/// Text attributes, by the names themes and `:hi` use.
+const ATTRIBUTES: &[(&str, Modifier)] = &[
+    ("bold", Modifier::BOLD),
+    ("italic", Modifier::ITALIC),
+    ("underline", Modifier::UNDERLINED),
+    ("reverse", Modifier::REVERSED),
+];
+
+/// How a group looks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Highlight {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub modifiers: Modifier,
+    /// Another group to look like instead.
+    pub link: Option<String>,
+}
+
+impl Highlight {
+    fn new(fg: Option<Color>, bg: Option<Color>, modifiers: Modifier) -> Self {
+        Self {
+            fg,
+            bg,
+            modifiers,
+            link: None,
+        }
+    }
+
+    fn linked(to: &str) -> Self {
+        Self {
+            link: Some(to.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// How `:hi` shows it: `fg=#5C6370 bg=Black bold`, `links to Visual`
+    /// or `cleared`.
+    pub fn describe(&self) -> String {
+        if let Some(link) = &self.link {
+            return format!("links to {link}");
+        }
+        let mut parts = Vec::new();
+        if let Some(fg) = self.fg {
+            parts.push(format!("fg={fg}"));
+        }
+        if let Some(bg) = self.bg {
+            parts.push(format!("bg={bg}"));
+        }
+        for &(name, modifier) in ATTRIBUTES {
+            if self.modifiers.contains(modifier) {
+                parts.push(name.to_string());
+            }
+        }
+        if parts.is_empty() {
+            return "cleared".to_string();
+        }
+        parts.join(" ")
+    }
+}
+
+/// Parse a color as a theme or `:hi` gives it.
+pub fn parse_color(text: &str) -> Option<Color> {
+    text.parse().ok()
+}
+
+/// Parse comma-separated attributes (`bold,italic`), or `NONE`.
+pub fn parse_attributes(text: &str) -> Option<Modifier> {
+    if text.eq_ignore_ascii_case("none") {
+        return Some(Modifier::empty());
+    }
+    text.split(',')
+        .try_fold(Modifier::empty(), |modifiers, name| {
+            let &(_, modifier) = ATTRIBUTES.iter().find(|&&(n, _)| n == name)?;
+            Some(modifiers | modifier)
+        })
+}
+
+/// Whether the terminal says it can show `#rrggbb` colors.
+pub fn supports_truecolor() -> bool {
+    std::env::var("COLORTERM").is_ok_and(|v| v == "truecolor" || v == "24bit")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub name: String,
+    groups: HashMap<String, Highlight>,
+    /// Whether `#rrggbb` colors are drawn as they are, rather than as the
+    /// nearest of the 256.
+    pub truecolor: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        let groups = [
+            (
+                "LineNr",
+                Highlight::new(Some(Color::DarkGray), None, Modifier::empty()),
+            ),
+            (
+                "CursorLineNr",
+                Highlight::new(Some(Color::Yellow), None, Modifier::BOLD),
+            ),
+            ("SignColumn", Highlight::linked("LineNr")),
+            ("FoldColumn", Highlight::linked("LineNr")),
+            (
+                "CursorLine",
+                Highlight::new(None, Some(Color::Rgb(48, 48, 48)), Modifier::empty()),
+            ),
+            ("CursorColumn", Highlight::linked("CursorLine")),
+            (
+                "ColorColumn",
+                Highlight::new(None, Some(Color::Rgb(68, 68, 68)), Modifier::empty()),
+            ),
+            (
+                "NonText",
+                Highlight::new(Some(Color::DarkGray), None, Modifier::empty()),
+            ),
+            (
+                "StatusLine",
+                Highlight::new(Some(Color::White), Some(Color::DarkGray), Modifier::empty()),
+            ),
+            (
+                "StatusLineNC",
+                Highlight::new(Some(Color::Gray), Some(Color::DarkGray), Modifier::empty()),
+            ),
+            (
+                "ModeNormal",
+                Highlight::new(Some(Color::Black), Some(Color::Blue), Modifier::BOLD),
+            ),
+            ("ModeInsert", Highlight::linked("ModeNormal")),
+            ("ModeReplace", Highlight::linked("ModeNormal")),
+            ("ModeVisual", Highlight::linked("ModeNormal")),
+            ("ModeCommand", Highlight::linked("StatusLine")),
+            (
+                "MatchParen",
+                Highlight::new(Some(Color::Black), Some(Color::Cyan), Modifier::empty()),
+            ),
+            (
+                "Visual",
+                Highlight::new(Some(Color::Black), Some(Color::Gray), Modifier::empty()),
+            ),
+        ];
+        Self {
+            name: DEFAULT.to_string(),
+            groups: groups
+                .into_iter()
+                .map(|(name, highlight)| (name.to_string(), highlight))
+                .collect(),
+            truecolor: supports_truecolor(),
+        }
+    }
+}
+
+impl Theme {
+    /// Where the theme called `name` lives.
+    pub fn path(name: &str) -> Option<PathBuf> {
+        Some(Config::dir()?.join("colors").join(format!("{name}.toml")))
+    }
+
+    /// Load the theme called `name`: the default, or a theme file.
+    pub fn load(name: &str) -> Result<Self, DvimError> {
+        if name == DEFAULT {
+            return Ok(Self::default());
+        }
+        let path = Self::path(name)
+            .filter(|_| !name.contains(['/', '\\']))
+            .ok_or_else(|| DvimError::UnknownColorscheme(name.to_string()))?;
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Self::parse(name, &text, &path),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(DvimError::UnknownColorscheme(name.to_string()))
+            }
+            Err(e) => Err(DvimError::FileRead {
+                path: path.display().to_string(),
+                source: e,
+            }),
+        }
+    }
+
+    /// Parse the text of theme file `path` as the theme called `name`.
+    pub fn parse(name: &str, text: &str, path: &Path) -> Result<Self, DvimError> {
+        let error = |message: String| DvimError::Config {
+            path: path.display().to_string(),
+            message,
+        };
+        let table: toml::Table = text
+            .parse()
+            .map_err(|e: toml::de::Error| error(e.message().to_string()))?;
+        let mut theme = Self {
+            name: name.to_string(),
+            ..Self::default()
+        };
+        for (group, value) in table {
+            let highlight = parse_highlight(&group, value).map_err(error)?;
+            theme.groups.insert(group, highlight);
+        }
+        Ok(theme)
+    }
+
+    pub fn get(&self, group: &str) -> Option<&Highlight> {
+        self.groups.get(group)
+    }
+
+    pub fn set(&mut self, group: &str, highlight: Highlight) {
+        self.groups.insert(group.to_string(), highlight);
+    }
+
+    /// Every group's name, sorted.
+    pub fn group_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.groups.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// How `group` looks once links are followed. A group that isn't set
+    /// looks plain.
+    pub fn resolve(&self, group: &str) -> Highlight {
+        let mut group = group;
+        // Links going round in a circle give up rather than hang.
+        for _ in 0..self.groups.len().max(1) {
+            match self.groups.get(group) {
+                Some(Highlight {
+                    link: Some(link), ..
+                }) => group = link,
+                Some(highlight) => return highlight.clone(),
+                None => break,
+            }
+        }
+        Highlight::default()
+    }
+
+    /// The style to draw `group` with.
+    pub fn style(&self, group: &str) -> Style {
+        let highlight = self.resolve(group);
+        let mut style = Style::default().add_modifier(highlight.modifiers);
+        if let Some(fg) = highlight.fg {
+            style = style.fg(self.terminal_color(fg));
+        }
+        if let Some(bg) = highlight.bg {
+            style = style.bg(self.terminal_color(bg));
+        }
+        style
+    }
+
+    fn terminal_color(&self, color: Color) -> Color {
+        match color {
+            Color::Rgb(r, g, b) if !self.truecolor => Color::Indexed(nearest_256(r, g, b)),
+            color => color,
+        }
+    }
+}
+
+/// A group as a theme file gives it: a table of colors and attributes, or
+/// the name of a group to link to.
+fn parse_highlight(group: &str, value: toml::Value) -> Result<Highlight, String> {
+    let table = match value {
+        toml::Value::String(link) => return Ok(Highlight::linked(&link)),
+        toml::Value::Table(table) => table,
+        value => return Err(format!("invalid highlight for '{group}': {value}")),
+    };
+    let mut highlight = Highlight::default();
+    for (key, value) in table {
+        let invalid = || format!("invalid value for '{group}.{key}'");
+        match (key.as_str(), value) {
+            ("fg" | "bg", toml::Value::String(text)) => {
+                let color = parse_color(&text).ok_or_else(invalid)?;
+                if key == "fg" {
+                    highlight.fg = Some(color);
+                } else {
+                    highlight.bg = Some(color);
+                }
+            }
+            ("fg" | "bg", toml::Value::Integer(index)) => {
+                let color = u8::try_from(index)
+                    .map(Color::Indexed)
+                    .map_err(|_| invalid())?;
+                if key == "fg" {
+                    highlight.fg = Some(color);
+                } else {
+                    highlight.bg = Some(color);
+                }
+            }
+            (name, toml::Value::Boolean(on)) => {
+                let &(_, modifier) = ATTRIBUTES
+                    .iter()
+                    .find(|&&(n, _)| n == name)
+                    .ok_or_else(|| format!("unknown key '{group}.{name}'"))?;
+                highlight.modifiers.set(modifier, on);
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(highlight)
+}
+
+/// The xterm 256-color index closest to `#rrggbb`: one of the 6x6x6 color
+/// cube or the 24 grays.
+fn nearest_256(r: u8, g: u8, b: u8) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let level = |c: u8| {
+        (0..6)
+            .min_by_key(|&i| (LEVELS[i] as i32 - c as i32).abs())
+            .unwrap_or(0)
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (LEVELS[ri], LEVELS[gi], LEVELS[bi]);
+
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray_index = ((average.saturating_sub(3)) / 10).min(23) as u8;
+    let gray = 8 + 10 * gray_index;
+
+    let distance = |(cr, cg, cb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(cr, r) + d(cg, g) + d(cb, b)
+    };
+    if distance((gray, gray, gray)) < distance(cube) {
+        232 + gray_index
+    } else {
+        16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Theme, DvimError> {
+        Theme::parse("test", text, Path::new("test.toml"))
+    }
+
+    #[test]
+    fn theme_files_change_groups_and_link() {
+        let theme = parse(
+            "LineNr = { fg = \"#5c6370\", bold = true }\nModeInsert = { bg = 2 }\n\"@keyword\" = \"LineNr\"\n",
+        )
+        .unwrap();
+        let line_nr = theme.resolve("@keyword");
+        assert_eq!(line_nr.fg, Some(Color::Rgb(0x5c, 0x63, 0x70)));
+        assert_eq!(line_nr.modifiers, Modifier::BOLD);
+        assert_eq!(theme.resolve("ModeInsert").bg, Some(Color::Indexed(2)));
+        // Groups the file leaves alone keep their defaults.
+        assert_eq!(theme.resolve("Visual"), Theme::default().resolve("Visual"));
+    }
+
+    #[test]
+    fn theme_file_errors() {
+        assert!(parse("LineNr = { fg = \"nocolor\" }\n").is_err());
+        assert!(parse("LineNr = { blink = true }\n").is_err());
+        assert!(parse("LineNr = 3\n").is_err());
+    }
+
+    #[test]
+    fn rgb_falls_back_to_256_colors() {
+        let mut theme = parse("LineNr = { fg = \"#ff0000\", bg = \"#808080\" }\n").unwrap();
+        theme.truecolor = false;
+        let style = theme.style("LineNr");
+        assert_eq!(style.fg, Some(Color::Indexed(196)));
+        assert_eq!(style.bg, Some(Color::Indexed(244)));
+        theme.truecolor = true;
+        assert_eq!(theme.style("LineNr").fg, Some(Color::Rgb(255, 0, 0)));
+    }
+
+    #[test]
+    fn link_cycles_look_plain() {
+        let theme = parse("A = \"B\"\nB = \"A\"\n").unwrap();
+        assert_eq!(theme.resolve("A"), Highlight::default());
+    }
+
+    #[test]
+    fn describe_and_attributes() {
+        let highlight = Highlight::new(
+            Some(Color::Red),
+            None,
+            parse_attributes("bold,underline").unwrap(),
+        );
+        assert_eq!(highlight.describe(), "fg=Red bold underline");
+        assert_eq!(Highlight::linked("Visual").describe(), "links to Visual");
+        assert!(parse_attributes("blink").is_none());
+    }
+}
@@ -0,0 +1,166 @@
+//! The gutter left of the text: fold column, sign column and line numbers.
+
+use ratatui::text::Span;
+
+use crate::editor::Editor;
+use crate::theme::Theme;
+
+/// The sign column is two cells wide, like vim's.
+const SIGN_WIDTH: usize = 2;
+
+/// How the gutter is laid out for one draw.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gutter {
+    fold: usize,
+    sign: usize,
+    /// The width of the number column, including the space after it.
+    number: usize,
+    absolute: bool,
+    relative: bool,
+    /// Whether the cursor line's number stands out.
+    cursor_nr: bool,
+    cursor_row: usize,
+}
+
+impl Gutter {
+    /// The gutter for `editor`'s window, `height` rows tall.
+    pub fn new(editor: &Editor, height: usize) -> Self {
+        let options = &editor.window_options;
+        let (absolute, relative) = (options.number, options.relativenumber);
+        let number = if absolute || relative {
+            // Relative numbers never get bigger than the window is tall.
+            let largest = if absolute {
+                editor.buffer.line_count()
+            } else {
+                height
+            };
+            (digits(largest) + 1).max(options.numberwidth)
+        } else {
+            0
+        };
+        // There's nothing to put in a sign column yet, so "auto" never
+        // shows one.
+        let sign = if options.signcolumn == "yes" {
+            SIGN_WIDTH
+        } else {
+            0
+        };
+        Self {
+            fold: options.foldcolumn,
+            sign,
+            number,
+            absolute,
+            relative,
+            cursor_nr: options.cursorline || relative,
+            cursor_row: editor.cursor_row,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.fold + self.sign + self.number
+    }
+
+    /// The gutter's spans for buffer line `row`, or for a row past the end
+    /// of the buffer if None.
+    pub fn spans(&self, row: Option<usize>, theme: &Theme) -> Vec<Span<'static>> {
+        let mut spans = Vec::new();
+        if self.fold > 0 {
+            spans.push(Span::styled(
+                " ".repeat(self.fold),
+                theme.style("FoldColumn"),
+            ));
+        }
+        if self.sign > 0 {
+            spans.push(Span::styled(
+                " ".repeat(self.sign),
+                theme.style("SignColumn"),
+            ));
+        }
+        if self.number > 0 {
+            let (text, group) = match row {
+                Some(row) => (
+                    self.number_text(row),
+                    if self.cursor_nr && row == self.cursor_row {
+                        "CursorLineNr"
+                    } else {
+                        "LineNr"
+                    },
+                ),
+                None => (" ".repeat(self.number), "LineNr"),
+            };
+            spans.push(Span::styled(text, theme.style(group)));
+        }
+        spans
+    }
+
+    /// The number column for `row`: its number, or its distance from the
+    /// cursor line, and a space.
+    fn number_text(&self, row: usize) -> String {
+        let width = self.number - 1;
+        if !self.relative {
+            return format!("{:>width$} ", row + 1);
+        }
+        if row == self.cursor_row && self.absolute {
+            // Hybrid numbers: the cursor line's is absolute, on the left.
+            return format!("{:<width$} ", row + 1);
+        }
+        format!("{:>width$} ", row.abs_diff(self.cursor_row))
+    }
+}
+
+fn digits(n: usize) -> usize {
+    n.max(1).ilog10() as usize + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::test_editor;
+
+    fn text(gutter: &Gutter, row: Option<usize>) -> String {
+        let theme = Theme::default();
+        gutter
+            .spans(row, &theme)
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect()
+    }
+
+    #[test]
+    fn absolute_numbers_are_at_least_numberwidth() {
+        let mut ed = test_editor("a\nb\nc\n");
+        ed.cursor_row = 1;
+        let gutter = Gutter::new(&ed, 10);
+        assert_eq!(gutter.width(), 4);
+        assert_eq!(text(&gutter, Some(0)), "  1 ");
+        assert_eq!(text(&gutter, None), "    ");
+        ed.window_options.numberwidth = 1;
+        assert_eq!(Gutter::new(&ed, 10).width(), 2);
+        ed.window_options.number = false;
+        assert_eq!(Gutter::new(&ed, 10).width(), 0);
+    }
+
+    #[test]
+    fn relative_and_hybrid_numbers() {
+        let mut ed = test_editor("a\nb\nc\nd\n");
+        ed.cursor_row = 2;
+        ed.window_options.relativenumber = true;
+        let hybrid = Gutter::new(&ed, 10);
+        assert_eq!(text(&hybrid, Some(0)), "  2 ");
+        assert_eq!(text(&hybrid, Some(2)), "3   ");
+        assert_eq!(text(&hybrid, Some(3)), "  1 ");
+        ed.window_options.number = false;
+        let relative = Gutter::new(&ed, 10);
+        assert_eq!(text(&relative, Some(2)), "  0 ");
+    }
+
+    #[test]
+    fn sign_and_fold_columns_are_reserved() {
+        let mut ed = test_editor("a\n");
+        ed.window_options.signcolumn = "yes".to_string();
+        ed.window_options.foldcolumn = 1;
+        let gutter = Gutter::new(&ed, 10);
+        assert_eq!(gutter.width(), 7);
+        assert_eq!(text(&gutter, Some(0)), "     1 ");
+    }
+}
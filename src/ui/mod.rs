@@ -1,11 +1,14 @@
+mod gutter;
+
 use ratatui::Frame;
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
-use ratatui::style::{Color, Modifier, Style};
+use ratatui::style::Style;
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Paragraph};
+use ratatui::widgets::{Block, Clear, Paragraph};
 
-use crate::editor::Editor;
+use crate::editor::{Editor, TextRange, cell_width};
 use crate::mode::Mode;
+use gutter::Gutter;
 
 /// Render the editor state to the terminal.
 pub fn draw(frame: &mut Frame, editor: &Editor) {
@@ -22,56 +25,213 @@ pub fn draw(frame: &mut Frame, editor: &Editor) {
 
     draw_text_area(frame, editor, chunks[0]);
     draw_status_bar(frame, editor, chunks[1]);
+    if let Some(message) = &editor.status_message
+        && message.contains('\n')
+    {
+        draw_message(frame, message, chunks[0]);
+    }
 }
 
-/// The width of the line number gutter, including the trailing space.
-fn gutter_width(line_count: usize) -> u16 {
-    let digits = if line_count == 0 {
-        1
-    } else {
-        (line_count as f64).log10().floor() as u16 + 1
+/// Show a multi-line message (`:marks`, `:jumps`) over the bottom of the
+/// text area until the next keypress.
+fn draw_message(frame: &mut Frame, message: &str, area: Rect) {
+    let lines: Vec<Line> = message.lines().map(|l| Line::raw(l.to_string())).collect();
+    let height = (lines.len() as u16).min(area.height);
+    let rect = Rect {
+        y: area.y + area.height - height,
+        height,
+        ..area
     };
-    digits + 1 // one space of padding after the number
+    frame.render_widget(Clear, rect);
+    frame.render_widget(Paragraph::new(lines), rect);
 }
 
 fn draw_text_area(frame: &mut Frame, editor: &Editor, area: Rect) {
     let viewport_height = area.height as usize;
-    let gutter_w = gutter_width(editor.buffer.line_count());
+    let gutter = Gutter::new(editor, viewport_height);
+    let gutter_w = gutter.width();
+    let text_width = text_width(editor, area.width, viewport_height);
+    let theme = &editor.theme;
+    let window = &editor.window_options;
+    let bracket_pair = editor.bracket_pair_at_cursor(viewport_height);
+    let selection = editor.visual_range();
+    let color_columns = window.color_columns();
+
+    let (cursor_index, cursor_x) = editor.cursor_display_pos();
+    let mut cursor_y = None;
 
     let mut lines: Vec<Line> = Vec::with_capacity(viewport_height);
 
-    for i in 0..viewport_height {
-        let file_line = editor.scroll_offset + i;
-        if let Some(content) = editor.buffer.line(file_line) {
-            let line_num = format!(
-                "{:>width$} ",
-                file_line + 1,
-                width = (gutter_w - 1) as usize
-            );
-            let spans = vec![
-                Span::styled(line_num, Style::default().fg(Color::DarkGray)),
-                Span::raw(content),
-            ];
-            lines.push(Line::from(spans));
-        } else {
+    let mut file_line = editor.scroll_offset;
+    while lines.len() < viewport_height {
+        let Some(content) = editor.buffer.line(file_line) else {
             // Vim shows '~' for lines past end of file
-            let padding = " ".repeat((gutter_w - 1) as usize);
-            lines.push(Line::from(vec![
-                Span::styled(format!("{padding} "), Style::default().fg(Color::DarkGray)),
-                Span::styled("-", Style::default().fg(Color::DarkGray)),
-            ]));
+            let mut spans = gutter.spans(None, theme);
+            spans.push(Span::styled("-", theme.style("NonText")));
+            lines.push(Line::from(spans));
+            continue;
+        };
+        let chars: Vec<char> = content.chars().collect();
+        let match_cols: Vec<usize> = bracket_pair
+            .iter()
+            .flatten()
+            .filter(|&&(row, _)| row == file_line)
+            .map(|&(_, col)| col)
+            .collect();
+        let selected = selection.and_then(|range| selected_cols(&range, file_line));
+        let cursor_line = window.cursorline && file_line == editor.cursor_row;
+        if file_line == editor.cursor_row {
+            cursor_y = Some(lines.len() + cursor_index);
         }
+
+        for (index, row) in editor.display_rows(file_line).into_iter().enumerate() {
+            if lines.len() == viewport_height {
+                break;
+            }
+            // Only the first screen row of a line gets its number.
+            let mut spans = gutter.spans((index == 0).then_some(file_line), theme);
+            let prefix = editor.display_prefix_width(&row);
+            if row.indent > 0 {
+                let style = if cursor_line {
+                    theme.style("CursorLine")
+                } else {
+                    Style::default()
+                };
+                spans.push(Span::styled(" ".repeat(row.indent), style));
+            }
+            if row.showbreak {
+                let showbreak = editor.global_options.showbreak.clone();
+                spans.push(Span::styled(showbreak, theme.style("NonText")));
+            }
+            // Each character to draw, with its offset into the row.
+            let mut text: Vec<(char, usize)> =
+                chars[row.start..row.end].iter().copied().zip(0..).collect();
+            let len = text.len();
+            // Without 'wrap', '<' and '>' mark text cut off at either side,
+            // padded out to the width of the character they cover.
+            let precedes = !window.wrap && row.start > 0;
+            let extends = !window.wrap && row.end < chars.len();
+            let mark = |text: &mut Vec<(char, usize)>, at: usize, marker: char| {
+                let (c, offset) = text[at];
+                let padding = std::iter::repeat_n((' ', offset), cell_width(c).saturating_sub(1));
+                text.splice(at..=at, std::iter::once((marker, offset)).chain(padding));
+            };
+            if extends && len > 0 {
+                mark(&mut text, len - 1, '>');
+            }
+            if precedes && len == 0 {
+                text.push(('<', 0));
+            } else if precedes && (len > 1 || !extends) {
+                mark(&mut text, 0, '<');
+            }
+            let cut_off =
+                |offset: usize| (precedes && offset == 0) || (extends && offset + 1 == len);
+            // Highlights that go past the end of the text need the rest of
+            // the row filled in to show.
+            if cursor_line || window.cursorcolumn || !color_columns.is_empty() {
+                let width = prefix + text.iter().map(|&(c, _)| cell_width(c)).sum::<usize>();
+                let fill = text.last().map_or(0, |&(_, offset)| offset + 1);
+                text.extend(
+                    (fill..)
+                        .map(|offset| (' ', offset))
+                        .take(text_width.saturating_sub(width)),
+                );
+            }
+            // The screen column each character starts at.
+            let xs: Vec<usize> = text
+                .iter()
+                .scan(prefix, |x, &(c, _)| {
+                    let at = *x;
+                    *x += cell_width(c);
+                    Some(at)
+                })
+                .collect();
+            let content = text.iter().map(|&(c, _)| c).collect();
+            spans.extend(styled_content(content, |index| {
+                let offset = text[index].1;
+                let col = row.start + offset;
+                let x = xs[index];
+                let layers = [
+                    (cursor_line, "CursorLine"),
+                    (color_columns.contains(&x), "ColorColumn"),
+                    (window.cursorcolumn && x == cursor_x, "CursorColumn"),
+                    (
+                        selected.as_ref().is_some_and(|cols| cols.contains(&col)),
+                        "Visual",
+                    ),
+                    (match_cols.contains(&col), "MatchParen"),
+                    (cut_off(offset), "NonText"),
+                ];
+                layers
+                    .into_iter()
+                    .filter(|&(on, _)| on)
+                    .map(|(_, group)| theme.style(group))
+                    .reduce(Style::patch)
+            }));
+            lines.push(Line::from(spans));
+        }
+        file_line += 1;
     }
 
     let paragraph = Paragraph::new(lines).block(Block::default());
     frame.render_widget(paragraph, area);
 
     // In command mode the cursor is on the status bar, not the text area.
-    if editor.mode != Mode::Command {
-        let cursor_x = area.x + gutter_w + editor.cursor_col as u16;
-        let cursor_y = area.y + (editor.cursor_row - editor.scroll_offset) as u16;
-        frame.set_cursor_position((cursor_x, cursor_y));
+    if editor.mode != Mode::Command
+        && let Some(cursor_y) = cursor_y.filter(|&y| y < viewport_height)
+    {
+        let cursor_x = area.x + (gutter_w + cursor_x) as u16;
+        frame.set_cursor_position((cursor_x, area.y + cursor_y as u16));
+    }
+}
+
+/// The width left for text once the gutter is drawn, in a window `width`
+/// cells wide and `height` rows tall.
+pub fn text_width(editor: &Editor, width: u16, height: usize) -> usize {
+    (width as usize).saturating_sub(Gutter::new(editor, height).width())
+}
+
+/// The columns of `row` covered by a visual selection.
+fn selected_cols(range: &TextRange, row: usize) -> Option<std::ops::Range<usize>> {
+    if row < range.start.0 || row > range.end.0 {
+        return None;
+    }
+    if range.linewise {
+        return Some(0..usize::MAX);
+    }
+    let from = if row == range.start.0 {
+        range.start.1
+    } else {
+        0
+    };
+    let to = if row == range.end.0 {
+        range.end.1
+    } else {
+        usize::MAX
+    };
+    Some(from..to)
+}
+
+/// Split `content` into spans, styling each character with `style_at` its
+/// column, or leaving it plain.
+fn styled_content(
+    content: String,
+    style_at: impl Fn(usize) -> Option<Style>,
+) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_style = None;
+    for (col, c) in content.chars().enumerate() {
+        let style = style_at(col);
+        if style != run_style && !run.is_empty() {
+            let text = std::mem::take(&mut run);
+            spans.push(Span::styled(text, run_style.unwrap_or_default()));
+        }
+        run_style = style;
+        run.push(c);
     }
+    spans.push(Span::styled(run, run_style.unwrap_or_default()));
+    spans
 }
 
 fn draw_status_bar(frame: &mut Frame, editor: &Editor, area: Rect) {
@@ -80,7 +240,7 @@ fn draw_status_bar(frame: &mut Frame, editor: &Editor, area: Rect) {
         let padding = " ".repeat((area.width as usize).saturating_sub(cmd_text.len()));
         let status_line = Line::from(Span::styled(
             format!("{cmd_text}{padding}"),
-            Style::default().bg(Color::DarkGray).fg(Color::White),
+            editor.theme.style("ModeCommand"),
         ));
         let paragraph = Paragraph::new(status_line);
         frame.render_widget(paragraph, area);
@@ -99,27 +259,76 @@ fn draw_status_bar(frame: &mut Frame, editor: &Editor, area: Rect) {
 
     let position = format!("{}:{}", editor.cursor_row + 1, editor.cursor_col + 1);
 
-    let mode_str = format!(" {} ", editor.mode);
-    let status = format!(" {filename}");
+    // A command typed after `Ctrl-o` in insert mode.
+    let mode_str = if editor.insert_normal {
+        " (INSERT) ".to_string()
+    } else {
+        format!(" {} ", editor.mode)
+    };
+    let mode_group = match editor.mode {
+        _ if editor.insert_normal => "ModeInsert",
+        Mode::Normal => "ModeNormal",
+        Mode::Insert => "ModeInsert",
+        Mode::Replace => "ModeReplace",
+        Mode::Command => "ModeCommand",
+        Mode::Visual | Mode::VisualLine => "ModeVisual",
+    };
+    let mut status = match &editor.status_message {
+        Some(msg) if !msg.contains('\n') => format!(" {msg}"),
+        Some(_) => " Press any key to continue".to_string(),
+        None if editor.buffer.is_modified() => format!(" {filename} [+]"),
+        None => format!(" {filename}"),
+    };
+    if let Some(register) = editor.macros.recording {
+        status.push_str(&format!("  recording @{register}"));
+    }
+    // The keys of a partly typed command go just left of the position.
+    let showcmd = if editor.global_options.showcmd {
+        format!("{:<12}", editor.showcmd())
+    } else {
+        String::new()
+    };
     // Right-align position info
-    let spacing_len =
-        (area.width as usize).saturating_sub(mode_str.len() + status.len() + position.len() + 1);
+    let spacing_len = (area.width as usize)
+        .saturating_sub(mode_str.len() + status.len() + showcmd.len() + position.len() + 1);
     let spacing = " ".repeat(spacing_len);
 
     let status_line = Line::from(vec![
+        Span::styled(mode_str, editor.theme.style(mode_group)),
         Span::styled(
-            mode_str,
-            Style::default()
-                .bg(Color::Blue)
-                .fg(Color::Black)
-                .add_modifier(Modifier::BOLD),
-        ),
-        Span::styled(
-            format!("{status}{spacing}{position} "),
-            Style::default().bg(Color::DarkGray).fg(Color::White),
+            format!("{status}{spacing}{showcmd}{position} "),
+            editor.theme.style("StatusLine"),
         ),
     ]);
 
     let paragraph = Paragraph::new(status_line);
     frame.render_widget(paragraph, area);
 }
+
+#[cfg(test)]
+mod tests {
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+    use crate::editor::test_editor;
+
+    /// The first screen row as drawn in a terminal `width` cells wide.
+    fn first_row(editor: &Editor, width: u16) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(width, 3)).unwrap();
+        terminal.draw(|frame| draw(frame, editor)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..width).map(|x| buffer[(x, 0)].symbol()).collect()
+    }
+
+    #[test]
+    fn cut_off_markers_cover_wide_characters() {
+        let mut ed = test_editor("ab中cdef\n");
+        ed.window_options.number = false;
+        ed.window_options.wrap = false;
+        ed.text_width = 4;
+        assert_eq!(first_row(&ed, 4), "ab> ");
+        ed.left_col = 2;
+        assert_eq!(first_row(&ed, 4), "< c>");
+    }
+}
@@ -0,0 +1,42 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Watches the open file for changes made by other programs.
+///
+/// The parent directory is watched rather than the file itself: formatters
+/// and `git checkout` often replace a file by renaming a new one over it,
+/// which would silently end a watch on the original inode.
+pub struct FileWatcher {
+    path: PathBuf,
+    events: Receiver<notify::Result<Event>>,
+    // Dropping the watcher stops it, so it has to live as long as we do.
+    _watcher: RecommendedWatcher,
+}
+
+impl FileWatcher {
+    pub fn new(path: &Path) -> notify::Result<Self> {
+        let path = std::path::absolute(path)?;
+        let dir = path.parent().unwrap_or(Path::new("/")).to_path_buf();
+        let (tx, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        Ok(Self {
+            path,
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    /// Drain pending notifications, returning true if any touched our file.
+    pub fn has_changed(&self) -> bool {
+        let mut changed = false;
+        while let Ok(event) = self.events.try_recv() {
+            if let Ok(event) = event {
+                changed |= event.paths.iter().any(|p| p == &self.path);
+            }
+        }
+        changed
+    }
+}